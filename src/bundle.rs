@@ -0,0 +1,724 @@
+// bundles are entries packed up so they can be handed to someone else and imported into their
+// rager: a zstd-compressed tarball with `manifest.json` first, and then each entry's files under its
+// `day/time`. The manifest has what we could tell from each entry's details, which server it came
+// from, and a checksum of every file, so nothing that's corrupted along the way gets imported.
+// Bundles exported with a time range only hold the lines of each log within it.
+
+use crate::{
+	config::Config,
+	entry::{Details, Entry},
+	err,
+	filter::Filter,
+	search::entries_with_filter,
+	sync_dir,
+	timestamp::{TimeRange, TimeSlice},
+	warn,
+};
+use sha2::{Digest, Sha256};
+use std::{
+	error::Error,
+	fs,
+	io::{self, Read, Write},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+const MANIFEST: &str = "manifest.json";
+// bumped whenever the manifest changes in a way that older versions of rager can't read
+const MANIFEST_VERSION: u64 = 1;
+
+// writes the entries at `day_times` (or, if there aren't any, every entry that matches `filter`)
+// to a bundle at `output`, which is only replaced if it's already there when `overwrite` is set.
+// Logs are cut down to `filter.range`, and entries with nothing logged within it are left out
+pub async fn export(
+	day_times: &[String],
+	filter: Filter,
+	config: Arc<Config>,
+	output: &Path,
+	overwrite: bool,
+) -> Result<(), Box<dyn Error>> {
+	if output.to_string_lossy().ends_with(".gz") {
+		return Err("Bundles are compressed with zstd, so please give them a name that ends in .tar.zst instead".into());
+	}
+
+	let range = filter.range.clone();
+
+	let mut entries = if day_times.is_empty() {
+		let Some(entries) = entries_with_filter(&Arc::new(filter), &config).await else {
+			return Err("Couldn't look through the entries on your device".into());
+		};
+
+		// there's no point sharing entries that we only have the details of
+		entries
+			.into_iter()
+			.filter(|e| {
+				let has_logs = e.has_logs();
+				if !has_logs {
+					warn!(
+						"Only the details of {} are on your device, so it won't be exported; view or sync it first",
+						e.date_time()
+					);
+				}
+				has_logs
+			})
+			.collect()
+	} else {
+		let mut entries = Vec::new();
+
+		for day_time in day_times {
+			let Some((day, time)) = day_time.split_once('/') else {
+				return Err(format!("{day_time} isn't an entry").into());
+			};
+
+			let mut entry = Entry::new(day, time, config.clone());
+			entry.ensure_all_files_downloaded().await?;
+			entries.push(entry);
+		}
+
+		entries
+	};
+
+	if entries.is_empty() {
+		return Err("There are no entries to export".into());
+	}
+
+	entries.sort_by_key(Entry::date_time);
+	entries.dedup_by_key(|e| e.date_time());
+
+	let mut manifest_entries = Vec::new();
+	let mut files = Vec::new();
+
+	for entry in &entries {
+		let dir = sync_dir().join(entry.date_time());
+
+		let details = fs::read_to_string(entry.details_file())
+			.map(|contents| Details::parse(&contents))
+			.unwrap_or_default();
+
+		let mut names = fs::read_dir(&dir)?
+			.filter_map(Result::ok)
+			.filter(|f| f.path().is_file())
+			.map(|f| f.file_name().to_string_lossy().to_string())
+			.collect::<Vec<_>>();
+		names.sort();
+
+		let date = chrono::NaiveDate::parse_from_str(&entry.day, "%Y-%m-%d").ok();
+		let mut entry_files = Vec::new();
+		let mut entry_contents = Vec::new();
+		let mut in_range = false;
+
+		for name in names {
+			let path = dir.join(&name);
+
+			// the details aren't logs, and anything that isn't text can't be sliced
+			let contents = match (date, fs::read_to_string(&path)) {
+				(Some(date), Ok(text)) if !range.is_empty() && name != crate::DETAILS => {
+					let sliced = slice(&text, &range, date);
+					in_range |= !sliced.is_empty();
+					Contents::Sliced(sliced.into_bytes())
+				}
+				_ => Contents::Whole(path),
+			};
+
+			let mut checksummed = Checksummed::new(io::sink());
+			match &contents {
+				Contents::Whole(path) => io::copy(&mut fs::File::open(path)?, &mut checksummed)?,
+				Contents::Sliced(data) => io::copy(&mut data.as_slice(), &mut checksummed)?,
+			};
+
+			entry_files.push(serde_json::json!({
+				"name": name,
+				"size": checksummed.size,
+				"sha256": checksummed.checksum(),
+			}));
+			entry_contents.push((format!("{}/{name}", entry.date_time()), contents));
+		}
+
+		if !range.is_empty() && !in_range {
+			warn!(
+				"Nothing in {} was logged within the time range, so it won't be exported",
+				entry.date_time()
+			);
+			continue;
+		}
+
+		files.extend(entry_contents);
+		manifest_entries.push(serde_json::json!({
+			"entry": entry.date_time(),
+			"reason": details.reason,
+			"user_id": details.user_id,
+			"os": details.os.map(|os| os.to_string().to_lowercase()),
+			"app_version": details.version,
+			"labels": details.labels,
+			"files": entry_files,
+		}));
+	}
+
+	if manifest_entries.is_empty() {
+		return Err("None of the entries have anything logged within the time range".into());
+	}
+
+	let manifest = serde_json::to_vec_pretty(&serde_json::json!({
+		"version": MANIFEST_VERSION,
+		"server": config.server,
+		"exported": chrono::Utc::now().to_rfc3339(),
+		"entries": manifest_entries,
+	}))?;
+
+	// so a bundle that's already there (like one exported earlier today) isn't lost by accident
+	let file = fs::OpenOptions::new()
+		.write(true)
+		.create_new(!overwrite)
+		.create(overwrite)
+		.truncate(overwrite)
+		.open(output)
+		.map_err(|err| -> Box<dyn Error> {
+			if err.kind() == io::ErrorKind::AlreadyExists {
+				format!(
+					"{} already exists; pass --overwrite to replace it",
+					output.display()
+				)
+				.into()
+			} else {
+				err.into()
+			}
+		})?;
+
+	let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+	append_data(&mut tar, MANIFEST, &manifest)?;
+
+	for (path, contents) in files {
+		match contents {
+			Contents::Whole(file) => tar.append_file(path, &mut fs::File::open(file)?)?,
+			Contents::Sliced(data) => append_data(&mut tar, &path, &data)?,
+		}
+	}
+
+	tar.into_inner()?.finish()?;
+
+	println!(
+		"✨ Exported {} entries to {}",
+		manifest_entries.len(),
+		output.display()
+	);
+
+	Ok(())
+}
+
+// unpacks the bundle at `path` into the entries on the device. Entries that are already here are
+// skipped, unless `overwrite` is set, and entries with files that don't match their checksums
+// aren't imported at all
+pub fn import(path: &Path, overwrite: bool, config: &Arc<Config>) -> Result<(), Box<dyn Error>> {
+	let file = fs::File::open(path)?;
+	let mut tar = tar::Archive::new(zstd::Decoder::new(file)?);
+	let mut files = tar.entries()?;
+
+	let not_bundle = || format!("{} isn't a bundle made by `rager export`", path.display());
+
+	let manifest = match files.next().transpose()? {
+		Some(mut file) if file.path_bytes().as_ref() == MANIFEST.as_bytes() => {
+			let mut text = Vec::new();
+			file.read_to_end(&mut text)?;
+			serde_json::from_slice::<serde_json::Value>(&text)?
+		}
+		_ => return Err(not_bundle().into()),
+	};
+
+	match manifest["version"].as_u64() {
+		Some(version) if version <= MANIFEST_VERSION => (),
+		Some(_) => {
+			return Err(
+				"This bundle was made by a newer version of rager; please update to import it"
+					.into(),
+			)
+		}
+		None => return Err(not_bundle().into()),
+	}
+
+	let server = manifest["server"].as_str().unwrap_or("an unknown server");
+	if server != config.server {
+		warn!("These entries came from {server}, not {}", config.server);
+	}
+
+	let entry_regex = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}/\d{6}$")?;
+
+	// every entry in the bundle, with the name, size and checksum of each of its files
+	let mut entries: Vec<BundledEntry> = Vec::new();
+
+	for entry in manifest["entries"].as_array().into_iter().flatten() {
+		let Some(date_time) = entry["entry"].as_str().filter(|e| entry_regex.is_match(e)) else {
+			return Err(not_bundle().into());
+		};
+
+		let mut files = Vec::new();
+
+		for file in entry["files"].as_array().into_iter().flatten() {
+			match (
+				file["name"].as_str(),
+				file["size"].as_u64(),
+				file["sha256"].as_str(),
+			) {
+				// the names can't be allowed to go anywhere but the entry's directory
+				(Some(name), Some(size), Some(sha256))
+					if !name.is_empty()
+						&& name != ".." && name != "."
+						&& !name.contains(['/', '\\']) =>
+				{
+					files.push((name.to_owned(), size, sha256.to_owned()));
+				}
+				_ => return Err(not_bundle().into()),
+			}
+		}
+
+		let (day, time) = date_time.split_at(10);
+		let skip = !overwrite && Entry::new(day, &time[1..], config.clone()).has_logs();
+
+		entries.push(BundledEntry {
+			date_time: date_time.to_owned(),
+			files,
+			skip,
+			failed: false,
+			received: Vec::new(),
+		});
+	}
+
+	// everything is unpacked next to the entries first, and only moved into place once all the
+	// files in the entry have been checked
+	let store = sync_dir();
+	fs::create_dir_all(&store)?;
+	let staging = tempfile::Builder::new()
+		.prefix(".rager-import")
+		.tempdir_in(&store)?;
+
+	for file in files {
+		let mut file = file?;
+
+		// bundles are only ever regular files
+		if !file.header().entry_type().is_file() {
+			continue;
+		}
+
+		let name = String::from_utf8_lossy(&file.path_bytes()).to_string();
+		let Some((date_time, file_name)) = name.rsplit_once('/') else {
+			return Err(not_bundle().into());
+		};
+
+		let Some(entry) = entries.iter_mut().find(|e| e.date_time == date_time) else {
+			return Err(format!("{name} is in the bundle, but not in its manifest").into());
+		};
+
+		let Some((_, expected_size, expected_sha256)) =
+			entry.files.iter().find(|(n, _, _)| n == file_name)
+		else {
+			return Err(format!("{name} is in the bundle, but not in its manifest").into());
+		};
+
+		// the rest of the file is skipped over when we move on to the next one
+		if entry.skip || entry.failed {
+			continue;
+		}
+
+		let dir = staging.path().join(date_time);
+		fs::create_dir_all(&dir)?;
+
+		let mut checksummed = Checksummed::new(fs::File::create(dir.join(file_name))?);
+		io::copy(&mut file, &mut checksummed)?;
+
+		if checksummed.size != *expected_size || checksummed.checksum() != *expected_sha256 {
+			err!("{name} doesn't match its checksum, so {date_time} won't be imported");
+			entry.failed = true;
+		} else {
+			entry.received.push(file_name.to_owned());
+		}
+	}
+
+	let mut imported = 0;
+
+	for entry in &mut entries {
+		if entry.skip || entry.failed {
+			continue;
+		}
+
+		if let Some((missing, _, _)) = entry
+			.files
+			.iter()
+			.find(|(n, _, _)| !entry.received.contains(n))
+		{
+			err!(
+				"{missing} is missing from the bundle, so {} won't be imported",
+				entry.date_time
+			);
+			entry.failed = true;
+			continue;
+		}
+
+		let target = store.join(&entry.date_time);
+
+		if target.exists() {
+			fs::remove_dir_all(&target)?;
+		}
+
+		if let Some(day_dir) = target.parent() {
+			fs::create_dir_all(day_dir)?;
+		}
+
+		// an entry with no files wouldn't have been unpacked at all
+		let unpacked = staging.path().join(&entry.date_time);
+		if unpacked.exists() {
+			fs::rename(unpacked, &target)?;
+		} else {
+			fs::create_dir_all(&target)?;
+		}
+
+		println!("Imported {}", entry.date_time);
+		imported += 1;
+	}
+
+	let skipped = entries.iter().filter(|e| e.skip).count();
+	let failed = entries.iter().filter(|e| e.failed).count();
+
+	println!(
+		"✨ Imported {imported} of the {} entries in the bundle",
+		entries.len()
+	);
+
+	if skipped > 0 {
+		println!("{skipped} were skipped because they're already on your device; pass --overwrite to replace them");
+	}
+
+	if failed > 0 {
+		return Err(format!("{failed} couldn't be imported").into());
+	}
+
+	Ok(())
+}
+
+// what goes in the bundle for each file of an entry
+enum Contents {
+	Whole(PathBuf),
+	Sliced(Vec<u8>),
+}
+
+// the lines of `text` that were logged within `range`, from an entry on `date`
+fn slice(text: &str, range: &TimeRange, date: chrono::NaiveDate) -> String {
+	let mut slice = TimeSlice::new(range, date);
+	text.lines()
+		.filter(|l| slice.keep(l))
+		.map(|l| format!("{l}\n"))
+		.collect()
+}
+
+fn append_data<W: Write>(tar: &mut tar::Builder<W>, path: &str, data: &[u8]) -> io::Result<()> {
+	let mut header = tar::Header::new_gnu();
+	header.set_size(data.len() as u64);
+	header.set_mode(0o644);
+	header.set_mtime(
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |d| d.as_secs()),
+	);
+	header.set_cksum();
+	tar.append_data(&mut header, path, data)
+}
+
+struct BundledEntry {
+	date_time: String,
+	// the name, size, and checksum of each file
+	files: Vec<(String, u64, String)>,
+	skip: bool,
+	failed: bool,
+	// the files that have been unpacked and matched their checksums
+	received: Vec<String>,
+}
+
+// passes everything through to `inner`, keeping track of how much there was and its checksum
+struct Checksummed<W: Write> {
+	inner: W,
+	hasher: Sha256,
+	size: u64,
+}
+
+impl<W: Write> Checksummed<W> {
+	fn new(inner: W) -> Self {
+		Checksummed {
+			inner,
+			hasher: Sha256::new(),
+			size: 0,
+		}
+	}
+
+	fn checksum(&self) -> String {
+		format!("{:x}", self.hasher.clone().finalize())
+	}
+}
+
+impl<W: Write> Write for Checksummed<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.inner.write(buf)?;
+		self.hasher.update(&buf[..written]);
+		self.size += written as u64;
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	const DETAILS: &str =
+		"crashed\nApplication: element-ios\nLabels: crash\nuser_id: @bob:example.org\nVersion: 1.8.2\n";
+
+	fn add_entries(env: &TestEnv) {
+		env.add_local_file(&format!("2021-07-08/161300/{}", crate::DETAILS), DETAILS);
+		env.add_local_file("2021-07-08/161300/logs.log", "opening room\nPANIC\n");
+		env.add_local_file(&format!("2021-07-09/010203/{}", crate::DETAILS), "slow\n");
+		env.add_local_file("2021-07-09/010203/console.log", "scrolling\n");
+	}
+
+	fn read_bundle(path: &Path) -> Vec<(String, Vec<u8>)> {
+		let decoder = zstd::Decoder::new(fs::File::open(path).unwrap()).unwrap();
+		let mut tar = tar::Archive::new(decoder);
+
+		tar.entries()
+			.unwrap()
+			.map(|file| {
+				let mut file = file.unwrap();
+				let name = String::from_utf8_lossy(&file.path_bytes()).to_string();
+				let mut data = Vec::new();
+				file.read_to_end(&mut data).unwrap();
+				(name, data)
+			})
+			.collect()
+	}
+
+	fn sha256(data: &[u8]) -> String {
+		format!("{:x}", Sha256::digest(data))
+	}
+
+	#[tokio::test]
+	async fn exports_entries_with_manifest() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		add_entries(&env);
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+
+		export(
+			&["2021-07-08/161300".to_owned()],
+			Filter::default(),
+			env.config.clone(),
+			&bundle,
+			false,
+		)
+		.await
+		.unwrap();
+
+		let files = read_bundle(&bundle);
+		assert_eq!(
+			files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+			[
+				MANIFEST,
+				&format!("2021-07-08/161300/{}", crate::DETAILS),
+				"2021-07-08/161300/logs.log"
+			]
+		);
+
+		let manifest = serde_json::from_slice::<serde_json::Value>(&files[0].1).unwrap();
+		assert_eq!(manifest["server"], server.url);
+
+		let entry = &manifest["entries"][0];
+		assert_eq!(entry["entry"], "2021-07-08/161300");
+		assert_eq!(entry["user_id"], "@bob:example.org");
+		assert_eq!(entry["os"], "ios");
+		assert_eq!(entry["labels"], serde_json::json!(["crash"]));
+		assert_eq!(entry["files"][1]["name"], "logs.log");
+		assert_eq!(entry["files"][1]["size"], 19);
+		assert_eq!(
+			entry["files"][1]["sha256"],
+			sha256(b"opening room\nPANIC\n")
+		);
+	}
+
+	#[tokio::test]
+	async fn exports_every_entry_matching_filter() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		add_entries(&env);
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+
+		let filter = Filter {
+			when: Some(vec![[2021, 7, 9]]),
+			..Filter::default()
+		};
+		export(&[], filter, env.config.clone(), &bundle, false)
+			.await
+			.unwrap();
+
+		let names = read_bundle(&bundle)
+			.into_iter()
+			.map(|(n, _)| n)
+			.collect::<Vec<_>>();
+		assert!(names.contains(&"2021-07-09/010203/console.log".to_owned()));
+		assert!(!names.iter().any(|n| n.starts_with("2021-07-08")));
+	}
+
+	#[tokio::test]
+	async fn exports_only_what_was_logged_within_range() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		add_entries(&env);
+		env.add_local_file(
+			"2021-07-08/161300/logs.log",
+			"2021-07-08T14:03:59Z INFO a\n2021-07-08T14:05:00Z ERROR b\n\tat stack.trace\n2021-07-08T14:20:00Z INFO c\n",
+		);
+		env.add_local_file(
+			"2021-07-09/010203/console.log",
+			"2021-07-09T01:02:03Z INFO scrolling\n",
+		);
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+
+		let filter = Filter {
+			range: TimeRange::new(Some("14:04"), Some("14:10"), None, None, false).unwrap(),
+			..Filter::default()
+		};
+		export(&[], filter, env.config.clone(), &bundle, false)
+			.await
+			.unwrap();
+
+		let files = read_bundle(&bundle);
+		assert!(!files.iter().any(|(n, _)| n.starts_with("2021-07-09")));
+
+		let sliced = b"2021-07-08T14:05:00Z ERROR b\n\tat stack.trace\n";
+		assert!(files.contains(&("2021-07-08/161300/logs.log".to_owned(), sliced.to_vec())));
+
+		// the checksums are of what's in the bundle, so it still imports
+		fs::remove_dir_all(env.store_path("2021-07-08")).unwrap();
+		import(&bundle, false, &env.config).unwrap();
+		assert_eq!(
+			fs::read(env.store_path("2021-07-08/161300/logs.log")).unwrap(),
+			sliced
+		);
+	}
+
+	#[tokio::test]
+	async fn only_replaces_bundles_when_asked() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		add_entries(&env);
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+		fs::write(&bundle, "an earlier bundle").unwrap();
+
+		let err = export(&[], Filter::default(), env.config.clone(), &bundle, false)
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("--overwrite"), "{err}");
+		assert_eq!(fs::read_to_string(&bundle).unwrap(), "an earlier bundle");
+
+		export(&[], Filter::default(), env.config.clone(), &bundle, true)
+			.await
+			.unwrap();
+		assert_eq!(read_bundle(&bundle)[0].0, MANIFEST);
+	}
+
+	#[tokio::test]
+	async fn imports_exported_entries() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		add_entries(&env);
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+
+		export(
+			&[
+				"2021-07-08/161300".to_owned(),
+				"2021-07-09/010203".to_owned(),
+			],
+			Filter::default(),
+			env.config.clone(),
+			&bundle,
+			false,
+		)
+		.await
+		.unwrap();
+
+		fs::remove_dir_all(env.store_path("2021-07-08")).unwrap();
+		fs::write(env.store_path("2021-07-09/010203/console.log"), "changed\n").unwrap();
+
+		// entries that are already here are left alone
+		import(&bundle, false, &env.config).unwrap();
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-08/161300/logs.log")).unwrap(),
+			"opening room\nPANIC\n"
+		);
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-09/010203/console.log")).unwrap(),
+			"changed\n"
+		);
+
+		// unless they're asked to be overwritten
+		import(&bundle, true, &env.config).unwrap();
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-09/010203/console.log")).unwrap(),
+			"scrolling\n"
+		);
+
+		// and nothing's left over from unpacking
+		assert!(!fs::read_dir(env.store_path("")).unwrap().any(|d| d
+			.unwrap()
+			.file_name()
+			.to_string_lossy()
+			.starts_with('.')));
+	}
+
+	#[tokio::test]
+	async fn refuses_corrupted_entries() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		let manifest = serde_json::json!({
+			"version": MANIFEST_VERSION,
+			"server": server.url,
+			"entries": [
+				{ "entry": "2021-07-08/161300", "files": [{ "name": "logs.log", "size": 3, "sha256": sha256(b"ok\n") }] },
+				{ "entry": "2021-07-09/010203", "files": [{ "name": "logs.log", "size": 3, "sha256": sha256(b"ok\n") }] },
+			],
+		})
+		.to_string();
+
+		let out = tempfile::tempdir().unwrap();
+		let bundle = out.path().join("bundle.tar.zst");
+
+		let mut tar =
+			tar::Builder::new(zstd::Encoder::new(fs::File::create(&bundle).unwrap(), 0).unwrap());
+		for (path, data) in [
+			(MANIFEST, manifest.as_bytes()),
+			("2021-07-08/161300/logs.log", b"bad"),
+			("2021-07-09/010203/logs.log", b"ok\n"),
+		] {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(data.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+			tar.append_data(&mut header, path, data).unwrap();
+		}
+		tar.into_inner().unwrap().finish().unwrap();
+
+		assert!(import(&bundle, false, &env.config).is_err());
+		assert!(!env.store_path("2021-07-08/161300").exists());
+		assert!(env.store_path("2021-07-09/010203/logs.log").exists());
+	}
+}
@@ -0,0 +1,158 @@
+use crate::{highlight::Highlighter, sync_dir, timeline, view};
+use std::{
+	fs,
+	io::{self, BufReader, IsTerminal, Write},
+	path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorChoice {
+	// whether we should actually color the output. `NO_COLOR` only overrides `auto`, since asking
+	// for `always` is more specific than having it set in the environment
+	pub fn enabled(self, is_terminal: bool, no_color: Option<&str>) -> bool {
+		match self {
+			ColorChoice::Always => true,
+			ColorChoice::Never => false,
+			ColorChoice::Auto => is_terminal && no_color.is_none_or(str::is_empty),
+		}
+	}
+}
+
+impl TryFrom<&str> for ColorChoice {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, String> {
+		match val {
+			"auto" => Ok(ColorChoice::Auto),
+			"always" => Ok(ColorChoice::Always),
+			"never" => Ok(ColorChoice::Never),
+			_ => Err(format!(
+				"Color must be one of 'auto', 'always', or 'never', not '{val}'"
+			)),
+		}
+	}
+}
+
+// writes the file (or every file in the entry, each with a header) to stdout, without asking
+// anything or paging it
+pub fn cat(
+	date_time: &str,
+	file: Option<&str>,
+	highlighter: &Highlighter,
+	color: ColorChoice,
+) -> io::Result<()> {
+	let mut dir = sync_dir();
+	dir.push(date_time);
+
+	let files = match file {
+		Some(file) => vec![file.to_owned()],
+		None => {
+			let mut files = fs::read_dir(&dir)?
+				.filter_map(|f| f.ok()?.file_name().into_string().ok())
+				.filter(|f| timeline::is_text(f))
+				.collect::<Vec<_>>();
+			files.sort();
+			files
+		}
+	};
+
+	let no_color = std::env::var("NO_COLOR").ok();
+	let stdout = io::stdout();
+	let color = color.enabled(stdout.is_terminal(), no_color.as_deref());
+
+	let mut out = io::BufWriter::new(stdout.lock());
+	write_files(&mut out, &dir, &files, color.then_some(highlighter))?;
+	out.flush()
+}
+
+// the headers are only written if there's more than one file, so a single file comes out exactly
+// as it is (apart from colors)
+fn write_files(
+	out: &mut impl Write,
+	dir: &Path,
+	files: &[String],
+	highlighter: Option<&Highlighter>,
+) -> io::Result<()> {
+	// whether the last file ended with a newline, so the next header starts on its own line
+	let mut ended = true;
+
+	for (idx, file) in files.iter().enumerate() {
+		let reader = BufReader::new(fs::File::open(dir.join(file))?);
+
+		if files.len() > 1 {
+			if !ended {
+				writeln!(out)?;
+			}
+
+			if idx > 0 {
+				writeln!(out)?;
+			}
+
+			match highlighter {
+				Some(_) => writeln!(out, "\x1b[1m==> {file} <==\x1b[0m")?,
+				None => writeln!(out, "==> {file} <==")?,
+			}
+		}
+
+		for line in view::read_lines(reader) {
+			ended = line.ends_with('\n');
+
+			match highlighter {
+				Some(highlighter) => {
+					out.write_all(view::color_line(highlighter, &line, None).as_bytes())?
+				}
+				None => out.write_all(line.as_bytes())?,
+			}
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn color_choice_respects_no_color() {
+		assert!(ColorChoice::Auto.enabled(true, None));
+		assert!(ColorChoice::Auto.enabled(true, Some("")));
+		assert!(!ColorChoice::Auto.enabled(true, Some("1")));
+		assert!(!ColorChoice::Auto.enabled(false, None));
+		assert!(ColorChoice::Always.enabled(false, Some("1")));
+		assert!(!ColorChoice::Never.enabled(true, None));
+	}
+
+	#[test]
+	fn writes_files_with_headers_only_when_there_are_many() {
+		let dir = tempfile::tempdir().unwrap();
+		fs::write(dir.path().join("a.log"), "E/x: 12 failed\n").unwrap();
+		fs::write(dir.path().join("b.log"), b"bad \xff byte").unwrap();
+		fs::write(dir.path().join("c.log"), "I/x: fine\n").unwrap();
+
+		let run = |files: &[&str], highlighter: Option<&Highlighter>| {
+			let files = files.iter().map(|f| (*f).to_owned()).collect::<Vec<_>>();
+			let mut out = Vec::new();
+			write_files(&mut out, dir.path(), &files, highlighter).unwrap();
+			String::from_utf8(out).unwrap()
+		};
+
+		assert_eq!(run(&["a.log"], None), "E/x: 12 failed\n");
+		assert_eq!(
+			run(&["a.log", "b.log", "c.log"], None),
+			"==> a.log <==\nE/x: 12 failed\n\n==> b.log <==\nbad \u{fffd} byte\n\n==> c.log <==\nI/x: fine\n"
+		);
+
+		let highlighter = Highlighter::theme("dark").unwrap();
+		assert_eq!(
+			run(&["a.log"], Some(&highlighter)),
+			"\x1b[31mE/x: \x1b[34;1m12\x1b[0m\x1b[31m failed\x1b[0m\n"
+		);
+	}
+}
@@ -1,6 +1,10 @@
-use crate::{err, sync_dir};
+use crate::{entry::Details, err, sync_dir};
+use clap::{Arg, Command};
+use std::{fmt, fs, path::PathBuf};
 
-const ZSH_INSTALL: &str = "
+// what installing completion used to append every time it was run, so it can be cleaned up when
+// completion is installed or uninstalled now
+const OLD_ZSH_INSTALL: &str = "
 # For rager view completion
 _rager_comp() {
 	compadd $(rager complete \"$words[3]\")
@@ -9,7 +13,7 @@ _rager_comp() {
 compdef _rager_comp rager view
 ";
 
-const BASH_INSTALL: &str = "
+const OLD_BASH_INSTALL: &str = "
 # For rager view completion
 _rager_comp() {
 	COMPREPLY=($(rager complete \"${COMP_WORDS[COMP_CWORD]}\"))
@@ -18,6 +22,107 @@ _rager_comp() {
 complete -o nospace -F _rager_comp rager view
 ";
 
+// what we put around the lines we add to the shell's startup file, so we can find them again
+const START_MARKER: &str = "# >>> rager completion >>>";
+const END_MARKER: &str = "# <<< rager completion <<<";
+
+const LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+const COLORS: &[&str] = &["auto", "always", "never"];
+const SHELLS: &[&str] = &["bash", "zsh", "fish", "elvish", "powershell"];
+const STORED: &[&str] = &["user", "version", "os", "label", "day"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shell {
+	Bash,
+	Zsh,
+	Fish,
+	Elvish,
+	Powershell,
+}
+
+impl Shell {
+	// the shell that rager is being run from, going by $SHELL
+	pub fn current() -> Result<Shell, String> {
+		match std::env::var("SHELL") {
+			Ok(var) if var.is_empty() => Err("The env var $SHELL is empty".to_owned()),
+			Ok(var) => {
+				let name = std::path::Path::new(&var)
+					.file_name()
+					.map(|n| n.to_string_lossy().to_string())
+					.unwrap_or_default();
+
+				Shell::try_from(name.as_str())
+					.map_err(|_| format!("Your shell ({var}) is currently not supported :("))
+			}
+			Err(err) => Err(format!("Unable to get value of $SHELL ({err})")),
+		}
+	}
+
+	// the file that the shell runs when it starts, which is where completion is installed
+	fn rc_file(self) -> Option<PathBuf> {
+		let mut path = dirs::home_dir()?;
+
+		match self {
+			Shell::Bash => path.push(".bashrc"),
+			Shell::Zsh => path.push(".zshrc"),
+			Shell::Fish => path.extend([".config", "fish", "config.fish"]),
+			Shell::Elvish => path.extend([".config", "elvish", "rc.elv"]),
+			Shell::Powershell if cfg!(windows) => {
+				path = dirs::document_dir()?;
+				path.extend(["PowerShell", "Microsoft.PowerShell_profile.ps1"]);
+			}
+			Shell::Powershell => {
+				path.extend([".config", "powershell", "Microsoft.PowerShell_profile.ps1"])
+			}
+		}
+
+		Some(path)
+	}
+
+	// what the startup file needs to run to load the script, so that the script is always the
+	// one for the version of rager that's installed
+	fn loader(self) -> &'static str {
+		match self {
+			Shell::Bash => "eval \"$(rager complete --script bash)\"",
+			Shell::Zsh => "eval \"$(rager complete --script zsh)\"",
+			Shell::Fish => "rager complete --script fish | source",
+			Shell::Elvish => "eval (rager complete --script elvish | slurp)",
+			Shell::Powershell => {
+				"rager complete --script powershell | Out-String | Invoke-Expression"
+			}
+		}
+	}
+}
+
+impl TryFrom<&str> for Shell {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, Self::Error> {
+		match val.to_lowercase().as_str() {
+			"bash" => Ok(Shell::Bash),
+			"zsh" => Ok(Shell::Zsh),
+			"fish" => Ok(Shell::Fish),
+			"elvish" => Ok(Shell::Elvish),
+			"powershell" | "pwsh" => Ok(Shell::Powershell),
+			_ => Err(format!(
+				"The shell must be 'bash', 'zsh', 'fish', 'elvish', or 'powershell', not '{val}'"
+			)),
+		}
+	}
+}
+
+impl fmt::Display for Shell {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Shell::Bash => "bash",
+			Shell::Zsh => "zsh",
+			Shell::Fish => "fish",
+			Shell::Elvish => "elvish",
+			Shell::Powershell => "powershell",
+		})
+	}
+}
+
 pub fn list_completions(input: &str) {
 	// for separating the directories
 	let sep_char = if cfg!(windows) {
@@ -89,34 +194,750 @@ pub fn list_completions(input: &str) {
 	}
 }
 
-pub fn install_completion() {
-	use std::io::Write;
+// prints the users, versions, OSes, labels, or days (`kind`) of the entries on the device that
+// start with `input`
+pub fn list_values(kind: &str, input: &str) {
+	match stored_values(kind, input) {
+		Ok(values) => {
+			for value in values {
+				println!("{value}");
+			}
+		}
+		Err(err) => err!("{err}"),
+	}
+}
 
-	// find what shell we should install completion for
-	let (file, install_str) = match std::env::var("SHELL") {
-		// only support these two for now...
-		Ok(var) if var.contains("zsh") => (".zshrc", ZSH_INSTALL),
-		Ok(var) if var.contains("bash") => (".bashrc", BASH_INSTALL),
-		// for others, just show an error and return
-		Ok(x) => {
-			if x.is_empty() {
-				println!("The env var $SHELL is empty; aborting");
-			} else {
-				println!("Your shell ({x}) is currently not supported :(");
+fn stored_values(kind: &str, input: &str) -> Result<Vec<String>, String> {
+	if !STORED.contains(&kind) {
+		return Err(format!(
+			"Can only list the values of {}, not '{kind}'",
+			STORED.join(", ")
+		));
+	}
+
+	let mut values: Vec<String> = Vec::new();
+
+	for day in fs::read_dir(sync_dir()).into_iter().flatten().flatten() {
+		// the days are just the directories, so there's no need to look at the entries in them
+		if kind == "day" {
+			values.push(day.file_name().to_string_lossy().to_string());
+			continue;
+		}
+
+		for time in fs::read_dir(day.path()).into_iter().flatten().flatten() {
+			let Ok(contents) = fs::read_to_string(time.path().join(crate::DETAILS)) else {
+				continue;
+			};
+
+			let details = Details::parse(&contents);
+
+			let found = match kind {
+				"user" => details.user_id.into_iter().collect(),
+				"version" => details.version.into_iter().collect(),
+				"label" => details.labels,
+				_ => details
+					.os
+					.map(|os| os.to_string().to_lowercase())
+					.into_iter()
+					.collect::<Vec<_>>(),
+			};
+
+			for value in found {
+				if !values.contains(&value) {
+					values.push(value);
+				}
 			}
-			return;
 		}
-		Err(err) => {
-			err!("Unable to get value of $SHELL ({err}); aborting");
-			return;
+	}
+
+	values.retain(|v| v.starts_with(input));
+	values.sort();
+	Ok(values)
+}
+
+pub fn print_script(shell: Shell, cli: &Command) {
+	print!("{}", script(shell, cli));
+}
+
+// the completion script for every subcommand and flag of `cli`
+fn script(shell: Shell, cli: &Command) -> String {
+	let mut cmds = Vec::new();
+	commands(cli, "rager".to_owned(), &mut cmds);
+
+	match shell {
+		Shell::Bash => bash_script(&cmds),
+		Shell::Zsh => zsh_script(&cmds),
+		Shell::Fish => fish_script(&cmds),
+		Shell::Elvish => elvish_script(&cmds),
+		Shell::Powershell => powershell_script(&cmds),
+	}
+}
+
+// what the value of an argument can be completed with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Values {
+	// it's a flag that doesn't take a value
+	Nothing,
+	// it could be anything, so there's nothing to suggest
+	Anything,
+	// a `day/time[/file]` on the device
+	Entry,
+	File,
+	Dir,
+	// something from the details of the entries on the device, from `rager complete --values`
+	Stored(&'static str),
+	Fixed(&'static [&'static str]),
+}
+
+fn values_of(arg: &Arg) -> Values {
+	if !arg.is_positional() && !arg.is_takes_value_set() {
+		return Values::Nothing;
+	}
+
+	match arg.get_id() {
+		"entry" | "entries" | "old" | "new" => Values::Entry,
+		"config" | "output" | "bundle" => Values::File,
+		"dir" => Values::Dir,
+		"user" => Values::Stored("user"),
+		"version" => Values::Stored("version"),
+		"os" => Values::Stored("os"),
+		// the filters' days, not the times in `time_range_args`
+		"when" | "before" | "after" => Values::Stored("day"),
+		"level" => Values::Fixed(LEVELS),
+		"color" => Values::Fixed(COLORS),
+		"script" | "install" | "uninstall" => Values::Fixed(SHELLS),
+		"values" => Values::Fixed(STORED),
+		_ => Values::Anything,
+	}
+}
+
+// a command or subcommand, as the scripts see it
+struct Cmd {
+	// e.g. `rager__issue__create`, which is safe to use as a name in all the shells
+	id: String,
+	subcommands: Vec<Sub>,
+	flags: Vec<Flag>,
+	// what any argument that isn't a flag or subcommand can be
+	positional: Values,
+}
+
+struct Sub {
+	id: String,
+	name: String,
+	aliases: Vec<String>,
+	about: String,
+}
+
+struct Flag {
+	names: Vec<String>,
+	help: String,
+	values: Values,
+}
+
+// adds `cmd` and all its subcommands (and their subcommands) to `out`
+fn commands(cmd: &Command, id: String, out: &mut Vec<Cmd>) {
+	let flags = cmd
+		.get_arguments()
+		.filter(|a| !a.is_positional() && !a.is_hide_set())
+		// clap gives every command a `--version` until it's built, even if it has no version
+		.filter(|a| a.get_id() != "version" || cmd.get_version().is_some())
+		.map(|a| Flag {
+			names: a
+				.get_short()
+				.map(|s| format!("-{s}"))
+				.into_iter()
+				.chain(a.get_long().map(|l| format!("--{l}")))
+				.collect(),
+			help: a.get_help().unwrap_or_default().to_owned(),
+			values: values_of(a),
+		})
+		.collect::<Vec<_>>();
+
+	let subcommands = cmd
+		.get_subcommands()
+		.filter(|s| !s.is_hide_set())
+		.map(|s| Sub {
+			id: format!("{id}__{}", s.get_name()),
+			name: s.get_name().to_owned(),
+			aliases: s.get_all_aliases().map(ToOwned::to_owned).collect(),
+			about: s.get_about().unwrap_or_default().to_owned(),
+		})
+		.collect::<Vec<_>>();
+
+	let positional = cmd
+		.get_positionals()
+		.next()
+		.map_or(Values::Nothing, values_of);
+
+	out.push(Cmd {
+		id: id.clone(),
+		subcommands,
+		flags,
+		positional,
+	});
+
+	for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+		commands(sub, format!("{id}__{}", sub.get_name()), out);
+	}
+}
+
+// every `(command, word, subcommand)`, for the scripts to work out which subcommand is being
+// completed from the words before it
+fn transitions(cmds: &[Cmd]) -> Vec<(&str, &str, &str)> {
+	cmds.iter()
+		.flat_map(|cmd| {
+			cmd.subcommands.iter().flat_map(move |sub| {
+				std::iter::once(&sub.name)
+					.chain(&sub.aliases)
+					.map(move |word| (cmd.id.as_str(), word.as_str(), sub.id.as_str()))
+			})
+		})
+		.collect()
+}
+
+impl Cmd {
+	fn flag_names(&self) -> Vec<&str> {
+		self.flags
+			.iter()
+			.flat_map(|f| f.names.iter().map(String::as_str))
+			.collect()
+	}
+
+	fn subcommand_names(&self) -> Vec<&str> {
+		self.subcommands.iter().map(|s| s.name.as_str()).collect()
+	}
+
+	// the flags that are followed by a value
+	fn value_flags(&self) -> impl Iterator<Item = &Flag> {
+		self.flags.iter().filter(|f| f.values != Values::Nothing)
+	}
+}
+
+// wraps `text` in single quotes for bash and zsh
+fn sh_quote(text: &str) -> String {
+	format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+fn bash_values(values: Values) -> String {
+	match values {
+		Values::Nothing | Values::Anything => "COMPREPLY=()".to_owned(),
+		Values::Entry => "_rager_values entry".to_owned(),
+		Values::File => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_owned(),
+		Values::Dir => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_owned(),
+		Values::Stored(kind) => format!("_rager_values {kind}"),
+		Values::Fixed(list) => format!(
+			"COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+			sh_quote(&list.join(" "))
+		),
+	}
+}
+
+fn bash_script(cmds: &[Cmd]) -> String {
+	let mut out = "# completion for rager, generated by `rager complete --script bash`
+
+_rager_values() {
+	local IFS=$'\\n'
+
+	if [[ \"$1\" == entry ]]; then
+		# entries are completed a directory at a time, so there shouldn't be a space after them
+		compopt -o nospace 2>/dev/null
+		COMPREPLY=($(rager complete \"$cur\" 2>/dev/null))
+	else
+		COMPREPLY=($(compgen -W \"$(rager complete --values \"$1\" 2>/dev/null)\" -- \"$cur\"))
+	fi
+}
+
+_rager() {
+	local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\" cmd=rager i
+	COMPREPLY=()
+
+	for ((i = 1; i < COMP_CWORD; i++)); do
+		case \"$cmd,${COMP_WORDS[i]}\" in
+"
+	.to_owned();
+
+	for (cmd, word, sub) in transitions(cmds) {
+		out += &format!("\t\t\t{cmd},{word}) cmd={sub} ;;\n");
+	}
+
+	out += "\t\tesac\n\tdone\n\n\tcase \"$cmd\" in\n";
+
+	for cmd in cmds {
+		out += &format!("\t\t{})\n", cmd.id);
+
+		if cmd.value_flags().next().is_some() {
+			out += "\t\t\tcase \"$prev\" in\n";
+			for flag in cmd.value_flags() {
+				out += &format!(
+					"\t\t\t\t{}) {}; return ;;\n",
+					flag.names.join("|"),
+					bash_values(flag.values)
+				);
+			}
+			out += "\t\t\tesac\n";
+		}
+
+		out += &format!(
+			"\t\t\tif [[ \"$cur\" == -* ]]; then\n\t\t\t\tCOMPREPLY=($(compgen -W {} -- \"$cur\"))\n\t\t\t\treturn\n\t\t\tfi\n",
+			sh_quote(&cmd.flag_names().join(" "))
+		);
+
+		if cmd.subcommands.is_empty() {
+			out += &format!("\t\t\t{}\n", bash_values(cmd.positional));
+		} else {
+			out += &format!(
+				"\t\t\tCOMPREPLY=($(compgen -W {} -- \"$cur\"))\n",
+				sh_quote(&cmd.subcommand_names().join(" "))
+			);
+		}
+
+		out += "\t\t\t;;\n";
+	}
+
+	out += "\tesac\n}\n\ncomplete -F _rager rager\n";
+	out
+}
+
+fn zsh_values(values: Values) -> String {
+	match values {
+		Values::Nothing | Values::Anything => ":".to_owned(),
+		Values::Entry => "_rager_values entry".to_owned(),
+		Values::File => "_files".to_owned(),
+		Values::Dir => "_files -/".to_owned(),
+		Values::Stored(kind) => format!("_rager_values {kind}"),
+		Values::Fixed(list) => format!("compadd -- {}", list.join(" ")),
+	}
+}
+
+fn zsh_script(cmds: &[Cmd]) -> String {
+	let mut out = "#compdef rager
+# completion for rager, generated by `rager complete --script zsh`
+
+_rager_values() {
+	if [[ \"$1\" == entry ]]; then
+		# entries are completed a directory at a time, so there shouldn't be a space after them
+		compadd -S '' -- ${(f)\"$(rager complete \"$cur\" 2>/dev/null)\"}
+	else
+		compadd -- ${(f)\"$(rager complete --values \"$1\" 2>/dev/null)\"}
+	fi
+}
+
+_rager() {
+	local cur=\"${words[CURRENT]}\" prev=\"${words[CURRENT-1]}\" cmd=rager i
+	local -a flags commands
+
+	for ((i = 2; i < CURRENT; i++)); do
+		case \"$cmd,${words[i]}\" in
+"
+	.to_owned();
+
+	for (cmd, word, sub) in transitions(cmds) {
+		out += &format!("\t\t\t{cmd},{word}) cmd={sub} ;;\n");
+	}
+
+	out += "\t\tesac\n\tdone\n\n\tcase \"$cmd\" in\n";
+
+	for cmd in cmds {
+		out += &format!("\t\t{})\n", cmd.id);
+
+		if cmd.value_flags().next().is_some() {
+			out += "\t\t\tcase \"$prev\" in\n";
+			for flag in cmd.value_flags() {
+				out += &format!(
+					"\t\t\t\t{}) {}; return ;;\n",
+					flag.names.join("|"),
+					zsh_values(flag.values)
+				);
+			}
+			out += "\t\t\tesac\n";
+		}
+
+		let flags = cmd
+			.flags
+			.iter()
+			.flat_map(|f| f.names.iter().map(|n| sh_quote(&format!("{n}:{}", f.help))))
+			.collect::<Vec<_>>();
+
+		out += &format!(
+			"\t\t\tif [[ \"$cur\" == -* ]]; then\n\t\t\t\tflags=({})\n\t\t\t\t_describe -t flags flag flags\n\t\t\t\treturn\n\t\t\tfi\n",
+			flags.join(" ")
+		);
+
+		if cmd.subcommands.is_empty() {
+			out += &format!("\t\t\t{}\n", zsh_values(cmd.positional));
+		} else {
+			let commands = cmd
+				.subcommands
+				.iter()
+				.map(|s| sh_quote(&format!("{}:{}", s.name, s.about)))
+				.collect::<Vec<_>>();
+
+			out += &format!(
+				"\t\t\tcommands=({})\n\t\t\t_describe -t commands command commands\n",
+				commands.join(" ")
+			);
+		}
+
+		out += "\t\t\t;;\n";
+	}
+
+	out += "\tesac\n}\n\n";
+	// this can either be autoloaded from $fpath or sourced
+	out += "if [[ \"$funcstack[1]\" == _rager ]]; then\n\t_rager \"$@\"\nelse\n\tcompdef _rager rager\nfi\n";
+	out
+}
+
+fn fish_quote(text: &str) -> String {
+	format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish_values(values: Values) -> String {
+	match values {
+		Values::Nothing => String::new(),
+		Values::Anything => " -r".to_owned(),
+		Values::Entry => " -r -f -a '(rager complete (commandline -ct))'".to_owned(),
+		Values::File => " -r -F".to_owned(),
+		Values::Dir => " -r -f -a '(__fish_complete_directories)'".to_owned(),
+		Values::Stored(kind) => format!(" -r -f -a '(rager complete --values {kind})'"),
+		Values::Fixed(list) => format!(" -r -f -a {}", fish_quote(&list.join(" "))),
+	}
+}
+
+fn fish_script(cmds: &[Cmd]) -> String {
+	let mut out = "# completion for rager, generated by `rager complete --script fish`
+
+# which subcommand is being completed, e.g. `rager__issue__create`
+function __rager_command
+	set -l cmd rager
+	for word in (commandline -opc)[2..-1]
+		switch \"$cmd,$word\"
+"
+	.to_owned();
+
+	for (cmd, word, sub) in transitions(cmds) {
+		out += &format!(
+			"\t\t\tcase {}\n\t\t\t\tset cmd {sub}\n",
+			fish_quote(&format!("{cmd},{word}"))
+		);
+	}
+
+	out += "\t\tend\n\tend\n\techo $cmd\nend\n\ncomplete -c rager -f\n";
+
+	for cmd in cmds {
+		let cond = format!("-n 'test (__rager_command) = {}'", cmd.id);
+		out += "\n";
+
+		for flag in &cmd.flags {
+			let names = flag
+				.names
+				.iter()
+				.map(|n| match n.strip_prefix("--") {
+					Some(long) => format!(" -l {long}"),
+					None => format!(" -s {}", &n[1..]),
+				})
+				.collect::<String>();
+
+			out += &format!(
+				"complete -c rager {cond}{names} -d {}{}\n",
+				fish_quote(&flag.help),
+				fish_values(flag.values)
+			);
+		}
+
+		for sub in &cmd.subcommands {
+			out += &format!(
+				"complete -c rager {cond} -a {} -d {}\n",
+				sub.name,
+				fish_quote(&sub.about)
+			);
 		}
+
+		if cmd.subcommands.is_empty() && cmd.positional != Values::Nothing {
+			// the positional arguments are completed the same way as the values of flags, except
+			// that they don't need `-r`
+			let values = fish_values(cmd.positional).replacen(" -r", "", 1);
+			if !values.is_empty() {
+				out += &format!("complete -c rager {cond}{values}\n");
+			}
+		}
+	}
+
+	out
+}
+
+// wraps `text` in single quotes for elvish and powershell, which both escape them by doubling
+fn doubled_quote(text: &str) -> String {
+	format!("'{}'", text.replace('\'', "''"))
+}
+
+fn elvish_values(values: Values) -> String {
+	match values {
+		Values::Nothing | Values::Anything => String::new(),
+		Values::Entry => "rager complete $cur | from-lines".to_owned(),
+		Values::File | Values::Dir => "edit:complete-filename $cur".to_owned(),
+		Values::Stored(kind) => format!("rager complete --values {kind} | from-lines"),
+		Values::Fixed(list) => format!(
+			"put {}",
+			list.iter()
+				.map(|v| doubled_quote(v))
+				.collect::<Vec<_>>()
+				.join(" ")
+		),
+	}
+}
+
+fn elvish_list(words: &[&str]) -> String {
+	words
+		.iter()
+		.map(|w| doubled_quote(w))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+fn elvish_script(cmds: &[Cmd]) -> String {
+	let mut out = "# completion for rager, generated by `rager complete --script elvish`
+
+use str
+
+set edit:completion:arg-completer[rager] = {|@words|
+	var subcommands = [
+"
+	.to_owned();
+
+	for (cmd, word, sub) in transitions(cmds) {
+		out += &format!("\t\t&{}={sub}\n", doubled_quote(&format!("{cmd},{word}")));
+	}
+
+	out += "\t]
+
+	var cmd = rager
+	for word $words[1..-1] {
+		if (has-key $subcommands $cmd','$word) {
+			set cmd = $subcommands[$cmd','$word]
+		}
+	}
+
+	var cur = $words[-1]
+	var prev = $words[-2]
+
+	var completions = [
+";
+
+	for cmd in cmds {
+		out += &format!("\t\t&{}={{\n", cmd.id);
+
+		for flag in cmd.value_flags() {
+			let names = flag.names.iter().map(String::as_str).collect::<Vec<_>>();
+
+			let values = match elvish_values(flag.values) {
+				values if values.is_empty() => values,
+				values => format!("\t\t\t\t{values}\n"),
+			};
+
+			out += &format!(
+				"\t\t\tif (has-value [{}] $prev) {{\n{values}\t\t\t\treturn\n\t\t\t}}\n",
+				elvish_list(&names),
+			);
+		}
+
+		out += &format!(
+			"\t\t\tif (str:has-prefix $cur -) {{\n\t\t\t\tput {}\n\t\t\t\treturn\n\t\t\t}}\n",
+			elvish_list(&cmd.flag_names())
+		);
+
+		if !cmd.subcommands.is_empty() {
+			out += &format!("\t\t\tput {}\n", elvish_list(&cmd.subcommand_names()));
+		} else if !elvish_values(cmd.positional).is_empty() {
+			out += &format!("\t\t\t{}\n", elvish_values(cmd.positional));
+		}
+
+		out += "\t\t}\n";
+	}
+
+	out += "\t]\n\n\t$completions[$cmd]\n}\n";
+	out
+}
+
+fn powershell_values(values: Values) -> String {
+	match values {
+		// powershell completes paths itself when we don't give it anything
+		Values::Nothing | Values::Anything | Values::File | Values::Dir => String::new(),
+		Values::Entry => "rager complete $wordToComplete".to_owned(),
+		Values::Stored(kind) => format!("rager complete --values {kind}"),
+		Values::Fixed(list) => list
+			.iter()
+			.map(|v| doubled_quote(v))
+			.collect::<Vec<_>>()
+			.join(", "),
+	}
+}
+
+fn powershell_list(words: &[&str]) -> String {
+	words
+		.iter()
+		.map(|w| doubled_quote(w))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+fn powershell_block(body: &str) -> String {
+	if body.is_empty() {
+		"{ }".to_owned()
+	} else {
+		format!("{{ {body} }}")
+	}
+}
+
+fn powershell_script(cmds: &[Cmd]) -> String {
+	let mut out = "# completion for rager, generated by `rager complete --script powershell`
+
+Register-ArgumentCompleter -Native -CommandName 'rager' -ScriptBlock {
+	param($wordToComplete, $commandAst, $cursorPosition)
+
+	$subcommands = @{
+"
+	.to_owned();
+
+	for (cmd, word, sub) in transitions(cmds) {
+		out += &format!(
+			"\t\t{} = '{sub}'\n",
+			doubled_quote(&format!("{cmd},{word}"))
+		);
+	}
+
+	out += "\t}
+
+	# every word before the one that's being completed, without `rager` itself
+	$words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() })
+	if ($wordToComplete -ne '' -and $words.Count -gt 0) {
+		$words = @($words | Select-Object -SkipLast 1)
+	}
+
+	$cmd = 'rager'
+	foreach ($word in $words) {
+		if ($subcommands.ContainsKey(\"$cmd,$word\")) {
+			$cmd = $subcommands[\"$cmd,$word\"]
+		}
+	}
+
+	$prev = if ($words.Count -gt 0) { $words[-1] } else { '' }
+
+	$candidates = switch ($cmd) {
+";
+
+	for cmd in cmds {
+		out += &format!("\t\t'{}' {{\n\t\t\t", cmd.id);
+
+		for flag in cmd.value_flags() {
+			let names = flag.names.iter().map(String::as_str).collect::<Vec<_>>();
+
+			// the flags' names are case sensitive, unlike powershell's `-contains`
+			out += &format!(
+				"if (@({}) -ccontains $prev) {}\n\t\t\telse",
+				powershell_list(&names),
+				powershell_block(&powershell_values(flag.values))
+			);
+		}
+
+		out += &format!(
+			"if ($wordToComplete.StartsWith('-')) {}\n",
+			powershell_block(&powershell_list(&cmd.flag_names()))
+		);
+
+		let positional = if cmd.subcommands.is_empty() {
+			powershell_values(cmd.positional)
+		} else {
+			powershell_list(&cmd.subcommand_names())
+		};
+
+		if !positional.is_empty() {
+			out += &format!("\t\t\telse {}\n", powershell_block(&positional));
+		}
+
+		out += "\t\t}\n";
+	}
+
+	out += "\t}
+
+	$candidates | Where-Object { $_ -clike \"$wordToComplete*\" } | ForEach-Object {
+		[System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+	}
+}
+";
+	out
+}
+
+// `contents` of a startup file with the lines that load completion for `shell` in it, replacing
+// whatever was installed before
+fn with_completion(contents: &str, shell: Shell) -> String {
+	let mut contents = without_completion(contents);
+
+	if !contents.is_empty() && !contents.ends_with('\n') {
+		contents.push('\n');
+	}
+
+	contents += &format!("{START_MARKER}\n{}\n{END_MARKER}\n", shell.loader());
+	contents
+}
+
+// `contents` of a startup file without any of the lines that installing completion added
+fn without_completion(contents: &str) -> String {
+	let mut contents = contents
+		.replace(OLD_ZSH_INSTALL, "")
+		.replace(OLD_BASH_INSTALL, "");
+
+	while let Some(start) = contents.find(START_MARKER) {
+		let end = contents[start..]
+			.find(END_MARKER)
+			.map_or(contents.len(), |end| start + end + END_MARKER.len());
+		// take the newline after the block with it too
+		let end = if contents[end..].starts_with('\n') {
+			end + 1
+		} else {
+			end
+		};
+
+		contents.replace_range(start..end, "");
+	}
+
+	contents
+}
+
+// the shell to install or uninstall completion for; the one that was asked for, or else $SHELL
+fn pick_shell(shell: Option<&str>) -> Option<Shell> {
+	let shell = match shell {
+		Some(shell) => Shell::try_from(shell),
+		None => Shell::current()
+			.map_err(|err| format!("{err}; pass the shell to use (e.g. `--install zsh`) instead")),
+	};
+
+	shell.map_err(|err| err!("{err}")).ok()
+}
+
+pub fn install_completion(shell: Option<&str>) {
+	let Some(shell) = pick_shell(shell) else {
+		return;
 	};
 
+	let Some(path) = shell.rc_file() else {
+		err!("Unable to find where the {shell} startup file should be");
+		return;
+	};
+
+	let contents = fs::read_to_string(&path).unwrap_or_default();
+	let installed = with_completion(&contents, shell);
+
+	if installed == contents {
+		println!("Completion is already installed in {path:?} :)");
+		return;
+	}
+
 	// show a quick explanation for what will happen if they continue
 	println!(
-		"To install shell completion for rager, we need to append the following lines to your ~/{file}:\
-		\n\x1b[1m{install_str}\x1b[0m\
+		"To install shell completion for rager, we need to add the following lines to {path:?}:\
+		\n\x1b[1m{START_MARKER}\n{}\n{END_MARKER}\x1b[0m\
 		\nIs that ok? [y/n]",
+		shell.loader()
 	);
 
 	// actually get their input to make sure it's ok
@@ -126,26 +947,175 @@ pub fn install_completion() {
 		.read_line(&mut input)
 		.expect("Did not enter text correctly");
 
-	// if it's ok...
-	if input.to_lowercase().starts_with('y') {
-		// get the actual file we want to write to
-		let mut path = dirs::home_dir().unwrap();
-		path.push(file);
-
-		// open it up, appending and creating if it doesn't exist
-		let shell_file = std::fs::OpenOptions::new()
-			.append(true)
-			.create(true)
-			.open(&path);
-
-		// try to write, print status based on if it worked or not
-		match shell_file {
-			Ok(mut f) => match f.write(install_str.as_bytes()) {
-				Err(err) => err!("Unable to write to file at {path:?} ({err:?}); are you sure you have the right permissions?"),
-				Ok(x) if x > 0 => println!("Successfully installed completion :)\nRun \x1b[1msource ~/{file}\x1b[0m to load it in right now."),
-				_ => err!("Did not install completion successfully; unknown error occured"),
-			},
-			Err(err) => err!("Unable to open file at {path:?}: {err}"),
+	if !input.to_lowercase().starts_with('y') {
+		return;
+	}
+
+	if let Some(dir) = path.parent() {
+		if let Err(err) = fs::create_dir_all(dir) {
+			err!("Unable to create the directory at {dir:?}: {err}");
+			return;
+		}
+	}
+
+	match fs::write(&path, installed) {
+		Ok(()) => println!(
+			"Successfully installed completion :)\nStart a new {shell} session to load it in."
+		),
+		Err(err) => err!(
+			"Unable to write to file at {path:?} ({err:?}); are you sure you have the right permissions?"
+		),
+	}
+}
+
+pub fn uninstall_completion(shell: Option<&str>) {
+	let Some(shell) = pick_shell(shell) else {
+		return;
+	};
+
+	let Some(path) = shell.rc_file() else {
+		err!("Unable to find where the {shell} startup file should be");
+		return;
+	};
+
+	let contents = fs::read_to_string(&path).unwrap_or_default();
+	let uninstalled = without_completion(&contents);
+
+	if uninstalled == contents {
+		println!("Completion isn't installed in {path:?}, so there's nothing to uninstall");
+		return;
+	}
+
+	match fs::write(&path, uninstalled) {
+		Ok(()) => println!("Successfully uninstalled completion from {path:?}"),
+		Err(err) => err!("Unable to write to file at {path:?}: {err}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	#[test]
+	fn scripts_cover_every_subcommand_and_flag() {
+		for shell in [
+			Shell::Bash,
+			Shell::Zsh,
+			Shell::Fish,
+			Shell::Elvish,
+			Shell::Powershell,
+		] {
+			let script = script(shell, &crate::cli());
+
+			for expected in [
+				"rager__view",
+				"rager__issue__create",
+				"rager,linear",
+				"--level",
+				"--timeline",
+				"--help",
+				"--version",
+				"trace",
+				"rager complete",
+			] {
+				// fish gives the long names of flags with `-l` instead of dashes
+				assert!(
+					script.contains(expected)
+						|| script.contains(&expected.replacen("--", "-l ", 1)),
+					"{shell} script is missing {expected}:\n{script}"
+				);
+			}
 		}
 	}
+
+	#[test]
+	fn filter_flags_complete_values_from_the_store() {
+		let bash = script(Shell::Bash, &crate::cli());
+		assert!(bash.contains("-u|--user) _rager_values user;"), "{bash}");
+		assert!(bash.contains("-w|--when) _rager_values day;"), "{bash}");
+
+		let fish = script(Shell::Fish, &crate::cli());
+		assert!(
+			fish.contains("-s w -l when -d 'Select logs from a specific day (e.g. \\'yesterday\\', \\'friday\\', \\'2021-07-09\\')' -r -f -a '(rager complete --values day)'"),
+			"{fish}"
+		);
+	}
+
+	#[test]
+	fn bash_script_is_valid() {
+		let Ok(output) = std::process::Command::new("bash")
+			.args(["-n", "-c", &script(Shell::Bash, &crate::cli())])
+			.output()
+		else {
+			// no bash to check it with
+			return;
+		};
+
+		assert!(
+			output.status.success(),
+			"{}",
+			String::from_utf8_lossy(&output.stderr)
+		);
+	}
+
+	#[test]
+	fn installing_is_idempotent() {
+		let original = "export PATH=\"$HOME/bin:$PATH\"\n";
+
+		let once = with_completion(original, Shell::Zsh);
+		assert!(once.starts_with(original));
+		assert!(once.contains("eval \"$(rager complete --script zsh)\""));
+		assert_eq!(with_completion(&once, Shell::Zsh), once);
+
+		assert_eq!(without_completion(&once), original);
+		assert_eq!(without_completion(original), original);
+	}
+
+	#[test]
+	fn installing_replaces_the_old_snippet() {
+		let old = format!("alias ll='ls -l'\n{OLD_BASH_INSTALL}{OLD_BASH_INSTALL}");
+
+		let installed = with_completion(&old, Shell::Bash);
+		assert!(!installed.contains("_rager_comp"), "{installed}");
+		assert_eq!(installed.matches(START_MARKER).count(), 1);
+		assert_eq!(without_completion(&installed), "alias ll='ls -l'\n");
+	}
+
+	#[tokio::test]
+	async fn lists_values_from_entries_on_device() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		env.add_local_file(
+			&format!("2021-07-08/161300/{}", crate::DETAILS),
+			"crashed\nApplication: element-ios\nLabels: crash, room-list\nuser_id: @bob:example.org\nVersion: 1.8.2\n",
+		);
+		env.add_local_file(
+			&format!("2021-07-09/010203/{}", crate::DETAILS),
+			"slow\nApplication: element-android\nLabels: performance\nuser_id: @alice:example.org\nVersion: 1.8.2\n",
+		);
+		env.add_local_file(
+			&format!("2021-07-09/020304/{}", crate::DETAILS),
+			"again\nApplication: element-ios\nuser_id: @bob:example.org\nVersion: 1.9.0\n",
+		);
+
+		assert_eq!(
+			stored_values("user", "").unwrap(),
+			["@alice:example.org", "@bob:example.org"]
+		);
+		assert_eq!(stored_values("user", "@b").unwrap(), ["@bob:example.org"]);
+		assert_eq!(stored_values("version", "").unwrap(), ["1.8.2", "1.9.0"]);
+		assert_eq!(stored_values("os", "").unwrap(), ["android", "ios"]);
+		assert_eq!(
+			stored_values("label", "").unwrap(),
+			["crash", "performance", "room-list"]
+		);
+		assert_eq!(
+			stored_values("day", "").unwrap(),
+			["2021-07-08", "2021-07-09"]
+		);
+		assert_eq!(stored_values("day", "2021-07-09").unwrap(), ["2021-07-09"]);
+		assert!(stored_values("reason", "").is_err());
+	}
 }
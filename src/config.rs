@@ -1,8 +1,18 @@
-use crate::err;
-use std::fs::read_to_string;
+use crate::{
+	err,
+	highlight::Highlighter,
+	hooks::HookFailure,
+	tracker::{Tracker, TrackerConfig},
+};
+use std::{
+	fs::{self, read_to_string},
+	time::Duration,
+};
 
 #[derive(Debug)]
 pub struct Config {
+	// the file this was read from, which the config files for other servers sit next to
+	pub file: String,
 	// the server to connect to
 	pub server: String,
 	// the username to use to connect
@@ -17,8 +27,33 @@ pub struct Config {
 	pub cache_details: bool,
 	// how many times to retry
 	pub sync_retry_limit: Option<usize>,
-	// the token to interact with the linear api
-	pub linear_token: Option<String>,
+	// how to talk to each of the issue trackers
+	pub linear: TrackerConfig,
+	pub github: TrackerConfig,
+	pub gitlab: TrackerConfig,
+	pub jira: TrackerConfig,
+	// which tracker issues are on when they don't say
+	pub default_tracker: Tracker,
+	// how long to wait for a request to the server before giving up
+	pub request_timeout: Option<Duration>,
+	// the command to run after each new entry is downloaded
+	pub on_new_entry: Option<String>,
+	// the command to run after each sync finishes
+	pub on_sync_complete: Option<String>,
+	// how long to let a hook run before killing it
+	pub hook_timeout: Duration,
+	// what to do when a hook fails
+	pub hook_failure: HookFailure,
+	// the secret that webhooks sent to `rager listen` must include
+	pub listen_secret: Option<String>,
+	// the rules to colorize logs with
+	pub highlight: Highlighter,
+	// the command to open logs with instead of the built-in pager
+	pub viewer: Option<String>,
+	// whether to colorize the logs before handing them to `viewer`
+	pub viewer_color: bool,
+	// regexes for the parts of lines to ignore when diffing, on top of the built-in ones
+	pub diff_normalize: Vec<regex::Regex>,
 }
 
 impl Config {
@@ -78,11 +113,116 @@ impl Config {
 			.and_then(toml::Value::as_bool)
 			.unwrap_or(false);
 
-		let linear_token = table
-			.get("linear-token")
+		// `linear-token` and `linear-api-url` were around before the `[linear]` section was
+		let linear_url = table
+			.get("linear-api-url")
+			.and_then(toml::Value::as_str)
+			.unwrap_or("https://api.linear.app/graphql");
+
+		let mut linear = TrackerConfig::from_config(table.get("linear"), linear_url);
+		linear.token = linear.token.or_else(|| {
+			table
+				.get("linear-token")
+				.and_then(|t| t.as_str().map(std::string::ToString::to_string))
+		});
+
+		let github = TrackerConfig::from_config(table.get("github"), "https://api.github.com");
+		let gitlab = TrackerConfig::from_config(table.get("gitlab"), "https://gitlab.com/api/v4");
+		let jira = TrackerConfig::from_config(table.get("jira"), "");
+
+		let default_tracker = match table.get("default-tracker").and_then(toml::Value::as_str) {
+			Some(t) => match t.try_into() {
+				Ok(t) => t,
+				Err(err) => {
+					err!("{err}");
+					return None;
+				}
+			},
+			None => Tracker::Linear,
+		};
+
+		// a number of seconds, which has to be positive if it's there at all
+		macro_rules! seconds {
+			($key:expr) => {
+				match table.get($key).map(toml::Value::as_integer) {
+					None => None,
+					Some(Some(secs)) if secs > 0 => Some(Duration::from_secs(secs as u64)),
+					Some(_) => {
+						err!(
+							"Your config file's '{}' must be a positive number of seconds",
+							$key
+						);
+						return None;
+					}
+				}
+			};
+		}
+
+		let request_timeout = seconds!("request-timeout");
+
+		let on_new_entry = table
+			.get("on-new-entry")
+			.and_then(|t| t.as_str().map(std::string::ToString::to_string));
+
+		let on_sync_complete = table
+			.get("on-sync-complete")
+			.and_then(|t| t.as_str().map(std::string::ToString::to_string));
+
+		let hook_timeout = seconds!("hook-timeout").unwrap_or(Duration::from_secs(30));
+
+		let hook_failure = match table.get("hook-failure").and_then(toml::Value::as_str) {
+			Some(f) => match f.try_into() {
+				Ok(f) => f,
+				Err(err) => {
+					err!("{err}");
+					return None;
+				}
+			},
+			None => HookFailure::Warn,
+		};
+
+		let listen_secret = table
+			.get("listen-secret")
+			.and_then(|t| t.as_str().map(std::string::ToString::to_string));
+
+		let viewer = table
+			.get("viewer")
 			.and_then(|t| t.as_str().map(std::string::ToString::to_string));
 
+		let viewer_color = table
+			.get("viewer-color")
+			.and_then(toml::Value::as_bool)
+			.unwrap_or(false);
+
+		let diff_normalize = match table
+			.get("diff-normalize")
+			.and_then(toml::Value::as_array)
+			.into_iter()
+			.flatten()
+			.filter_map(toml::Value::as_str)
+			.map(|r| {
+				regex::Regex::new(r)
+					.map_err(|err| format!("Bad regex '{r}' in diff-normalize: {err}"))
+			})
+			.collect::<Result<Vec<_>, _>>()
+		{
+			Ok(d) => d,
+			Err(err) => {
+				err!("{err}");
+				return None;
+			}
+		};
+
+		let highlight = match Highlighter::from_config(table.get("highlight")) {
+			Ok(h) => h,
+			Err(err) => {
+				err!("{err}");
+				return None;
+			}
+		};
+
 		Some(Config {
+			file: conf,
 			server,
 			username,
 			password,
@@ -90,10 +230,61 @@ impl Config {
 			beeper_hacks,
 			cache_details,
 			sync_retry_limit,
-			linear_token,
+			linear,
+			github,
+			gitlab,
+			jira,
+			default_tracker,
+			request_timeout,
+			on_new_entry,
+			on_sync_complete,
+			hook_timeout,
+			hook_failure,
+			listen_secret,
+			highlight,
+			viewer,
+			viewer_color,
+			diff_normalize,
 		})
 	}
 
+	// just the host (and port) of the server, e.g. `rageshakes.example.org`
+	pub fn host(&self) -> &str {
+		host(&self.server)
+	}
+
+	// the config for the server at `host`, from one of the `rager*.toml` files next to this one
+	pub fn for_host(&self, host_to_find: &str) -> Option<Config> {
+		let file = std::path::Path::new(&self.file);
+		let dir = file.parent()?.read_dir().ok()?;
+
+		let mut files = dir
+			.filter_map(|f| f.ok().map(|f| f.path()))
+			.filter(|f| {
+				f != file
+					&& f.extension().is_some_and(|e| e == "toml")
+					&& f.file_name()
+						.and_then(|n| n.to_str())
+						.is_some_and(|n| n.starts_with("rager"))
+			})
+			.collect::<Vec<_>>();
+		files.sort();
+
+		let file = files.into_iter().find(|f| {
+			fs::read_to_string(f)
+				.ok()
+				.and_then(|text| text.parse::<toml::Value>().ok())
+				.and_then(|val| {
+					val.get("server")?
+						.as_str()
+						.map(|server| host(server).eq_ignore_ascii_case(host_to_find))
+				})
+				.unwrap_or(false)
+		})?;
+
+		Config::from_file(&file.to_str().map(ToOwned::to_owned))
+	}
+
 	pub fn default_file_url() -> String {
 		// safe to unwrap 'cause the documentation says it always returns `Some`
 		let mut config_dir = dirs::config_dir().unwrap();
@@ -103,3 +294,11 @@ impl Config {
 		config_dir.to_str().unwrap_or_default().to_string()
 	}
 }
+
+fn host(server: &str) -> &str {
+	server
+		.split("://")
+		.last()
+		.and_then(|s| s.split('/').next())
+		.unwrap_or_default()
+}
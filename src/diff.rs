@@ -0,0 +1,585 @@
+use crate::{cat::ColorChoice, config::Config, sync_dir, timeline, view};
+use lazy_static::lazy_static;
+use regex::Regex;
+use similar::{DiffOp, DiffTag};
+use std::{
+	fs,
+	io::{self, BufReader, IsTerminal, Write},
+	path::Path,
+	time::{Duration, Instant},
+};
+
+// logs can be huge and completely different, so past this we take whatever diff we've got
+const DIFF_DEADLINE: Duration = Duration::from_secs(5);
+
+// what the matches of the regexes from the config are replaced with
+const NORMALIZED: &str = "<*>";
+
+lazy_static! {
+	// things that are different in every run even when nothing else is, and what to replace them
+	// with. They're applied in order, so timestamps go before anything that'd match part of one
+	static ref BUILTIN_RULES: Vec<(Regex, &'static str)> = [
+		(
+			r"(?:\d{4}-)?(?:\d{2}-\d{2}[T ])?\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|\ ?[+-]\d{2}:?\d{2}\b)?",
+			"<time>",
+		),
+		(
+			r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
+			"<uuid>",
+		),
+		(r"\b0x[0-9a-fA-F]+\b", "<addr>"),
+		(
+			r#"(?i)(\b(?:request|req|txn|transaction)_?id"?\s*[=:]\s*"?)[^\s",)]+"#,
+			"${1}<id>",
+		),
+		(r"\b[0-9a-fA-F]{16,}\b", "<hex>"),
+	]
+	.into_iter()
+	.map(|(regex, with)| (Regex::new(regex).unwrap(), with))
+	.collect();
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffOptions {
+	// show the files next to each other instead of one on top of the other
+	pub side_by_side: bool,
+	// how many unchanged lines to show around each change
+	pub context: usize,
+	// compare the lines exactly as they are, without replacing timestamps and such
+	pub exact: bool,
+}
+
+// replaces the volatile parts of lines, so that two runs of the same thing come out the same
+pub struct Normalizer<'a> {
+	builtin: bool,
+	extra: &'a [Regex],
+}
+
+impl<'a> Normalizer<'a> {
+	pub fn new(extra: &'a [Regex], exact: bool) -> Normalizer<'a> {
+		Normalizer {
+			builtin: !exact,
+			extra: if exact {
+				&[]
+			} else {
+				extra
+			},
+		}
+	}
+
+	pub fn normalize(&self, line: &str) -> String {
+		let mut line = line.trim_end_matches(['\n', '\r']).to_owned();
+
+		if self.builtin {
+			for (regex, with) in BUILTIN_RULES.iter() {
+				line = regex.replace_all(&line, *with).into_owned();
+			}
+		}
+
+		for regex in self.extra {
+			line = regex.replace_all(&line, NORMALIZED).into_owned();
+		}
+
+		line
+	}
+}
+
+// one of the two things being compared
+struct Side {
+	name: String,
+	// the lines as they are, which is what's shown
+	lines: Vec<String>,
+	// the lines with the volatile parts replaced, which is what's compared
+	normalized: Vec<String>,
+}
+
+impl Side {
+	fn new(name: String, lines: Vec<String>, normalizer: &Normalizer) -> Side {
+		let lines = lines
+			.into_iter()
+			.map(|l| l.trim_end_matches(['\n', '\r']).to_owned())
+			.collect::<Vec<_>>();
+		let normalized = lines.iter().map(|l| normalizer.normalize(l)).collect();
+
+		Side {
+			name,
+			lines,
+			normalized,
+		}
+	}
+
+	fn read(dir: &Path, name: String, normalizer: &Normalizer) -> io::Result<Side> {
+		let reader = BufReader::new(fs::File::open(dir.join(&name))?);
+		Ok(Side::new(
+			name,
+			view::read_lines(reader).collect(),
+			normalizer,
+		))
+	}
+}
+
+// compares two entries (`2021-07-08/161300`) or files in them (`2021-07-08/161300/logs.log`). If
+// only one of them names a file, the file with the same name in the other entry is used
+pub fn diff(a: &str, b: &str, config: &Config, options: DiffOptions) -> Result<(), String> {
+	let (a_entry, a_file) = split(a)?;
+	let (b_entry, b_file) = split(b)?;
+	let normalizer = Normalizer::new(&config.diff_normalize, options.exact);
+
+	let no_color = std::env::var("NO_COLOR").ok();
+	let is_terminal = io::stdout().is_terminal();
+	let color = ColorChoice::Auto.enabled(is_terminal, no_color.as_deref());
+	let cols = crossterm::terminal::size().map_or(80, |(cols, _)| cols as usize);
+
+	let (output, title) = match a_file.or(b_file) {
+		Some(file) => {
+			let a_file = a_file.unwrap_or(file);
+			let b_file = b_file.unwrap_or(file);
+
+			let read = |entry: &str, file: &str| {
+				Side::read(&sync_dir(), format!("{entry}/{file}"), &normalizer)
+					.map_err(|err| format!("Couldn't read {entry}/{file}: {err}"))
+			};
+
+			let (a, b) = (read(a_entry, a_file)?, read(b_entry, b_file)?);
+			let output = if options.side_by_side {
+				side_by_side(&a, &b, options.context, cols, color)
+			} else {
+				unified(&a, &b, options.context, color)
+			};
+
+			(output, format!("{a_entry}/{a_file} → {b_entry}/{b_file}"))
+		}
+		None => (
+			entries(a_entry, b_entry, &normalizer, color)?,
+			format!("{a_entry} → {b_entry}"),
+		),
+	};
+
+	let Some(output) = output else {
+		println!("No differences between {a} and {b}");
+		return Ok(());
+	};
+
+	if !is_terminal {
+		let mut out = io::stdout().lock();
+		return match out.write_all(output.as_bytes()).and_then(|()| out.flush()) {
+			Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+				Err(format!("Couldn't print the diff: {err}"))
+			}
+			_ => Ok(()),
+		};
+	}
+
+	let paging_failed = |_| "Failed to display the diff on the page".to_owned();
+	let pager = minus::Pager::new();
+
+	pager
+		.set_line_numbers(minus::LineNumbers::Disabled)
+		.map_err(paging_failed)?;
+	pager
+		.set_exit_strategy(minus::ExitStrategy::PagerQuit)
+		.map_err(paging_failed)?;
+	pager.set_prompt(title).map_err(paging_failed)?;
+	pager.push_str(output).map_err(paging_failed)?;
+
+	minus::dynamic_paging(pager).map_err(|_| "Failed to display the diff on the page".to_owned())
+}
+
+// splits `2021-07-08/161300/logs.log` into the entry and the file
+fn split(path: &str) -> Result<(&str, Option<&str>), String> {
+	let path = path.trim_matches(['/', '\\']);
+	let mut splits = path.splitn(3, ['/', '\\']);
+
+	let (Some(day), Some(time)) = (splits.next(), splits.next()) else {
+		return Err(format!(
+			"'{path}' isn't an entry; you must enter at least a day and time to compare"
+		));
+	};
+
+	let entry = &path[..day.len() + 1 + time.len()];
+
+	if !sync_dir().join(path).exists() {
+		return Err(format!(
+			"Entry/file '{path}' does not exist or is not downloaded"
+		));
+	}
+
+	Ok((entry, splits.next()))
+}
+
+fn diff_ops(a: &Side, b: &Side) -> Vec<DiffOp> {
+	similar::capture_diff_slices_deadline(
+		similar::Algorithm::Patience,
+		&a.normalized,
+		&b.normalized,
+		Some(Instant::now() + DIFF_DEADLINE),
+	)
+}
+
+fn paint(text: &str, style: &str, color: bool) -> String {
+	if color {
+		format!("\x1b[{style}m{text}\x1b[0m")
+	} else {
+		text.to_owned()
+	}
+}
+
+// `@@ -1,4 +1,5 @@`, with the line numbers starting at 1
+fn hunk_header(group: &[DiffOp]) -> String {
+	let (Some(first), Some(last)) = (group.first(), group.last()) else {
+		return String::new();
+	};
+
+	let old = first.old_range().start..last.old_range().end;
+	let new = first.new_range().start..last.new_range().end;
+
+	// like `diff -u`, an empty range gives the line before it
+	let start = |range: &std::ops::Range<usize>| range.start + usize::from(!range.is_empty());
+
+	format!(
+		"@@ -{},{} +{},{} @@",
+		start(&old),
+		old.len(),
+		start(&new),
+		new.len()
+	)
+}
+
+// the changes, one file after the other like `diff -u`. Returns `None` if there aren't any
+fn unified(a: &Side, b: &Side, context: usize, color: bool) -> Option<String> {
+	let groups = similar::group_diff_ops(diff_ops(a, b), context);
+
+	if groups.is_empty() {
+		return None;
+	}
+
+	let mut out = paint(&format!("--- {}\n+++ {}\n", a.name, b.name), "1", color);
+
+	for group in groups {
+		out.push_str(&paint(&hunk_header(&group), "36", color));
+		out.push('\n');
+
+		for op in group {
+			let (tag, old, new) = op.as_tag_tuple();
+
+			if tag == DiffTag::Equal {
+				for line in &a.lines[old] {
+					out.push_str(&format!(" {line}\n"));
+				}
+				continue;
+			}
+
+			for line in &a.lines[old] {
+				out.push_str(&paint(&format!("-{line}"), "31", color));
+				out.push('\n');
+			}
+
+			for line in &b.lines[new] {
+				out.push_str(&paint(&format!("+{line}"), "32", color));
+				out.push('\n');
+			}
+		}
+	}
+
+	Some(out)
+}
+
+// fits the line into a column of the side-by-side view
+fn cell(line: Option<&String>, width: usize) -> String {
+	let line = line.map_or(String::new(), |l| l.replace('\t', "    "));
+	let line = line
+		.chars()
+		.filter(|c| !c.is_control())
+		.take(width)
+		.collect::<String>();
+	let pad = width - line.chars().count();
+
+	format!("{line}{}", " ".repeat(pad))
+}
+
+// the changes with the two files next to each other like `sdiff`. Returns `None` if there aren't
+// any
+fn side_by_side(a: &Side, b: &Side, context: usize, cols: usize, color: bool) -> Option<String> {
+	let groups = similar::group_diff_ops(diff_ops(a, b), context);
+
+	if groups.is_empty() {
+		return None;
+	}
+
+	let width = (cols.saturating_sub(3) / 2).max(10);
+
+	let mut out = paint(
+		&format!("{} {}\n", cell(Some(&a.name), width + 2), b.name),
+		"1",
+		color,
+	);
+
+	for group in groups {
+		out.push_str(&paint(&hunk_header(&group), "36", color));
+		out.push('\n');
+
+		for op in group {
+			let (tag, old, new) = op.as_tag_tuple();
+			let rows = old.len().max(new.len());
+
+			for row in 0..rows {
+				let left = (row < old.len()).then(|| &a.lines[old.start + row]);
+				let right = (row < new.len()).then(|| &b.lines[new.start + row]);
+
+				let (mark, left_style, right_style) = match (tag, left, right) {
+					(DiffTag::Equal, _, _) => (' ', "0", "0"),
+					(_, Some(_), Some(_)) => ('|', "31", "32"),
+					(_, Some(_), None) => ('<', "31", "0"),
+					_ => ('>', "0", "32"),
+				};
+
+				out.push_str(&format!(
+					"{} {mark} {}\n",
+					paint(&cell(left, width), left_style, color),
+					paint(&cell(right, width), right_style, color).trim_end()
+				));
+			}
+		}
+	}
+
+	Some(out)
+}
+
+// the details file as `(field, value)`. The first line is the reason the entry was sent, and
+// the rest are `field: value`
+fn details(dir: &Path) -> Vec<(String, String)> {
+	let Ok(text) = fs::read_to_string(dir.join(crate::DETAILS)) else {
+		return Vec::new();
+	};
+
+	text.lines()
+		.enumerate()
+		.filter(|(_, line)| !line.trim().is_empty())
+		.map(|(idx, line)| match line.split_once(':') {
+			_ if idx == 0 => ("reason".to_owned(), line.trim().to_owned()),
+			Some((field, value)) => (field.trim().to_owned(), value.trim().to_owned()),
+			None => (line.trim().to_owned(), String::new()),
+		})
+		.collect()
+}
+
+fn files(dir: &Path) -> Vec<String> {
+	let mut files = fs::read_dir(dir)
+		.into_iter()
+		.flatten()
+		.filter_map(|f| f.ok()?.file_name().into_string().ok())
+		.filter(|f| f != crate::DETAILS)
+		.collect::<Vec<_>>();
+	files.sort();
+	files
+}
+
+// compares the details and list of files of two entries, marking which of the files that are in
+// both are different. Returns `None` if they're the same
+fn entries(
+	a: &str,
+	b: &str,
+	normalizer: &Normalizer,
+	color: bool,
+) -> Result<Option<String>, String> {
+	let (a_dir, b_dir) = (sync_dir().join(a), sync_dir().join(b));
+	let mut out = paint(&format!("--- {a}\n+++ {b}\n"), "1", color);
+	let mut changed = false;
+
+	out.push_str(&paint("details\n", "36", color));
+
+	let (a_details, b_details) = (details(&a_dir), details(&b_dir));
+	let find = |details: &[(String, String)], field: &str| {
+		details
+			.iter()
+			.find(|(f, _)| f == field)
+			.map(|(_, v)| v.clone())
+	};
+
+	// in the order they're in the old entry, then whatever's only in the new one
+	let mut fields: Vec<&str> = Vec::new();
+	for (field, _) in a_details.iter().chain(&b_details) {
+		if !fields.contains(&field.as_str()) {
+			fields.push(field);
+		}
+	}
+
+	for field in fields {
+		let old = find(&a_details, field);
+		let new = find(&b_details, field);
+
+		match (old, new) {
+			(Some(old), Some(new)) if normalizer.normalize(&old) == normalizer.normalize(&new) => {
+				out.push_str(&format!("  {field}: {old}\n"));
+			}
+			(old, new) => {
+				changed = true;
+
+				if let Some(old) = old {
+					out.push_str(&paint(&format!("- {field}: {old}"), "31", color));
+					out.push('\n');
+				}
+
+				if let Some(new) = new {
+					out.push_str(&paint(&format!("+ {field}: {new}"), "32", color));
+					out.push('\n');
+				}
+			}
+		}
+	}
+
+	out.push_str(&paint("files\n", "36", color));
+
+	let (a_files, b_files) = (files(&a_dir), files(&b_dir));
+	let mut all = a_files.iter().chain(&b_files).collect::<Vec<_>>();
+	all.sort();
+	all.dedup();
+
+	for file in all {
+		let line = match (a_files.contains(file), b_files.contains(file)) {
+			(true, true) => {
+				let same = if timeline::is_text(file) {
+					let read = |dir: &Path| {
+						Side::read(dir, file.clone(), normalizer)
+							.map_err(|err| format!("Couldn't read {file}: {err}"))
+					};
+					read(&a_dir)?.normalized == read(&b_dir)?.normalized
+				} else {
+					fs::read(a_dir.join(file)).ok() == fs::read(b_dir.join(file)).ok()
+				};
+
+				if same {
+					format!("  {file}")
+				} else {
+					paint(&format!("~ {file} (differs)"), "33", color)
+				}
+			}
+			(true, false) => paint(&format!("- {file}"), "31", color),
+			_ => paint(&format!("+ {file}"), "32", color),
+		};
+
+		changed |= !line.starts_with(' ');
+		out.push_str(&line);
+		out.push('\n');
+	}
+
+	Ok(changed.then_some(out))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	fn side(name: &str, text: &str, normalizer: &Normalizer) -> Side {
+		Side::new(
+			name.to_owned(),
+			text.split_inclusive('\n').map(ToOwned::to_owned).collect(),
+			normalizer,
+		)
+	}
+
+	#[test]
+	fn normalizes_volatile_tokens() {
+		let extra = [Regex::new(r"device \w+").unwrap()];
+		let normalizer = Normalizer::new(&extra, false);
+
+		assert_eq!(
+			normalizer.normalize(
+				"2021-07-08T16:13:00.123Z sending txn_id=m1625760780.5 to 0x7ffee4b1 on device ABCDEF\n"
+			),
+			"<time> sending txn_id=<id> to <addr> on <*>"
+		);
+		assert_eq!(
+			normalizer.normalize("07-08 16:13:00.123 E x: 3fa85f64-5717-4562-b3fc-2c963f66afa6"),
+			"<time> E x: <uuid>"
+		);
+		assert_eq!(
+			Normalizer::new(&extra, true).normalize("16:13:00 on device A"),
+			"16:13:00 on device A"
+		);
+	}
+
+	#[test]
+	fn renders_unified_and_side_by_side() {
+		let normalizer = Normalizer::new(&[], false);
+		let a = side(
+			"a.log",
+			"16:13:00 start\n16:13:01 sync\n16:13:02 failed\n16:13:03 done\n",
+			&normalizer,
+		);
+		let b = side(
+			"b.log",
+			"17:00:00 start\n17:00:01 sync\n17:00:02 ok\n17:00:03 done\n",
+			&normalizer,
+		);
+
+		assert_eq!(
+			unified(&a, &b, 1, false).unwrap(),
+			"--- a.log\n+++ b.log\n@@ -2,3 +2,3 @@\n 16:13:01 sync\n-16:13:02 failed\n+17:00:02 ok\n 16:13:03 done\n"
+		);
+		assert_eq!(
+			side_by_side(&a, &b, 0, 33, false).unwrap(),
+			"a.log             b.log\n\
+			@@ -3,1 +3,1 @@\n\
+			16:13:02 failed | 17:00:02 ok\n"
+		);
+
+		let same = side("c.log", "18:00:00 start\n", &normalizer);
+		assert!(unified(&a, &a, 3, false).is_none());
+		assert!(unified(
+			&side("d.log", "16:13:00 start\n", &normalizer),
+			&same,
+			3,
+			false
+		)
+		.is_none());
+	}
+
+	#[tokio::test]
+	async fn compares_details_and_files() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "diff-normalize = ['\\bv\\d+']");
+		let write = |path: &str, text: &str| env.add_local_file(path, text);
+
+		write(
+			"2021-07-08/161300/details.log.gz",
+			"sync broke\nuser_id: @a:x.org\nVersion: 1.0\n",
+		);
+		write("2021-07-08/161300/logs.log", "16:13:00 fine\n");
+		write("2021-07-08/161300/old.log", "x\n");
+		write("2021-07-08/161300/sdk.log", "loaded v1\n");
+		write(
+			"2021-07-09/120000/details.log.gz",
+			"sync broke\nuser_id: @a:x.org\nVersion: 1.1\n",
+		);
+		write("2021-07-09/120000/logs.log", "12:00:00 fine\n");
+		write("2021-07-09/120000/new.log", "y\n");
+		write("2021-07-09/120000/sdk.log", "loaded v2\n");
+
+		let normalizer = Normalizer::new(&env.config.diff_normalize, false);
+		let out = entries("2021-07-08/161300", "2021-07-09/120000", &normalizer, false)
+			.unwrap()
+			.unwrap();
+
+		assert_eq!(
+			out,
+			"--- 2021-07-08/161300\n+++ 2021-07-09/120000\n\
+			details\n  reason: sync broke\n  user_id: @a:x.org\n- Version: 1.0\n+ Version: 1.1\n\
+			files\n  logs.log\n+ new.log\n- old.log\n  sdk.log\n"
+		);
+
+		assert_eq!(
+			split("2021-07-08/161300/logs.log").unwrap(),
+			("2021-07-08/161300", Some("logs.log"))
+		);
+		assert!(split("2021-07-08").is_err());
+		assert!(split("2021-07-10/000000").is_err());
+
+		let exact = Normalizer::new(&env.config.diff_normalize, true);
+		let out = entries("2021-07-08/161300", "2021-07-09/120000", &exact, false)
+			.unwrap()
+			.unwrap();
+		assert!(out.contains("~ logs.log (differs)\n"));
+	}
+}
@@ -6,6 +6,7 @@ use crate::{
 	get_links, req_with_auth,
 	sync::{download_files, Download, SyncTracker},
 	sync_dir,
+	timestamp::{TimeRange, TimeSlice},
 };
 use std::{
 	convert::TryFrom,
@@ -121,62 +122,12 @@ impl Entry {
 			}
 		};
 
-		let mut total_found = 0;
-		let total = 5;
-
-		for (idx, line) in contents.lines().enumerate() {
-			if idx == 0 {
-				self.reason = Some(line.to_owned());
-
-				total_found += 1;
-			} else if line.starts_with("Application") {
-				let lower = line.to_lowercase();
-
-				if lower.contains("android") {
-					self.os = Some(EntryOS::Android);
-				} else if lower.contains("web") || lower.contains("desktop") {
-					self.os = Some(EntryOS::Desktop);
-				} else if lower.contains("ios") {
-					self.os = Some(EntryOS::iOS);
-				}
-
-				total_found += 1;
-			} else if line.starts_with("user_id") {
-				let mut components = line.split(' ');
-
-				if let Some(user_id) = components.nth(1) {
-					self.user_id = Some(user_id.to_owned());
-				}
-
-				total_found += 1;
-			} else if line.starts_with("Version") || line.starts_with("app_hash") {
-				let mut components = line.split(' ');
-
-				if let Some(version) = components.nth(1) {
-					self.version = Some(version.to_owned());
-				}
-
-				total_found += 1;
-			} else if line.starts_with("build") {
-				let components: Vec<&str> = line.split(' ').collect();
-
-				if components.len() > 1 {
-					let build = components[1..].join(" ");
-
-					self.version = self
-						.version
-						.as_ref()
-						.map(|vers| format!("{vers} ({build})"))
-						.or(Some(build));
-				}
-
-				total_found += 1;
-			}
-
-			if total_found == total {
-				break;
-			}
-		}
+		let details = Details::parse(&contents);
+		// keep anything we already knew that isn't in the file
+		self.reason = details.reason.or(self.reason.take());
+		self.os = details.os.or(self.os.take());
+		self.user_id = details.user_id.or(self.user_id.take());
+		self.version = details.version.or(self.version.take());
 
 		self.checked_details = true;
 
@@ -276,8 +227,14 @@ impl Entry {
 		std::fs::read_dir(dir).is_ok_and(|r| r.count() != 0)
 	}
 
-	pub async fn files_containing_term(&mut self, term: &str) -> Result<Vec<String>, FilterErrors> {
+	// the files that contain `term`, only counting the lines that were logged within `range`
+	pub async fn files_containing_term(
+		&mut self,
+		term: &str,
+		range: &TimeRange,
+	) -> Result<Vec<String>, FilterErrors> {
 		let regex = regex::Regex::new(term).map_err(|_| FilterErrors::BadRegexTerm)?;
+		let date = chrono::NaiveDate::parse_from_str(&self.day, "%Y-%m-%d").ok();
 
 		let mut dir = sync_dir();
 		dir.push(self.date_time());
@@ -295,10 +252,17 @@ impl Entry {
 					file_dir.push(file);
 
 					// if we can read it to string and it matches the regex, push it
-					match fs::read_to_string(&file_dir) {
-						Ok(text) if regex.is_match(&text) => Some(file.clone()),
-						_ => None,
-					}
+					let text = fs::read_to_string(&file_dir).ok()?;
+
+					let matches = match date {
+						Some(date) if !range.is_empty() => {
+							let mut slice = TimeSlice::new(range, date);
+							text.lines().any(|l| slice.keep(l) && regex.is_match(l))
+						}
+						_ => regex.is_match(&text),
+					};
+
+					matches.then(|| file.clone())
 				})
 				.collect::<Vec<String>>())
 		} else {
@@ -306,8 +270,18 @@ impl Entry {
 		}
 	}
 
+	// whether any logs are on the device, and not just the details file (which is all that's
+	// kept of entries that didn't pass the filter when syncing with `cache-details`)
+	pub fn has_logs(&self) -> bool {
+		let mut dir = sync_dir();
+		dir.push(self.date_time());
+
+		std::fs::read_dir(dir)
+			.is_ok_and(|mut r| r.any(|f| f.is_ok_and(|f| f.file_name() != crate::DETAILS)))
+	}
+
 	pub async fn ensure_all_files_downloaded(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-		if self.is_downloaded() {
+		if self.has_logs() {
 			// If files is Some and not empty...
 			return Ok(());
 		}
@@ -323,6 +297,7 @@ impl Entry {
 			started: 0,
 			done: 0,
 			total: self.files.as_ref().map_or(0, std::vec::Vec::len),
+			completed: Vec::new(),
 		}));
 
 		if let Some(downloads) = self.files.as_ref().map(|files| {
@@ -331,6 +306,8 @@ impl Entry {
 				.map(|f| Download {
 					subdir: self.date_time() + "/" + f,
 					is_cache: false,
+					// only syncing finds new entries
+					new_entry: false,
 					state: state.clone(),
 					config: self.config.clone(),
 				})
@@ -350,6 +327,90 @@ impl Entry {
 	}
 }
 
+// what we can tell about an entry from its details file
+#[derive(Default, Debug)]
+pub struct Details {
+	pub reason: Option<String>,
+	pub os: Option<EntryOS>,
+	pub user_id: Option<String>,
+	pub version: Option<String>,
+	pub labels: Vec<String>,
+}
+
+impl Details {
+	pub fn parse(contents: &str) -> Details {
+		let mut details = Details::default();
+
+		let mut total_found = 0;
+		let total = 6;
+
+		for (idx, line) in contents.lines().enumerate() {
+			if idx == 0 {
+				details.reason = Some(line.to_owned());
+
+				total_found += 1;
+			} else if line.starts_with("Application") {
+				let lower = line.to_lowercase();
+
+				if lower.contains("android") {
+					details.os = Some(EntryOS::Android);
+				} else if lower.contains("web") || lower.contains("desktop") {
+					details.os = Some(EntryOS::Desktop);
+				} else if lower.contains("ios") {
+					details.os = Some(EntryOS::iOS);
+				}
+
+				total_found += 1;
+			} else if let Some(labels) = line.strip_prefix("Labels:") {
+				details.labels = labels
+					.split(',')
+					.map(str::trim)
+					.filter(|l| !l.is_empty())
+					.map(ToOwned::to_owned)
+					.collect();
+
+				total_found += 1;
+			} else if line.starts_with("user_id") {
+				let mut components = line.split(' ');
+
+				if let Some(user_id) = components.nth(1) {
+					details.user_id = Some(user_id.to_owned());
+				}
+
+				total_found += 1;
+			} else if line.starts_with("Version") || line.starts_with("app_hash") {
+				let mut components = line.split(' ');
+
+				if let Some(version) = components.nth(1) {
+					details.version = Some(version.to_owned());
+				}
+
+				total_found += 1;
+			} else if line.starts_with("build") {
+				let components: Vec<&str> = line.split(' ').collect();
+
+				if components.len() > 1 {
+					let build = components[1..].join(" ");
+
+					details.version = details
+						.version
+						.as_ref()
+						.map(|vers| format!("{vers} ({build})"))
+						.or(Some(build));
+				}
+
+				total_found += 1;
+			}
+
+			if total_found == total {
+				break;
+			}
+		}
+
+		details
+	}
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum EntryOS {
 	iOS,
@@ -384,3 +445,222 @@ impl TryFrom<&str> for EntryOS {
 		}
 	}
 }
+
+// turns a link to an entry or file on the rageshake server (like the ones pasted into issues) into
+// the `day/time[/file]` it's for. Anything that isn't a link is passed through as it is. Links to
+// another server switch `config` to the config file for that server
+pub fn entry_path(arg: &str, config: &mut Arc<config::Config>) -> Result<String, String> {
+	let Some(link) = arg
+		.strip_prefix("https://")
+		.or_else(|| arg.strip_prefix("http://"))
+	else {
+		return Ok(arg.trim_matches('/').to_owned());
+	};
+
+	let link = link.split(['?', '#']).next().unwrap_or_default();
+	let (host, path) = link.split_once('/').unwrap_or((link, ""));
+
+	// we can only download from the server in the config file, so a link to another one needs
+	// the config file for that one
+	if !host.eq_ignore_ascii_case(config.host()) {
+		match config.for_host(host) {
+			Some(other) => *config = Arc::new(other),
+			None => {
+				return Err(format!(
+					"That link is to {host}, but {} is for {}, and there's no config file for {host} next to it (e.g. rager-work.toml)",
+					config.file,
+					config.host()
+				))
+			}
+		}
+	}
+
+	match path.split_once("api/listing/") {
+		Some((_, day_time)) if !day_time.trim_matches('/').is_empty() => {
+			Ok(day_time.trim_matches('/').to_owned())
+		}
+		_ => Err(format!(
+			"'{arg}' isn't a link to an entry or file (e.g. '{}/api/listing/2021-07-08/161300')",
+			config.server
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	#[tokio::test]
+	async fn parses_details_file() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		env.add_local_file(
+			"2021-07-08/161300/details.log.gz",
+			"app crashed when opening room\n\
+			Application: element-ios\n\
+			user_id: @bob:example.org\n\
+			Version: 1.8.2\n\
+			build: 20210708 (abcdef)\n",
+		);
+
+		let mut entry = Entry::new("2021-07-08/", "161300/", env.config.clone());
+		entry.set_download_values().await.unwrap();
+
+		assert!(entry.checked_details);
+		assert_eq!(
+			entry.reason.as_deref(),
+			Some("app crashed when opening room")
+		);
+		assert_eq!(entry.os, Some(EntryOS::iOS));
+		assert_eq!(entry.user_id.as_deref(), Some("@bob:example.org"));
+		assert_eq!(entry.version.as_deref(), Some("1.8.2 (20210708 (abcdef))"));
+		assert_eq!(
+			entry.selectable_description(),
+			"@bob:example.org (iOS, on 2021-07-08 at 16:13:00): app crashed when opening room"
+		);
+
+		// nothing should've been downloaded since it was all on the device
+		assert!(server.requests().is_empty());
+	}
+
+	#[tokio::test]
+	async fn retrieves_file_lists_from_server_and_device() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&["reason"],
+			&[("logs-0001.log", ""), ("logs-0000.log", "")],
+		);
+		env.add_local_file("2021-07-08/161300/logs-0000.log", "");
+
+		let mut entry = Entry::new("2021-07-08", "161300", env.config.clone());
+
+		entry.retrieve_file_list(true).await.unwrap();
+		assert_eq!(
+			entry.files.as_deref().unwrap(),
+			["details.log.gz", "logs-0000.log", "logs-0001.log"]
+		);
+
+		entry.retrieve_file_list(false).await.unwrap();
+		assert_eq!(entry.files.as_deref().unwrap(), ["logs-0000.log"]);
+	}
+
+	#[tokio::test]
+	async fn downloads_missing_entries() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&[
+				"reason",
+				"Application: riot-web",
+				"user_id: @alice:example.org",
+			],
+			&[("console.log", "hello")],
+		);
+
+		// as if it was only cached when syncing
+		env.add_local_file(
+			"2021-07-08/161300/details.log.gz",
+			"reason\nApplication: riot-web\nuser_id: @alice:example.org\n",
+		);
+
+		let mut entry = Entry::new("2021-07-08", "161300", env.config.clone());
+		assert!(entry.is_downloaded());
+		assert!(!entry.has_logs());
+
+		entry.ensure_all_files_downloaded().await.unwrap();
+
+		assert!(entry.has_logs());
+		assert_eq!(entry.os, Some(EntryOS::Desktop));
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-08/161300/console.log")).unwrap(),
+			"hello"
+		);
+	}
+
+	#[tokio::test]
+	async fn downloading_an_entry_does_not_run_hooks() {
+		let server = MockServer::start().await;
+		let hooks = tempfile::tempdir().unwrap();
+		let ran = hooks.path().join("ran");
+		let env = TestEnv::new(
+			&server,
+			&format!("on-new-entry = \"touch '{}'\"", ran.display()),
+		);
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&["reason", "Application: riot-web"],
+			&[("console.log", "hello")],
+		);
+
+		let mut entry = Entry::new("2021-07-08", "161300", env.config.clone());
+		entry.ensure_all_files_downloaded().await.unwrap();
+
+		assert!(entry.has_logs());
+		assert!(!ran.exists());
+	}
+
+	#[tokio::test]
+	async fn reads_entry_links() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+		let mut config = env.config.clone();
+
+		let link = format!(
+			"{}/api/listing/2021-07-08/161300/logs.log?raw#top",
+			server.url
+		);
+		assert_eq!(
+			entry_path(&link, &mut config).as_deref(),
+			Ok("2021-07-08/161300/logs.log")
+		);
+
+		let link = format!("{}/api/listing/2021-07-08/161300/", server.url);
+		assert_eq!(
+			entry_path(&link, &mut config).as_deref(),
+			Ok("2021-07-08/161300")
+		);
+
+		// paths are left alone, besides stray slashes
+		assert_eq!(
+			entry_path("2021-07-08/161300/", &mut config).as_deref(),
+			Ok("2021-07-08/161300")
+		);
+
+		assert!(entry_path(
+			"https://rageshake.example.org/api/listing/2021-07-08/161300",
+			&mut config
+		)
+		.unwrap_err()
+		.contains("rageshake.example.org"));
+		assert!(entry_path(&format!("{}/about", server.url), &mut config).is_err());
+		assert_eq!(config.server, server.url);
+
+		// links to another server use the config file for it, if there's one next to ours
+		env.add_config("rager-work.toml", "https://rageshake.example.org");
+		env.add_config("notes.toml", "https://rageshake.example.net");
+		assert_eq!(
+			entry_path(
+				"https://rageshake.example.org/api/listing/2021-07-08/161300",
+				&mut config
+			)
+			.as_deref(),
+			Ok("2021-07-08/161300")
+		);
+		assert_eq!(config.server, "https://rageshake.example.org");
+		assert!(config.file.ends_with("rager-work.toml"));
+
+		assert!(entry_path(
+			"https://rageshake.example.net/api/listing/2021-07-08/161300",
+			&mut config
+		)
+		.is_err());
+	}
+}
@@ -20,4 +20,6 @@ pub enum FilterErrors {
 	FileReadingFailed,
 	#[error("Paging the view to the screen failed")]
 	ViewPagingFailed,
+	#[error("Opening the file with an external viewer failed: {0}")]
+	ExternalViewerFailed(String),
 }
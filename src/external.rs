@@ -0,0 +1,184 @@
+use crate::{highlight::Highlighter, timeline, view};
+use flate2::bufread::MultiGzDecoder;
+use std::{
+	fs,
+	io::{self, BufRead, BufReader, BufWriter, Write},
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+// whether the command wants every file in the entry, so we shouldn't ask which one to open
+pub fn wants_all_files(command: &str) -> bool {
+	command.contains("{files}")
+}
+
+// opens the files (which are in `dir`) with `command` and waits for it to exit. If `highlighter`
+// is given, the files are colorized first
+pub fn open(
+	command: &str,
+	dir: &Path,
+	files: &[String],
+	highlighter: Option<&Highlighter>,
+) -> Result<(), String> {
+	// copies of the files go in here, so it has to stay around until the command is done
+	let tmp = tempfile::Builder::new()
+		.prefix("rager-")
+		.tempdir()
+		.map_err(|err| format!("Couldn't create a temporary directory: {err}"))?;
+
+	let paths = files
+		.iter()
+		.map(|file| prepare(&dir.join(file), file, tmp.path(), highlighter))
+		.collect::<io::Result<Vec<_>>>()
+		.map_err(|err| format!("Couldn't prepare the files to open: {err}"))?;
+
+	let filled = fill_placeholders(command, &paths);
+
+	let mut shell = if cfg!(windows) {
+		let mut c = Command::new("cmd");
+		c.arg("/C");
+		c
+	} else {
+		let mut c = Command::new("sh");
+		c.arg("-c");
+		c
+	};
+
+	let status = shell
+		.arg(&filled)
+		.status()
+		.map_err(|err| format!("Couldn't run '{filled}': {err}"))?;
+
+	if status.success() {
+		Ok(())
+	} else {
+		Err(format!("'{command}' exited with {status}"))
+	}
+}
+
+// the path to give the command for the file. Files that are named `.gz` (whether or not they're
+// actually still compressed) or need to be colorized are copied to `tmp`, and anything else is
+// opened right where it is
+fn prepare(
+	path: &Path,
+	name: &str,
+	tmp: &Path,
+	highlighter: Option<&Highlighter>,
+) -> io::Result<PathBuf> {
+	let stripped = name.strip_suffix(".gz");
+	// there's nothing to do for screenshots and such
+	let highlighter = highlighter.filter(|_| timeline::is_text(name));
+
+	if stripped.is_none() && highlighter.is_none() {
+		return Ok(path.to_path_buf());
+	}
+
+	let mut reader = BufReader::new(fs::File::open(path)?);
+
+	// the server decompresses files before sending them, but files that got here some other way
+	// might still be compressed
+	let reader: Box<dyn BufRead> = if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+		Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+	} else {
+		Box::new(reader)
+	};
+
+	let dest = tmp.join(stripped.unwrap_or(name));
+	let mut out = BufWriter::new(fs::File::create(&dest)?);
+
+	for line in view::read_lines(reader) {
+		match highlighter {
+			Some(highlighter) => {
+				out.write_all(view::color_line(highlighter, &line, None).as_bytes())?;
+			}
+			None => out.write_all(line.as_bytes())?,
+		}
+	}
+
+	out.flush()?;
+	Ok(dest)
+}
+
+// replaces `{files}` with all the paths and `{file}` with the first one, or adds them all to the
+// end if neither is there
+fn fill_placeholders(command: &str, paths: &[PathBuf]) -> String {
+	let quoted = paths
+		.iter()
+		.map(|p| quote(&p.to_string_lossy()))
+		.collect::<Vec<_>>();
+
+	if !command.contains("{file}") && !wants_all_files(command) {
+		return format!("{command} {}", quoted.join(" "));
+	}
+
+	command
+		.replace("{files}", &quoted.join(" "))
+		.replace("{file}", quoted.first().map_or("", String::as_str))
+}
+
+// so paths with spaces and such make it through the shell as they are
+fn quote(path: &str) -> String {
+	if cfg!(windows) {
+		format!("\"{path}\"")
+	} else {
+		format!("'{}'", path.replace('\'', r"'\''"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use flate2::{write::GzEncoder, Compression};
+
+	#[test]
+	fn fills_placeholders() {
+		let paths = [PathBuf::from("/a/logs.log"), PathBuf::from("/a/it's.log")];
+
+		assert_eq!(
+			fill_placeholders("lnav {files}", &paths),
+			r"lnav '/a/logs.log' '/a/it'\''s.log'"
+		);
+		assert_eq!(
+			fill_placeholders("less -R {file} +G", &paths),
+			"less -R '/a/logs.log' +G"
+		);
+		assert_eq!(
+			fill_placeholders("$EDITOR", &paths[..1]),
+			"$EDITOR '/a/logs.log'"
+		);
+	}
+
+	#[test]
+	fn copies_files_only_when_needed() {
+		let dir = tempfile::tempdir().unwrap();
+		let tmp = tempfile::tempdir().unwrap();
+
+		fs::write(dir.path().join("logs.log"), "E/x: failed\n").unwrap();
+		fs::write(dir.path().join("details.log.gz"), "plain\n").unwrap();
+
+		let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+		gz.write_all(b"compressed\n").unwrap();
+		fs::write(dir.path().join("old.log.gz"), gz.finish().unwrap()).unwrap();
+
+		let prep = |name: &str, highlighter: Option<&Highlighter>| {
+			prepare(&dir.path().join(name), name, tmp.path(), highlighter).unwrap()
+		};
+
+		assert_eq!(prep("logs.log", None), dir.path().join("logs.log"));
+
+		let plain = prep("details.log.gz", None);
+		assert_eq!(plain, tmp.path().join("details.log"));
+		assert_eq!(fs::read_to_string(plain).unwrap(), "plain\n");
+
+		let old = prep("old.log.gz", None);
+		assert_eq!(fs::read_to_string(old).unwrap(), "compressed\n");
+
+		let highlighter = Highlighter::theme("none").unwrap();
+		let colored = prep("logs.log", Some(&highlighter));
+		assert_eq!(colored, tmp.path().join("logs.log"));
+		assert_eq!(
+			fs::read_to_string(colored).unwrap(),
+			"\x1b[31mE/x: failed\x1b[0m\n"
+		);
+	}
+}
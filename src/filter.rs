@@ -4,11 +4,12 @@ use crate::{
 	err,
 	errors::FilterErrors,
 	get_last_synced_day,
+	timestamp::TimeRange,
 };
 use chrono::Datelike;
 use std::{cmp::Ordering, convert::TryInto, fs};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Filter {
 	pub oses: Option<Vec<EntryOS>>,
 	pub before: Option<[u16; 3]>,
@@ -16,6 +17,8 @@ pub struct Filter {
 	pub when: Option<Vec<[u16; 3]>>,
 	pub user: Option<String>,
 	pub term: Option<String>,
+	// only count matches for `term` that were logged within this range
+	pub range: TimeRange,
 	pub any: bool,
 	pub reject_unsure: bool,
 }
@@ -98,6 +101,7 @@ impl Filter {
 					after: Some(last_day),
 					when: None,
 					term: None,
+					range: TimeRange::default(),
 				};
 			}
 		}
@@ -111,6 +115,7 @@ impl Filter {
 			any,
 			reject_unsure,
 			term: None,
+			range: TimeRange::default(),
 		}
 	}
 
@@ -165,7 +170,10 @@ impl Filter {
 				return Err(FilterErrors::TermFilterBeforeDownloading);
 			}
 			// since this is the last condition, we can just return it
-			return Ok(!entry.files_containing_term(term).await?.is_empty());
+			return Ok(!entry
+				.files_containing_term(term, &self.range)
+				.await?
+				.is_empty());
 		}
 
 		Ok(true)
@@ -285,3 +293,165 @@ impl Filter {
 		Some([first, second, third])
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{Failure, MockServer, TestEnv};
+
+	async fn android_server() -> MockServer {
+		let server = MockServer::start().await;
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&[
+				"crashed on launch",
+				"Application: riot-android",
+				"user_id: @bob:example.org",
+			],
+			&[("logs-0000.log", "sync failed with M_UNKNOWN_TOKEN")],
+		);
+
+		server
+	}
+
+	async fn check(filter: &Filter, env: &TestEnv) -> Result<bool, FilterErrors> {
+		let mut entry = Entry::new("2021-07-08", "161300", env.config.clone());
+		filter.entry_ok(&mut entry, true).await
+	}
+
+	#[test]
+	fn day_conditions() {
+		let before = Filter {
+			before: Some([2021, 7, 10]),
+			..Filter::default()
+		};
+		assert!(before.day_ok("2021-07-09"));
+		assert!(!before.day_ok("2021-07-10"));
+		assert!(!before.day_ok("2022-01-01"));
+
+		let after = Filter {
+			after: Some([2021, 7, 10]),
+			..Filter::default()
+		};
+		assert!(after.day_ok("2021-07-11/"));
+		assert!(!after.day_ok("2021-07-10"));
+		assert!(!after.day_ok("2020-12-31"));
+
+		let when = Filter {
+			when: Some(Filter::string_to_dates("2021-07-01,2021-07-03")),
+			..Filter::default()
+		};
+		assert!(when.day_ok("2021-07-03"));
+		assert!(!when.day_ok("2021-07-02"));
+
+		let between = Filter {
+			before: Some([2021, 7, 10]),
+			after: Some([2021, 7, 1]),
+			..Filter::default()
+		};
+		assert!(between.day_ok("2021-07-05"));
+		assert!(!between.day_ok("2021-07-15"));
+
+		let either = Filter {
+			any: true,
+			..between
+		};
+		assert!(either.day_ok("2021-07-15"));
+	}
+
+	#[tokio::test]
+	async fn os_and_user_conditions() {
+		let server = android_server().await;
+		let env = TestEnv::new(&server, "");
+
+		let cases = [
+			(Some(EntryOS::Android), None, false, true),
+			(Some(EntryOS::iOS), None, false, false),
+			(None, Some("@bob"), false, true),
+			(None, Some("@alice"), false, false),
+			(Some(EntryOS::Android), Some("@bob"), false, true),
+			(Some(EntryOS::Android), Some("@alice"), false, false),
+			(Some(EntryOS::iOS), Some("@bob"), false, false),
+			(Some(EntryOS::iOS), Some("@bob"), true, true),
+			(Some(EntryOS::Android), Some("@alice"), true, true),
+		];
+
+		for (os, user, any, expected) in cases {
+			let filter = Filter {
+				oses: os.clone().map(|o| vec![o]),
+				user: user.map(ToOwned::to_owned),
+				any,
+				..Filter::default()
+			};
+
+			assert_eq!(
+				check(&filter, &env).await.unwrap(),
+				expected,
+				"os: {os:?}, user: {user:?}, any: {any}"
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn day_is_checked_before_anything_else() {
+		let server = android_server().await;
+		let env = TestEnv::new(&server, "");
+
+		let filter = Filter {
+			after: Some([2021, 8, 1]),
+			user: Some("@bob".to_owned()),
+			..Filter::default()
+		};
+
+		assert!(!check(&filter, &env).await.unwrap());
+		// we shouldn't have had to get the details to know that
+		assert!(server.requests().is_empty());
+	}
+
+	#[tokio::test]
+	async fn unsure_entries_are_decided_by_reject_unsure() {
+		let server = android_server().await;
+		let env = TestEnv::new(&server, "");
+
+		for reject_unsure in [true, false] {
+			server.fail("2021-07-08/161300/details.log.gz", Failure::Status(500));
+
+			let filter = Filter {
+				user: Some("@bob".to_owned()),
+				reject_unsure,
+				..Filter::default()
+			};
+
+			assert_eq!(check(&filter, &env).await.unwrap(), reject_unsure);
+		}
+	}
+
+	#[tokio::test]
+	async fn term_conditions_need_downloaded_entries() {
+		let server = android_server().await;
+		let env = TestEnv::new(&server, "");
+
+		let matching = Filter {
+			term: Some("M_UNKNOWN_\\w+".to_owned()),
+			..Filter::default()
+		};
+		let missing = Filter {
+			term: Some("M_FORBIDDEN".to_owned()),
+			..Filter::default()
+		};
+
+		assert!(matches!(
+			check(&matching, &env).await,
+			Err(FilterErrors::TermFilterBeforeDownloading)
+		));
+
+		env.add_local_file(
+			"2021-07-08/161300/logs-0000.log",
+			"sync failed with M_UNKNOWN_TOKEN",
+		);
+
+		assert!(check(&matching, &env).await.unwrap());
+		assert!(!check(&missing, &env).await.unwrap());
+	}
+}
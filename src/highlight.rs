@@ -0,0 +1,359 @@
+use regex::Regex;
+use std::fs;
+
+// (name, regex, dark style, light style, priority) for each of the built-in rules. If a regex has
+// a group named `hl`, only that group is colored; otherwise the whole match is.
+const BUILT_INS: [(&str, &str, &str, &str, i64); 8] = [
+	(
+		"user",
+		r"@[\w=]+:[^\.]+(\.[a-z]+)+",
+		"cyan bold",
+		"blue bold",
+		80,
+	),
+	(
+		"room",
+		r"![a-zA-Z]+:[a-z]+(\.[a-z]+)+",
+		"yellow italic",
+		"magenta italic",
+		70,
+	),
+	(
+		"url",
+		r"(_matrix|.well-known)(/[\w%\-@:\.!]+)*",
+		"red italic",
+		"red italic",
+		60,
+	),
+	("hex", r"0x[a-fA-F0-9]+", "yellow bold", "magenta", 50),
+	("null", r"\(null\)", "red bold", "red bold", 40),
+	(
+		"fn",
+		r" (?P<hl>[a-z]+[A-Z][a-zA-Z]*)[: ]",
+		"magenta bold",
+		"magenta",
+		30,
+	),
+	("ns", r"\[[a-zA-Z]+\]", "green bold", "green", 20),
+	(
+		"num",
+		r"(?:[^\w]|^)(?P<hl>#?\d+((\.|\-|:)\d+)*)[^\w]",
+		"blue bold",
+		"blue",
+		10,
+	),
+];
+
+#[derive(Debug)]
+pub struct Rule {
+	pub name: String,
+	pub regex: Regex,
+	// the SGR parameters to color matches with, e.g. `34;1`
+	pub style: String,
+	// when two rules match the same text, the one with the higher priority wins
+	pub priority: i64,
+	// whether the regex has an `hl` group, so we know if we need to get the captures
+	has_group: bool,
+}
+
+impl Rule {
+	pub fn new(name: &str, regex: &str, style: &str, priority: i64) -> Result<Rule, String> {
+		let regex = Regex::new(regex)
+			.map_err(|err| format!("The regex for highlight rule '{name}' is invalid: {err}"))?;
+		let has_group = regex.capture_names().any(|n| n == Some("hl"));
+
+		Ok(Rule {
+			name: name.to_owned(),
+			style: style_to_sgr(style).ok_or_else(|| {
+				format!("The style for highlight rule '{name}' is invalid: '{style}'")
+			})?,
+			regex,
+			priority,
+			has_group,
+		})
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct Highlighter {
+	// sorted from highest priority to lowest
+	pub rules: Vec<Rule>,
+}
+
+impl Highlighter {
+	// the built-in rules for the given theme (`dark`, `light`, or `none`)
+	pub fn theme(theme: &str) -> Result<Highlighter, String> {
+		let light = match theme {
+			"dark" => false,
+			"light" => true,
+			"none" => return Ok(Highlighter::default()),
+			_ => {
+				return Err(format!(
+					"Highlight theme must be 'dark', 'light', or 'none', not '{theme}'"
+				))
+			}
+		};
+
+		let rules = BUILT_INS
+			.iter()
+			.map(|(name, regex, dark_style, light_style, priority)| {
+				let style = if light {
+					light_style
+				} else {
+					dark_style
+				};
+				Rule::new(name, regex, style, *priority)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(Highlighter { rules })
+	}
+
+	// builds the highlighter from the `[highlight]` table of the config file, if there is one
+	pub fn from_config(table: Option<&toml::Value>) -> Result<Highlighter, String> {
+		let Some(table) = table else {
+			return Highlighter::theme("dark");
+		};
+
+		let theme = table
+			.get("theme")
+			.and_then(toml::Value::as_str)
+			.unwrap_or("dark");
+
+		let mut highlighter = Highlighter::theme(theme)?;
+
+		// rules from a separate theme file go first so that the ones in the config file can
+		// override them
+		if let Some(file) = table.get("file").and_then(toml::Value::as_str) {
+			let text = fs::read_to_string(file)
+				.map_err(|err| format!("Couldn't read highlight file at {file}: {err}"))?;
+			let val = text.parse::<toml::Value>().map_err(|err| {
+				format!("Highlight file at {file} is not proper TOML format: {err}")
+			})?;
+
+			highlighter.apply(&val)?;
+		}
+
+		highlighter.apply(table)?;
+
+		Ok(highlighter)
+	}
+
+	// applies the `disable` list and `rules` from a table; rules with the same name as an existing
+	// rule replace it
+	fn apply(&mut self, table: &toml::Value) -> Result<(), String> {
+		if let Some(disabled) = table.get("disable").and_then(toml::Value::as_array) {
+			let disabled = disabled
+				.iter()
+				.filter_map(toml::Value::as_str)
+				.collect::<Vec<_>>();
+
+			self.rules.retain(|r| !disabled.contains(&r.name.as_str()));
+		}
+
+		for rule in table
+			.get("rules")
+			.and_then(toml::Value::as_array)
+			.into_iter()
+			.flatten()
+		{
+			let get_str = |key: &str| {
+				rule.get(key)
+					.and_then(toml::Value::as_str)
+					.ok_or_else(|| format!("Each highlight rule needs a '{key}'"))
+			};
+
+			let name = get_str("name")?;
+			let rule = Rule::new(
+				name,
+				get_str("regex")?,
+				get_str("style")?,
+				rule.get("priority")
+					.and_then(toml::Value::as_integer)
+					.unwrap_or(100),
+			)?;
+
+			self.rules.retain(|r| r.name != name);
+			self.rules.push(rule);
+		}
+
+		// stable, so rules with the same priority stay in the order they were given
+		self.rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
+
+		Ok(())
+	}
+
+	pub fn colorize_line(&self, line: &str) -> String {
+		self.colorize_with(line, None)
+	}
+
+	// colorizes the line with `extra` (e.g. the term that's being searched for) taking precedence
+	// over all the other rules
+	pub fn colorize_with(&self, line: &str, extra: Option<&Rule>) -> String {
+		// (start, end, style) of every bit of the line we're going to color. Higher-priority rules
+		// claim their matches first, and lower ones can't color anything that's already claimed
+		let mut spans: Vec<(usize, usize, &str)> = Vec::new();
+
+		for rule in extra.into_iter().chain(&self.rules) {
+			let matches: Box<dyn Iterator<Item = regex::Match>> = if rule.has_group {
+				Box::new(rule.regex.captures_iter(line).filter_map(|c| c.name("hl")))
+			} else {
+				Box::new(rule.regex.find_iter(line))
+			};
+
+			for m in matches.filter(|m| !m.is_empty()) {
+				if !spans
+					.iter()
+					.any(|(start, end, _)| m.start() < *end && *start < m.end())
+				{
+					spans.push((m.start(), m.end(), &rule.style));
+				}
+			}
+		}
+
+		if spans.is_empty() {
+			return line.to_owned();
+		}
+
+		spans.sort_unstable_by_key(|s| s.0);
+
+		let mut colored = String::with_capacity(line.len() + spans.len() * 12);
+		let mut last = 0;
+
+		for (start, end, style) in spans {
+			colored.push_str(&line[last..start]);
+			colored.push_str("\x1b[");
+			colored.push_str(style);
+			colored.push('m');
+			colored.push_str(&line[start..end]);
+			colored.push_str("\x1b[0m");
+			last = end;
+		}
+
+		colored.push_str(&line[last..]);
+		colored
+	}
+}
+
+// turns a style like `red bold` or `on-blue underline` into SGR parameters like `31;1`. Raw
+// parameters (like `38;5;208`) are passed through as they are.
+pub fn style_to_sgr(style: &str) -> Option<String> {
+	const COLORS: [&str; 8] = [
+		"black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+	];
+
+	let codes = style
+		.split(|c: char| c.is_whitespace() || c == ',')
+		.filter(|w| !w.is_empty())
+		.map(|word| {
+			let word = word.to_lowercase();
+
+			if word.chars().all(|c| c.is_ascii_digit() || c == ';') {
+				return Some(word);
+			}
+
+			let color = |name: &str| COLORS.iter().position(|c| *c == name);
+
+			let code = match word.as_str() {
+				"bold" => 1,
+				"dim" => 2,
+				"italic" => 3,
+				"underline" => 4,
+				"reverse" => 7,
+				w => {
+					if let Some(c) = w.strip_prefix("on-bright-").and_then(color) {
+						100 + c
+					} else if let Some(c) = w.strip_prefix("on-").and_then(color) {
+						40 + c
+					} else if let Some(c) = w.strip_prefix("bright-").and_then(color) {
+						90 + c
+					} else {
+						30 + color(w)?
+					}
+				}
+			};
+
+			Some(code.to_string())
+		})
+		.collect::<Option<Vec<String>>>()?;
+
+	(!codes.is_empty()).then(|| codes.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_styles() {
+		assert_eq!(style_to_sgr("blue bold").as_deref(), Some("34;1"));
+		assert_eq!(
+			style_to_sgr("bright-red, on-white underline").as_deref(),
+			Some("91;47;4")
+		);
+		assert_eq!(style_to_sgr("38;5;208").as_deref(), Some("38;5;208"));
+		assert_eq!(style_to_sgr("blurple"), None);
+		assert_eq!(style_to_sgr(""), None);
+	}
+
+	#[test]
+	fn built_ins_match_the_old_colors() {
+		let highlighter = Highlighter::theme("dark").unwrap();
+
+		assert_eq!(
+			highlighter.colorize_line("sent 12 events to @bob:example.org via (null)\n"),
+			"sent \x1b[34;1m12\x1b[0m events to \x1b[36;1m@bob:example.org\x1b[0m via \x1b[31;1m(null)\x1b[0m\n"
+		);
+		assert_eq!(
+			highlighter.colorize_line(" handleSync: done"),
+			" \x1b[35;1mhandleSync\x1b[0m: done"
+		);
+	}
+
+	#[test]
+	fn higher_priority_rules_win_overlaps() {
+		let mut highlighter = Highlighter::theme("none").unwrap();
+		let table = toml::toml! {
+			rules = [
+				{ name = "word", regex = "error \\d+", style = "red", priority = 5 },
+				{ name = "code", regex = "\\d+", style = "blue", priority = 10 },
+			]
+		};
+		highlighter.apply(&table).unwrap();
+
+		assert_eq!(
+			highlighter.colorize_line("error 42"),
+			"error \x1b[34m42\x1b[0m"
+		);
+	}
+
+	#[test]
+	fn extra_rule_wins_over_everything() {
+		let highlighter = Highlighter::theme("dark").unwrap();
+		let term = Rule::new("term", "events to @bob", "reverse", 0).unwrap();
+
+		assert_eq!(
+			highlighter.colorize_with("sent 12 events to @bob:example.org", Some(&term)),
+			"sent \x1b[34;1m12\x1b[0m \x1b[7mevents to @bob\x1b[0m:example.org"
+		);
+	}
+
+	#[test]
+	fn rules_can_be_disabled_and_replaced() {
+		let table = toml::toml! {
+			theme = "light"
+			disable = ["num"]
+			rules = [{ name = "null", regex = "\\(null\\)", style = "green" }]
+		};
+		let highlighter = Highlighter::from_config(Some(&table)).unwrap();
+
+		assert!(highlighter.rules.iter().all(|r| r.name != "num"));
+		assert_eq!(
+			highlighter.colorize_line("got 12 (null)"),
+			"got 12 \x1b[32m(null)\x1b[0m"
+		);
+
+		let bad = toml::toml! { rules = [{ name = "bad", regex = "(", style = "red" }] };
+		assert!(Highlighter::from_config(Some(&bad)).is_err());
+	}
+}
@@ -0,0 +1,245 @@
+use crate::{config::Config, entry::Entry, err, sync_dir};
+use std::{
+	process::Stdio,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+use tokio::io::AsyncWriteExt;
+
+// set once a hook fails and `hook-failure` is `stop`, so that we don't run any more after that
+static HOOKS_STOPPED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookFailure {
+	// don't say anything
+	Ignore,
+	// print an error and keep running hooks
+	Warn,
+	// print an error and don't run any more hooks until rager is restarted
+	Stop,
+}
+
+impl TryFrom<&str> for HookFailure {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, Self::Error> {
+		match val.to_lowercase().as_str() {
+			"ignore" => Ok(HookFailure::Ignore),
+			"warn" => Ok(HookFailure::Warn),
+			"stop" => Ok(HookFailure::Stop),
+			_ => Err("hook-failure must be one of 'ignore', 'warn', or 'stop'".to_owned()),
+		}
+	}
+}
+
+// runs the `on-new-entry` hook for each of the entries (given as `day/time`), one after another
+pub async fn on_new_entries(conf: &Arc<Config>, date_times: &[String]) {
+	let Some(ref cmd) = conf.on_new_entry else {
+		return;
+	};
+
+	for date_time in date_times {
+		let Some((day, time)) = date_time.split_once('/') else {
+			continue;
+		};
+
+		let mut entry = Entry::new(day, time, conf.clone());
+
+		// these are all on the device by now, so neither of these will hit the network
+		let _ = entry.retrieve_file_list(false).await;
+		if let Err(err) = entry.set_download_values().await {
+			err!("Couldn't read details of {date_time} to pass to hook: {err}");
+		}
+
+		let mut path = sync_dir();
+		path.push(entry.date_time());
+		let path = path.to_string_lossy().to_string();
+
+		let os = entry.os.as_ref().map(ToString::to_string);
+
+		let payload = serde_json::json!({
+			"day": entry.day,
+			"time": entry.time,
+			"user_id": entry.user_id,
+			"os": os,
+			"version": entry.version,
+			"reason": entry.reason,
+			"path": path,
+			"files": entry.files,
+		});
+
+		let env = [
+			("RAGER_DAY", Some(entry.day.clone())),
+			("RAGER_TIME", Some(entry.time.clone())),
+			("RAGER_USER_ID", entry.user_id.clone()),
+			("RAGER_OS", os),
+			("RAGER_VERSION", entry.version.clone()),
+			("RAGER_REASON", entry.reason.clone()),
+			("RAGER_PATH", Some(path)),
+		];
+
+		run_hook("on-new-entry", cmd, &env, &payload, conf).await;
+	}
+}
+
+// runs the `on-sync-complete` hook with the list of entries that were downloaded during the sync
+pub async fn on_sync_complete(conf: &Arc<Config>, new_entries: &[String], success: bool) {
+	let Some(ref cmd) = conf.on_sync_complete else {
+		return;
+	};
+
+	let payload = serde_json::json!({
+		"new_entries": new_entries,
+		"success": success,
+	});
+
+	let env = [
+		("RAGER_NEW_ENTRIES", Some(new_entries.len().to_string())),
+		("RAGER_SUCCESS", Some(success.to_string())),
+	];
+
+	run_hook("on-sync-complete", cmd, &env, &payload, conf).await;
+}
+
+async fn run_hook(
+	name: &str,
+	cmd: &str,
+	env: &[(&str, Option<String>)],
+	payload: &serde_json::Value,
+	conf: &Config,
+) {
+	if HOOKS_STOPPED.load(Ordering::SeqCst) {
+		return;
+	}
+
+	let Err(fail) = spawn_and_wait(cmd, env, payload, conf).await else {
+		return;
+	};
+
+	match conf.hook_failure {
+		HookFailure::Ignore => (),
+		HookFailure::Warn => err!("The {name} hook failed: {fail}"),
+		HookFailure::Stop => {
+			err!("The {name} hook failed: {fail}; not running any more hooks");
+			HOOKS_STOPPED.store(true, Ordering::SeqCst);
+		}
+	}
+}
+
+async fn spawn_and_wait(
+	cmd: &str,
+	env: &[(&str, Option<String>)],
+	payload: &serde_json::Value,
+	conf: &Config,
+) -> Result<(), String> {
+	let mut command = if cfg!(windows) {
+		let mut c = tokio::process::Command::new("cmd");
+		c.arg("/C");
+		c
+	} else {
+		let mut c = tokio::process::Command::new("sh");
+		c.arg("-c");
+		c
+	};
+
+	command
+		.arg(cmd)
+		.stdin(Stdio::piped())
+		.kill_on_drop(true)
+		.envs(
+			env.iter()
+				.map(|(k, v)| (k, v.as_deref().unwrap_or_default())),
+		);
+
+	let mut child = command
+		.spawn()
+		.map_err(|err| format!("couldn't start `{cmd}`: {err}"))?;
+
+	let payload = payload.to_string();
+
+	let finished = async {
+		// the hook doesn't have to read its stdin, so we don't care if it closed it early.
+		// dropping stdin once we're done writing closes it so the hook sees EOF
+		if let Some(mut stdin) = child.stdin.take() {
+			let _ = stdin.write_all(payload.as_bytes()).await;
+		}
+
+		child.wait().await
+	};
+
+	match tokio::time::timeout(conf.hook_timeout, finished).await {
+		Err(_) => Err(format!(
+			"`{cmd}` didn't finish within {}s",
+			conf.hook_timeout.as_secs()
+		)),
+		Ok(Err(err)) => Err(format!("couldn't wait on `{cmd}`: {err}")),
+		Ok(Ok(status)) if !status.success() => Err(format!("`{cmd}` exited with {status}")),
+		Ok(Ok(_)) => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+	use std::time::{Duration, Instant};
+
+	#[tokio::test]
+	async fn passes_entries_in_the_env_and_on_stdin() {
+		let server = MockServer::start().await;
+		let hooks = tempfile::tempdir().unwrap();
+		let out = hooks.path().join("out");
+		let env = TestEnv::new(
+			&server,
+			&format!(
+				"on-new-entry = \"{{ echo $RAGER_DAY $RAGER_USER_ID; cat; }} > '{}'\"",
+				out.display()
+			),
+		);
+
+		env.add_local_file(
+			&format!("2021-07-08/161300/{}", crate::DETAILS),
+			"crashed\nApplication: riot-android\nuser_id: @bob:example.org\n",
+		);
+		env.add_local_file("2021-07-08/161300/logs.log", "hello");
+
+		on_new_entries(&env.config, &["2021-07-08/161300".to_owned()]).await;
+
+		let out = std::fs::read_to_string(out).unwrap();
+		let (vars, stdin) = out.split_once('\n').unwrap();
+		assert_eq!(vars, "2021-07-08 @bob:example.org");
+
+		let payload = serde_json::from_str::<serde_json::Value>(stdin).unwrap();
+		assert_eq!(payload["time"], "161300");
+		assert_eq!(payload["user_id"], "@bob:example.org");
+		assert_eq!(payload["reason"], "crashed");
+		assert!(payload["files"]
+			.as_array()
+			.unwrap()
+			.contains(&serde_json::json!("logs.log")));
+	}
+
+	#[tokio::test]
+	async fn kills_hooks_that_take_too_long() {
+		let server = MockServer::start().await;
+		let hooks = tempfile::tempdir().unwrap();
+		let ran = hooks.path().join("ran");
+		let env = TestEnv::new(
+			&server,
+			&format!(
+				"on-sync-complete = \"sleep 2; touch '{}'\"\nhook-timeout = 1",
+				ran.display()
+			),
+		);
+
+		let start = Instant::now();
+		on_sync_complete(&env.config, &[], true).await;
+		assert!(start.elapsed() < Duration::from_secs(2));
+
+		// if it were still running, it would've gotten to the end by now
+		tokio::time::sleep(Duration::from_secs(2)).await;
+		assert!(!ran.exists());
+	}
+}
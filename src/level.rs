@@ -0,0 +1,281 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::VecDeque;
+
+// what's shown in place of the lines that were hidden between two that were kept
+pub const SEPARATOR: &str = "\x1b[2m--\x1b[0m\n";
+
+lazy_static! {
+	// the markers that each level uses across the different clients (logcat, rust's tracing/log,
+	// iOS, and JS consoles), from lowest severity to highest
+	static ref LEVEL_REGEXES: [(Level, Regex); 5] = [
+		(
+			Level::Trace,
+			Regex::new(r"(?:^|\s)V/|\bTRACE\b|\[(?i:trace|verbose)\]").unwrap()
+		),
+		(
+			Level::Debug,
+			Regex::new(r"(?:^|\s)D/|\bDEBUG\b|\[(?i:debug)\]|console\.debug").unwrap()
+		),
+		(
+			Level::Info,
+			Regex::new(r"(?:^|\s)I/|\bINFO\b|\[(?i:info|notice)\]|console\.(?:info|log)").unwrap()
+		),
+		(
+			Level::Warn,
+			Regex::new(r"(?:^|\s)W/|\bWARN(?:ING)?\b|\[(?i:warn|warning)\]|console\.warn").unwrap()
+		),
+		(
+			Level::Error,
+			Regex::new(r"(?:^|\s)[EF]/|\b(?:ERROR|FATAL|CRITICAL)\b|\[(?i:error|fault|fatal)\]|console\.error").unwrap()
+		),
+	];
+	// logcat's `threadtime` format, e.g. `07-08 16:13:00.123  1234  5678 E Tag: message`
+	static ref LOGCAT_REGEX: Regex =
+		Regex::new(r"^\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d+\s+\d+\s+\d+\s+(?P<level>[VDIWEF])\s").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+	Trace,
+	Debug,
+	Info,
+	Warn,
+	Error,
+}
+
+impl Level {
+	// figures out what level the line was logged at, if it says
+	pub fn detect(line: &str) -> Option<Level> {
+		if let Some(level) = LOGCAT_REGEX.captures(line).and_then(|c| c.name("level")) {
+			return match level.as_str() {
+				"V" => Some(Level::Trace),
+				"D" => Some(Level::Debug),
+				"I" => Some(Level::Info),
+				"W" => Some(Level::Warn),
+				_ => Some(Level::Error),
+			};
+		}
+
+		// the marker is generally at the start of the line, so if there are multiple (e.g. an info
+		// line that logs the word `ERROR`), the first one is what we want
+		LEVEL_REGEXES
+			.iter()
+			.filter_map(|(level, regex)| regex.find(line).map(|m| (m.start(), *level)))
+			.min_by_key(|(start, _)| *start)
+			.map(|(_, level)| level)
+	}
+
+	// the SGR parameters to color a whole line of this level with, if it should be
+	pub fn style(self) -> Option<&'static str> {
+		match self {
+			Level::Error => Some("31"),
+			Level::Warn => Some("33"),
+			_ => None,
+		}
+	}
+
+	// colors the whole (already-colorized) line, making sure the color comes back after any
+	// resets that are already in it
+	pub fn colorize(self, line: &str) -> String {
+		let Some(style) = self.style() else {
+			return line.to_owned();
+		};
+
+		let start = format!("\x1b[{style}m");
+		let body = line.trim_end_matches('\n');
+		let newline = &line[body.len()..];
+
+		format!(
+			"{start}{}\x1b[0m{newline}",
+			body.replace("\x1b[0m", &format!("\x1b[0m{start}"))
+		)
+	}
+}
+
+impl TryFrom<&str> for Level {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, String> {
+		match val.to_lowercase().as_str() {
+			"trace" | "verbose" => Ok(Level::Trace),
+			"debug" => Ok(Level::Debug),
+			"info" => Ok(Level::Info),
+			"warn" | "warning" => Ok(Level::Warn),
+			"error" => Ok(Level::Error),
+			_ => {
+				Err("Level must be one of 'trace', 'debug', 'info', 'warn', or 'error'".to_owned())
+			}
+		}
+	}
+}
+
+// decides which lines to show when only showing lines of at least a certain level, along with
+// `context` lines before and after each of them (like `grep -C`)
+pub struct LevelFilter<T> {
+	min: Level,
+	context: usize,
+	// the level of the last line that had one, which lines without one (like the rest of a stack
+	// trace) are counted as
+	current: Option<Level>,
+	// the most recent hidden lines, in case one of the next lines needs them for context
+	before: VecDeque<T>,
+	// how many more lines to show as context after the last line that was kept
+	after: usize,
+	// whether we've shown anything yet, and whether any lines have been left out since then
+	shown_any: bool,
+	dropped: bool,
+}
+
+impl<T: AsRef<str>> LevelFilter<T> {
+	pub fn new(min: Level, context: usize) -> LevelFilter<T> {
+		LevelFilter {
+			min,
+			context,
+			current: None,
+			before: VecDeque::with_capacity(context),
+			after: 0,
+			shown_any: false,
+			dropped: false,
+		}
+	}
+
+	// takes the next line of the file and returns the lines that should be shown because of it,
+	// with `None` wherever a separator should go
+	pub fn push(&mut self, line: T) -> Vec<Option<T>> {
+		if let Some(level) = Level::detect(line.as_ref()) {
+			self.current = Some(level);
+		}
+
+		if self.current.is_some_and(|l| l >= self.min) {
+			let mut out = Vec::with_capacity(self.before.len() + 2);
+
+			if self.dropped && self.shown_any {
+				out.push(None);
+			}
+
+			out.extend(self.before.drain(..).map(Some));
+			out.push(Some(line));
+
+			self.after = self.context;
+			self.shown_any = true;
+			self.dropped = false;
+
+			return out;
+		}
+
+		if self.after > 0 {
+			self.after -= 1;
+			return vec![Some(line)];
+		}
+
+		if self.before.len() == self.context {
+			self.dropped = true;
+			self.before.pop_front();
+		}
+
+		if self.context > 0 {
+			self.before.push_back(line);
+		}
+
+		Vec::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn detects_levels_across_platforms() {
+		let cases = [
+			("E/MatrixSync: sync failed", Some(Level::Error)),
+			("W/Crypto: no session", Some(Level::Warn)),
+			(
+				"07-08 16:13:00.123  1234  5678 W Tag: slow response",
+				Some(Level::Warn),
+			),
+			(
+				"2021-07-08T16:13:00Z ERROR matrix_sdk::sync: failed",
+				Some(Level::Error),
+			),
+			(
+				"2021-07-08T16:13:00Z  INFO matrix_sdk: started",
+				Some(Level::Info),
+			),
+			(
+				"2021-07-08 16:13:00 [error] MXSession failed",
+				Some(Level::Error),
+			),
+			("console.warn: deprecated call", Some(Level::Warn)),
+			("INFO got response: ERROR 500", Some(Level::Info)),
+			("just a line with Errors in it", None),
+		];
+
+		for (line, level) in cases {
+			assert_eq!(Level::detect(line), level, "{line}");
+		}
+	}
+
+	#[test]
+	fn colorizes_whole_lines_around_resets() {
+		assert_eq!(
+			Level::Error.colorize("E/x: \x1b[34;1m12\x1b[0m failed\n"),
+			"\x1b[31mE/x: \x1b[34;1m12\x1b[0m\x1b[31m failed\x1b[0m\n"
+		);
+		assert_eq!(Level::Info.colorize("I/x: fine\n"), "I/x: fine\n");
+	}
+
+	#[test]
+	fn filters_by_level_with_context() {
+		let lines = [
+			"I/a: 1",
+			"I/a: 2",
+			"I/a: 3",
+			"E/a: 4",
+			"    at trace",
+			"I/a: 6",
+			"I/a: 7",
+			"I/a: 8",
+			"I/a: 9",
+			"W/a: 10",
+		];
+
+		let run = |context| {
+			let mut filter = LevelFilter::new(Level::Warn, context);
+			lines
+				.iter()
+				.flat_map(|l| filter.push(*l))
+				.map(|l| l.unwrap_or(SEPARATOR))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(run(0), ["E/a: 4", "    at trace", SEPARATOR, "W/a: 10"]);
+		assert_eq!(
+			run(1),
+			[
+				"I/a: 3",
+				"E/a: 4",
+				"    at trace",
+				"I/a: 6",
+				SEPARATOR,
+				"I/a: 9",
+				"W/a: 10"
+			]
+		);
+		assert_eq!(
+			run(2),
+			[
+				"I/a: 2",
+				"I/a: 3",
+				"E/a: 4",
+				"    at trace",
+				"I/a: 6",
+				"I/a: 7",
+				"I/a: 8",
+				"I/a: 9",
+				"W/a: 10"
+			]
+		);
+	}
+}
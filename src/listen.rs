@@ -0,0 +1,316 @@
+use crate::{config::Config, entry::Entry, err, filter::Filter};
+use futures::{channel::mpsc, StreamExt};
+use hyper::{
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+	collections::HashSet,
+	convert::Infallible,
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+};
+
+// everything the request handler needs to get at
+struct Listener {
+	conf: Arc<Config>,
+	secret: Option<String>,
+	// the `day/time` of every entry that's waiting to be downloaded or was already downloaded,
+	// so that the same submission being posted twice doesn't download it twice
+	queued: Mutex<HashSet<String>>,
+	sender: mpsc::UnboundedSender<String>,
+}
+
+pub async fn listen(
+	bind: SocketAddr,
+	secret: Option<String>,
+	filter: Arc<Filter>,
+	conf: Arc<Config>,
+) -> Result<(), hyper::Error> {
+	let (sender, receiver) = mpsc::unbounded();
+
+	let listener = Arc::new(Listener {
+		conf: conf.clone(),
+		secret,
+		queued: Mutex::new(HashSet::new()),
+		sender,
+	});
+
+	let make_svc = {
+		let listener = listener.clone();
+		make_service_fn(move |_| {
+			let listener = listener.clone();
+			async move {
+				Ok::<_, Infallible>(service_fn(move |req| {
+					let listener = listener.clone();
+					async move { Ok::<_, Infallible>(handle(req, &listener).await) }
+				}))
+			}
+		})
+	};
+
+	let server = Server::try_bind(&bind)?.serve(make_svc);
+
+	println!("👂 Listening for rageshake webhooks on \x1b[1mhttp://{bind}\x1b[0m");
+
+	// the queue is only ever closed if the listener goes away, which doesn't happen while the
+	// server is running, so this runs forever alongside the server
+	let worker = receiver.for_each_concurrent(conf.threads, |date_time| {
+		let filter = filter.clone();
+		let conf = conf.clone();
+		let listener = listener.clone();
+
+		async move {
+			if !download(&date_time, &filter, conf).await {
+				// let it be queued again if the server (or someone else) retries the webhook
+				if let Ok(mut queued) = listener.queued.lock() {
+					queued.remove(&date_time);
+				}
+			}
+		}
+	});
+
+	let (res, ()) = futures::join!(server, worker);
+	res
+}
+
+// returns whether we're done with the entry (either because we downloaded it or because it
+// doesn't match the filter)
+async fn download(date_time: &str, filter: &Filter, conf: Arc<Config>) -> bool {
+	let Some((day, time)) = date_time.split_once('/') else {
+		return true;
+	};
+
+	let mut entry = Entry::new(day, time, conf);
+
+	match filter.entry_ok(&mut entry, true).await {
+		Err(err) => {
+			err!("Failed to check entry {date_time}: {err}");
+			return false;
+		}
+		Ok(false) => {
+			println!("Skipping {date_time} since it doesn't match the sync filter");
+			return true;
+		}
+		Ok(true) => (),
+	}
+
+	if let Err(err) = entry.ensure_all_files_downloaded().await {
+		err!("Failed to download entry {date_time}: {err}");
+		return false;
+	}
+
+	if !entry.checked_details {
+		let _ = entry.set_download_values().await;
+	}
+
+	println!(
+		"[{}] \x1b[32;1m+\x1b[0m {}",
+		chrono::Local::now().format("%H:%M:%S"),
+		entry.selectable_description()
+	);
+
+	true
+}
+
+async fn handle(req: Request<Body>, listener: &Listener) -> Response<Body> {
+	macro_rules! respond {
+		($status:expr, $msg:expr) => {
+			return Response::builder()
+				.status($status)
+				.body(Body::from($msg))
+				.unwrap_or_default()
+		};
+	}
+
+	if req.method() != Method::POST {
+		respond!(StatusCode::METHOD_NOT_ALLOWED, "Only POST is supported");
+	}
+
+	if let Some(ref secret) = listener.secret {
+		if !secret_matches(&req, secret) {
+			respond!(StatusCode::UNAUTHORIZED, "Missing or incorrect secret");
+		}
+	}
+
+	let Ok(body) = hyper::body::to_bytes(req.into_body()).await else {
+		respond!(StatusCode::BAD_REQUEST, "Couldn't read request body");
+	};
+
+	let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&body) else {
+		respond!(StatusCode::BAD_REQUEST, "Request body is not valid JSON");
+	};
+
+	let Some(date_time) = payload
+		.get("listing_url")
+		.and_then(serde_json::Value::as_str)
+		.and_then(|url| entry_from_listing_url(url, &listener.conf.server))
+	else {
+		respond!(
+			StatusCode::BAD_REQUEST,
+			"Payload has no `listing_url` pointing at the configured server"
+		);
+	};
+
+	let newly_queued = listener
+		.queued
+		.lock()
+		.is_ok_and(|mut queued| queued.insert(date_time.clone()));
+
+	if !newly_queued {
+		respond!(StatusCode::OK, "Entry was already queued");
+	}
+
+	if listener.sender.unbounded_send(date_time).is_err() {
+		respond!(
+			StatusCode::INTERNAL_SERVER_ERROR,
+			"Download queue is closed"
+		);
+	}
+
+	respond!(StatusCode::ACCEPTED, "Entry queued for download");
+}
+
+// the secret can be passed as either `Authorization: Bearer <secret>` or `X-Rager-Secret: <secret>`
+fn secret_matches(req: &Request<Body>, secret: &str) -> bool {
+	let headers = req.headers();
+
+	let given = headers
+		.get(hyper::header::AUTHORIZATION)
+		.and_then(|h| h.to_str().ok())
+		.and_then(|h| h.strip_prefix("Bearer "))
+		.or_else(|| headers.get("X-Rager-Secret").and_then(|h| h.to_str().ok()));
+
+	// compare every byte so that how long this takes doesn't give away how much of it matched
+	given.is_some_and(|given| {
+		given.len() == secret.len()
+			&& given
+				.bytes()
+				.zip(secret.bytes())
+				.fold(0, |acc, (g, s)| acc | (g ^ s))
+				== 0
+	})
+}
+
+// turns `<server>/api/listing/<day>/<time>[/...]` into `<day>/<time>`
+fn entry_from_listing_url(url: &str, server: &str) -> Option<String> {
+	let rest = url
+		.strip_prefix(server)?
+		.trim_start_matches('/')
+		.strip_prefix("api/listing/")?;
+
+	let mut splits = rest.split('/').filter(|s| !s.is_empty());
+	let (day, time) = (splits.next()?, splits.next()?);
+
+	// the time has to be checked too, since it ends up in the path the entry is downloaded to
+	let time_regex = regex::Regex::new(r"^\d{6}$").ok()?;
+	if !time_regex.is_match(time) {
+		return None;
+	}
+
+	Filter::date_array(day).map(|_| format!("{day}/{time}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	const SERVER: &str = "https://rageshake.example.org";
+
+	fn request(headers: &[(&str, &str)], body: &str) -> Request<Body> {
+		let mut req = Request::builder().method(Method::POST).uri("/");
+		for (name, value) in headers {
+			req = req.header(*name, *value);
+		}
+		req.body(Body::from(body.to_owned())).unwrap()
+	}
+
+	#[test]
+	fn parses_listing_urls() {
+		let parse = |url: &str| entry_from_listing_url(url, SERVER);
+
+		assert_eq!(
+			parse(&format!("{SERVER}/api/listing/2021-07-08/161300")).as_deref(),
+			Some("2021-07-08/161300")
+		);
+		assert_eq!(
+			parse(&format!("{SERVER}/api/listing/2021-07-08/161300/logs.log")).as_deref(),
+			Some("2021-07-08/161300")
+		);
+
+		assert_eq!(parse(&format!("{SERVER}/api/listing/2021-07-08/..")), None);
+		assert_eq!(
+			parse(&format!("{SERVER}/api/listing/2021-07-08/1613")),
+			None
+		);
+		assert_eq!(
+			parse(&format!("{SERVER}/api/listing/yesterday/161300")),
+			None
+		);
+		assert_eq!(parse(&format!("{SERVER}/api/listing/2021-07-08")), None);
+		assert_eq!(
+			parse("https://rageshake.example.net/api/listing/2021-07-08/161300"),
+			None
+		);
+	}
+
+	#[test]
+	fn checks_secrets() {
+		assert!(secret_matches(
+			&request(&[("Authorization", "Bearer hunter2")], ""),
+			"hunter2"
+		));
+		assert!(secret_matches(
+			&request(&[("X-Rager-Secret", "hunter2")], ""),
+			"hunter2"
+		));
+
+		assert!(!secret_matches(&request(&[], ""), "hunter2"));
+		assert!(!secret_matches(
+			&request(&[("Authorization", "Bearer hunter3")], ""),
+			"hunter2"
+		));
+		assert!(!secret_matches(
+			&request(&[("Authorization", "hunter2")], ""),
+			"hunter2"
+		));
+		assert!(!secret_matches(
+			&request(&[("X-Rager-Secret", "hunter")], ""),
+			"hunter2"
+		));
+	}
+
+	#[tokio::test]
+	async fn queues_each_entry_once() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		let (sender, mut receiver) = mpsc::unbounded();
+		let listener = Listener {
+			conf: env.config.clone(),
+			secret: Some("hunter2".to_owned()),
+			queued: Mutex::new(HashSet::new()),
+			sender,
+		};
+
+		let body = format!(
+			"{{\"listing_url\": \"{}/api/listing/2021-07-08/161300\"}}",
+			server.url
+		);
+		let post = || request(&[("X-Rager-Secret", "hunter2")], &body);
+
+		let res = handle(post(), &listener).await;
+		assert_eq!(res.status(), StatusCode::ACCEPTED);
+		assert_eq!(receiver.next().await.as_deref(), Some("2021-07-08/161300"));
+
+		let res = handle(post(), &listener).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		let text = hyper::body::to_bytes(res.into_body()).await.unwrap();
+		assert_eq!(text.as_ref(), b"Entry was already queued");
+		assert!(receiver.try_next().is_err());
+
+		let res = handle(request(&[], &body), &listener).await;
+		assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+	}
+}
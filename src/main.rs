@@ -5,16 +5,31 @@ use errors::FilterErrors::*;
 use std::convert::TryInto;
 use std::sync::{Arc, Mutex};
 
+mod bundle;
+mod cat;
 mod completion;
 mod config;
+mod diff;
 mod entry;
 mod errors;
+mod external;
 mod filter;
-mod linear;
+mod highlight;
+mod hooks;
+mod level;
+mod listen;
+#[cfg(test)]
+mod mock;
 mod prune;
 mod search;
+mod serve;
 mod sync;
+mod timeline;
+mod timestamp;
+mod tracker;
+mod tui;
 mod view;
+mod watch;
 
 const ERR_PREFIX: &str = "\x1b[31;1mERROR:\x1b[0m";
 const WARN_PREFIX: &str = "\x1b[33;1mWARNING:\x1b[0m";
@@ -35,8 +50,22 @@ macro_rules! warn{
 	}
 }
 
-#[tokio::main]
-async fn main() {
+// the path separator, as a literal so it can go in the help text with `concat!`
+#[cfg(windows)]
+macro_rules! sep {
+	() => {
+		"\\"
+	};
+}
+
+#[cfg(not(windows))]
+macro_rules! sep {
+	() => {
+		"/"
+	};
+}
+
+fn cli() -> Command<'static> {
 	macro_rules! subcommand_search {
 		($name:expr, $about:expr) => {
 			Command::new($name)
@@ -100,13 +129,46 @@ async fn main() {
 		};
 	}
 
-	let sep_char = if cfg!(windows) {
-		'\\'
-	} else {
-		'/'
-	};
+	// for the commands that can show or search just part of each log
+	macro_rules! time_range_args {
+		($cmd:expr) => {
+			$cmd.arg(
+				Arg::new("from")
+					.long("from")
+					.help("Only look at lines logged at or after this time (e.g. '14:05', '2021-07-08 14:05:30')")
+					.takes_value(true)
+					.conflicts_with("around"),
+			)
+			.arg(
+				Arg::new("to")
+					.long("to")
+					.help("Only look at lines logged at or before this time")
+					.takes_value(true)
+					.conflicts_with("around"),
+			)
+			.arg(
+				Arg::new("around")
+					.long("around")
+					.help("Only look at lines logged within --window of this time")
+					.takes_value(true),
+			)
+			.arg(
+				Arg::new("window")
+					.long("window")
+					.help("How far before and after --around to look (e.g. '90s', '5m'; defaults to 5m)")
+					.takes_value(true)
+					.requires("around"),
+			)
+			.arg(
+				Arg::new("local")
+					.long("local")
+					.help("Convert timestamps to your timezone before comparing, instead of using the timezone they were logged in")
+					.action(ArgAction::SetTrue),
+			)
+		};
+	}
 
-	let matches = Command::new("Rager")
+	Command::new("Rager")
 		.version(VERSION.unwrap_or("latest"))
 		.author("Ian Welker <@janshai:beeper.com>")
 		.subcommand(
@@ -129,56 +191,451 @@ async fn main() {
 						.long("sync-since-last-day")
 						.help("Sync entries only since the last day you synced (inclusive)")
 						.action(ArgAction::SetTrue)
+				)
+				.arg(
+					Arg::new("watch")
+						.long("watch")
+						.help("Keep running after syncing, checking the server for new entries every interval (e.g. '90s', '5m', '1h'; 5m by default)")
+						.takes_value(true)
+						.min_values(0)
+						.require_equals(true)
+						.default_missing_value("5m"),
 				),
 		)
 		.subcommand(Command::new("desync").about("Clear all logs off of your device"))
+		.subcommand(time_range_args!(
+			subcommand_search!("search", "Search through the logs currently on your device")
+				.arg(
+					Arg::new("preview")
+						.short('p')
+						.long("preview")
+						.help("See only an overview of the selected issue, as opposed to viewing any of the logs")
+						.takes_value(false),
+				)
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("with")
+						.long("with")
+						.help("Open the file with this command instead of the built-in pager, e.g. '$EDITOR' or 'lnav {files}' ({file} is the file you pick, {files} is every log in the entry)")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("linked")
+						.short('l')
+						.long("linked")
+						.help("Show which issues on linear link to each entry (cached for up to a day)")
+						.action(ArgAction::SetTrue),
+				)
+		))
+		.subcommand(time_range_args!(
+			Command::new("view")
+				.about("View a specific Entry")
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("entry")
+						.index(1)
+						.required(true)
+						.help(concat!("The entry (e.g. '2021-07-08", sep!(), "161300') or file (e.g. '2021-07-08", sep!(), "161300", sep!(), "details.log.gz') to view the logs for, or a link to either on the server. Entries that aren't on the device are downloaded first"))
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("level")
+						.short('l')
+						.long("level")
+						.help("Only show lines logged at this level or higher ('trace', 'debug', 'info', 'warn', or 'error')")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("context")
+						.short('C')
+						.long("context")
+						.help("With --level, also show this many lines before and after each line that's shown")
+						.takes_value(true)
+						.requires("level"),
+				)
+				.arg(
+					Arg::new("timeline")
+						.short('t')
+						.long("timeline")
+						.help("Show every log in the entry at once, with their lines interleaved in the order they were logged")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("with")
+						.long("with")
+						.help("Open the file with this command instead of the built-in pager, e.g. '$EDITOR' or 'lnav {files}' ({file} is the file you pick, {files} is every log in the entry)")
+						.takes_value(true)
+						.conflicts_with("timeline"),
+				)
+		))
 		.subcommand(
-			subcommand_search!("search", "Search through the logs currently on your device").arg(
-				Arg::new("preview")
-					.short('p')
-					.long("preview")
-					.help("See only an overview of the selected issue, as opposed to viewing any of the logs")
-					.takes_value(false),
-			),
+			Command::new("cat")
+				.about("Print the logs of an entry, without any prompts or paging, so they can be piped to other tools")
+				.arg(
+					Arg::new("entry")
+						.index(1)
+						.required(true)
+						.help(concat!("The entry (e.g. '2021-07-08", sep!(), "161300') to print every file of, or file (e.g. '2021-07-08", sep!(), "161300", sep!(), "details.log.gz') to print, or a link to either on the server"))
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("color")
+						.long("color")
+						.help("When to color the output ('auto', 'always', or 'never'). 'auto' only colors when printing to a terminal and NO_COLOR isn't set")
+						.takes_value(true)
+						.default_value("auto"),
+				),
 		)
 		.subcommand(
-			Command::new("view").about("View a specific Entry").arg(
-				Arg::new("entry")
-					.index(1)
-					.required(true)
-					.help(format!("The entry (e.g. '2021-07-08{c}161300') or file (e.g. '2021-07-08{c}161300{c}details.log.gz') to view the logs for", c = sep_char).as_str())
-					.takes_value(true),
-			),
+			Command::new("diff")
+				.about("Compare two entries, or a file in each, ignoring things like timestamps that change every run")
+				.arg(
+					Arg::new("old")
+						.index(1)
+						.required(true)
+						.help(concat!("The entry (e.g. '2021-07-08", sep!(), "161300') to compare the details and files of, or file (e.g. '2021-07-08", sep!(), "161300", sep!(), "logs.log') to compare, or a link to either on the server"))
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("new")
+						.index(2)
+						.required(true)
+						.help("The entry or file to compare it to. If only one of the two is a file, the file with the same name in the other entry is used")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("side-by-side")
+						.short('s')
+						.long("side-by-side")
+						.help("Show the two files next to each other instead of as a unified diff")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("context")
+						.short('C')
+						.long("context")
+						.help("How many unchanged lines to show around each change")
+						.takes_value(true)
+						.default_value("3"),
+				)
+				.arg(
+					Arg::new("exact")
+						.long("exact")
+						.help("Compare the lines exactly as they are, without ignoring timestamps, addresses, IDs, or the config's 'diff-normalize' regexes")
+						.action(ArgAction::SetTrue),
+				),
+		)
+		.subcommand(
+			subcommand_search!("tui", "Browse the entries and logs on your device in a full-screen interface, starting with the given filter")
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				),
 		)
 		.subcommand(subcommand_search!("prune", "Delete all entries that match the terms"))
 		.subcommand(
 			Command::new("complete")
-				.about("List completions for view command")
+				.about("List completions for entries, or print, install, or uninstall the completion script for your shell")
 				.arg(
 					Arg::new("input")
 						.index(1)
 						.help("The input to get completions for")
 						.takes_value(true)
 				)
+				.arg(
+					Arg::new("values")
+						.long("values")
+						.help("List the values of this ('user', 'version', 'os', 'label', or 'day') from the entries on your device that start with the input, instead of entries")
+						.takes_value(true)
+				)
+				.arg(
+					Arg::new("script")
+						.long("script")
+						.help("Print the completion script for this shell ('bash', 'zsh', 'fish', 'elvish', or 'powershell')")
+						.takes_value(true)
+				)
 				.arg(
 					Arg::new("install")
-						.help("Install completion to your $SHELL")
+						.help("Install completion to the startup file of this shell, or $SHELL if it isn't given")
 						.short('i')
 						.long("install")
+						.takes_value(true)
+						.min_values(0)
+						.max_values(1)
+				)
+				.arg(
+					Arg::new("uninstall")
+						.help("Uninstall completion from the startup file of this shell, or $SHELL if it isn't given")
+						.long("uninstall")
+						.takes_value(true)
+						.min_values(0)
+						.max_values(1)
+						.conflicts_with("install")
+				)
+		)
+		.subcommand(
+			subcommand_search!("listen", "Listen for webhooks from the rageshake server and download matching entries as soon as they're submitted")
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("bind")
+						.long("bind")
+						.help("The address to listen on")
+						.takes_value(true)
+						.default_value("127.0.0.1:9110"),
 				)
+				.arg(
+					Arg::new("secret")
+						.long("secret")
+						.help("The secret that webhooks must send in an `Authorization: Bearer` or `X-Rager-Secret` header (overrides listen-secret in the config file)")
+						.takes_value(true),
+				),
 		)
 		.subcommand(
-			Command::new("linear")
-				.about("Search for logs associated with a specific issue on linear")
+			Command::new("serve")
+				.about("Serve the entries on your device to other copies of rager, just like a rageshake server would")
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("bind")
+						.long("bind")
+						.help("The address to serve on")
+						.takes_value(true)
+						.default_value("127.0.0.1:9120"),
+				)
+				.arg(
+					Arg::new("dir")
+						.long("dir")
+						.help("The directory to serve entries from, instead of the one that rager syncs to")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("username")
+						.long("username")
+						.help("The username that clients must use (the username in the config file by default)")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("password")
+						.long("password")
+						.help("The password that clients must use (the password in the config file by default)")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("no-auth")
+						.long("no-auth")
+						.help("Don't require clients to authenticate at all")
+						.action(ArgAction::SetTrue),
+				),
+		)
+		.subcommand(time_range_args!(
+			subcommand_search!("export", "Bundle entries up with their details and checksums, so they can be shared and imported into someone else's rager")
+				.arg(
+					Arg::new("entries")
+						.index(1)
+						.help("The entries to export (e.g. '2021-07-08/161300'), or links to them on the server. Entries that aren't on the device are downloaded first. Without any, every entry on the device that matches the filter is exported")
+						.takes_value(true)
+						.multiple_values(true),
+				)
+				.arg(
+					Arg::new("output")
+						.short('O')
+						.long("output")
+						.help("Where to write the bundle, e.g. `-O bundle.tar.zst` (rager-export-<date>-<time>.tar.zst in the current directory by default). It's a capital O, since -o is --os")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("overwrite")
+						.long("overwrite")
+						.help("Replace the file at --output if there's already one there, instead of stopping")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				)
+		))
+		.subcommand(
+			Command::new("import")
+				.about("Unpack a bundle made with `export` into the entries on your device, checking that none of it was corrupted along the way")
+				.arg(
+					Arg::new("bundle")
+						.index(1)
+						.required(true)
+						.help("The bundle to import")
+						.takes_value(true),
+				)
+				.arg(
+					Arg::new("overwrite")
+						.long("overwrite")
+						.help("Replace entries that are already on your device, instead of skipping them")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("config")
+						.short('c')
+						.help("The TOML config file to use. Located at ~/.config/rager.toml (on linux) by default")
+						.takes_value(true),
+				),
+		)
+		.subcommand(
+			Command::new("issue")
+				.alias("linear")
+				.about("Search for logs associated with a specific issue on linear, github, gitlab, or jira")
+				.args_conflicts_with_subcommands(true)
+				.subcommand_negates_reqs(true)
 				.arg(
 					Arg::new("issue")
 						.index(1)
-						.help("The issue to search for, in the form of `TEAM-NUM` (e.g. `IOS-1000`)")
+						.help("The issue to search for, e.g. `linear:IOS-1000`, `github:owner/repo#1000`, `gitlab:group/project#1000`, `jira:PROJ-1000`, or a link to it (issues without a tracker are looked for on the default-tracker)")
 						.takes_value(true)
 						.required(true)
 				)
+				.arg(
+					Arg::new("all")
+						.long("all")
+						.help("Download every entry linked to from the issue instead of picking one to view")
+						.action(ArgAction::SetTrue)
+				)
+				.subcommand(
+					Command::new("refs")
+						.about("List the issues on linear that link to an entry")
+						.arg(
+							Arg::new("entry")
+								.index(1)
+								.help("The entry to look for, in the form of `day/time` (e.g. `2021-07-08/161300`) or a link to it on the server")
+								.takes_value(true)
+								.required(true)
+						)
+				)
+				.subcommand(
+					Command::new("create")
+						.about("File an issue for an entry")
+						.arg(
+							Arg::new("entry")
+								.index(1)
+								.help("The entry to file an issue for, in the form of `day/time` (e.g. `2021-07-08/161300`) or a link to it on the server")
+								.takes_value(true)
+								.required(true)
+						)
+						.arg(
+							Arg::new("in")
+								.long("in")
+								.alias("team")
+								.help("Where to file the issue, e.g. `linear:IOS`, `github:owner/repo`, `gitlab:group/project`, or `jira:PROJ` (without a tracker, it's filed on the default-tracker)")
+								.takes_value(true)
+								.required(true)
+						)
+						.arg(
+							Arg::new("label")
+								.short('l')
+								.long("label")
+								.help("A label to give the issue; can be passed multiple times")
+								.takes_value(true)
+								.action(ArgAction::Append)
+						)
+						.arg(
+							Arg::new("assignee")
+								.long("assignee")
+								.help("Who to assign the issue to, by name or email, or 'me'")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("project")
+								.long("project")
+								.help("The name of the project to put the issue in")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("term")
+								.short('t')
+								.long("term")
+								.help("Include the lines of the logs that match this term (rust-flavored regex supported) in the issue")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("dry-run")
+								.long("dry-run")
+								.help("Print the request that would create the issue instead of sending it")
+								.action(ArgAction::SetTrue)
+						)
+				)
+				.subcommand(
+					Command::new("sync")
+						.about("Download every entry linked to from the issues on linear that match all of the given filters")
+						.arg(
+							Arg::new("cycle")
+								.long("cycle")
+								.help("Only issues in this cycle: `current`, `previous`, `next`, or the cycle's number")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("project")
+								.long("project")
+								.help("Only issues in the project with this name")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("label")
+								.short('l')
+								.long("label")
+								.help("Only issues with the label with this name")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("query")
+								.short('q')
+								.long("query")
+								.help("Only issues that contain this text in their title, description, or comments")
+								.takes_value(true)
+						)
+						.arg(
+							Arg::new("team")
+								.long("team")
+								.help("Only issues on the team with this key (e.g. `IOS`)")
+								.takes_value(true)
+						)
+				)
 		)
-		.get_matches();
+}
+
+#[tokio::main]
+async fn main() {
+	let matches = cli().get_matches();
 
 	if let Some(args) = matches.subcommand_matches("sync") {
 		// get the filter and the config file
@@ -195,6 +652,15 @@ async fn main() {
 			}
 		}
 
+		let watch_interval = match args.value_of("watch").map(parse_duration) {
+			Some(None) => {
+				err!("The 'watch' argument must be a duration like '90s', '5m', or '1h'");
+				return;
+			}
+			Some(Some(interval)) => Some(interval),
+			None => None,
+		};
+
 		println!("Starting sync with server...");
 
 		let lim = config.sync_retry_limit.map_or(-1, |l| l as i8);
@@ -209,6 +675,7 @@ async fn main() {
 			started: 0,
 			done: 0,
 			total: 0,
+			completed: Vec::new(),
 		}));
 
 		let mut retried: i8 = 0;
@@ -217,6 +684,7 @@ async fn main() {
 
 		while let Err(err) = result {
 			if lim != 0 && retried >= lim {
+				result = Err(err);
 				break;
 			}
 
@@ -243,31 +711,57 @@ async fn main() {
 				}
 			}
 		}
+
+		let completed = state
+			.lock()
+			.map(|s| s.completed.clone())
+			.unwrap_or_default();
+
+		// only syncing counts as finding new entries; everything else that downloads files is
+		// just fetching something the user asked for
+		hooks::on_new_entries(&conf_arc, &completed).await;
+		hooks::on_sync_complete(&conf_arc, &completed, result.is_ok()).await;
+
+		if let Some(interval) = watch_interval {
+			watch::watch(filter_arc, conf_arc, interval).await;
+		}
 	} else if matches.subcommand_matches("desync").is_some() {
 		sync::desync_all();
 	} else if let Some(args) = matches.subcommand_matches("search") {
 		let view = !args.is_present("preview");
 
-		let (filter, config) =
+		let (mut filter, config) =
 			filter_and_config(args, false).expect("Can't read configuration from given file");
 
-		search::search(filter, config, view).await;
+		filter.range = match time_range(args) {
+			Ok(range) => range,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		let with = args.value_of("with").map(ToOwned::to_owned);
+		let linked = *args.get_one::<bool>("linked").unwrap_or(&false);
+
+		search::search(filter, config, view, with, linked).await;
 	} else if let Some(args) = matches.subcommand_matches("view") {
-		// safe to unwrap 'cause Clap would catch if it wasn't included
-		let day_time = args.value_of("entry").unwrap();
+		let config_file = args.value_of("config").map(ToOwned::to_owned);
 
-		let mut dir = sync_dir();
-		dir.push(day_time);
+		let mut config = config::Config::from_file(&config_file)
+			.map(Arc::new)
+			.expect("Could not read or parse config file");
 
-		// make sure it matches the regex so we can parse it correctly
-		if !dir.as_path().exists() {
-			err!(
-				"Entry/file '{}' does not exist or is not downloaded",
-				day_time
-			);
-			return;
-		}
+		// safe to unwrap 'cause Clap would catch if it wasn't included
+		let day_time = match entry::entry_path(args.value_of("entry").unwrap(), &mut config) {
+			Ok(day_time) => day_time,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
 
+		// entries that aren't on the device yet are downloaded when they're viewed
 		let mut splits = day_time.split('/');
 		let day = splits
 			.next()
@@ -280,53 +774,362 @@ async fn main() {
 
 		let file = splits.next().map(ToOwned::to_owned);
 
-		let config_file = args.value_of("config").map(ToOwned::to_owned);
+		let level = match args.value_of("level").map(level::Level::try_from) {
+			Some(Err(err)) => {
+				err!("{err}");
+				return;
+			}
+			Some(Ok(level)) => Some(level),
+			None => None,
+		};
 
-		let config = config::Config::from_file(&config_file)
-			.map(Arc::new)
-			.expect("Could not read or parse config file");
+		let Ok(context) = args.value_of("context").unwrap_or("0").parse() else {
+			err!("The 'context' argument must be passed in as an integer");
+			return;
+		};
 
-		let entry = entry::Entry::new(day, time, config);
+		let timeline = args.get_one::<bool>("timeline").copied().unwrap_or(false);
 
-		if let Err(err) = view::view(entry, file, None).await {
+		if timeline && file.is_some() {
+			err!(
+				"The timeline shows every file in the entry, so you can't pick one to view with it"
+			);
+			return;
+		}
+
+		let range = match time_range(args) {
+			Ok(range) => range,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		let options = view::ViewOptions {
+			level,
+			context,
+			timeline,
+			range,
+			term: None,
+			with: args.value_of("with").map(ToOwned::to_owned),
+		};
+
+		let mut entry = entry::Entry::new(day, time, config);
+
+		if let Err(err) = view::view(&mut entry, file, None, &options).await {
 			match err {
 				ViewingBeforeDownloading => err!("Cannot view a file before downloading the entry"),
 				FileRetrievalFailed => err!("Failed to determine list of files in entry"),
 				FileReadingFailed => err!("Failed to read specified file"),
 				ViewPagingFailed => err!("Failed to display file on page"),
+				ExternalViewerFailed(err) => err!("{err}"),
 				_ => (),
 			}
 		}
+	} else if let Some(args) = matches.subcommand_matches("cat") {
+		let color = match cat::ColorChoice::try_from(args.value_of("color").unwrap_or("auto")) {
+			Ok(color) => color,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		let config_file = args.value_of("config").map(ToOwned::to_owned);
+		let Some(mut config) = config::Config::from_file(&config_file).map(Arc::new) else {
+			return;
+		};
+
+		// safe to unwrap 'cause Clap would catch if it wasn't included
+		let day_time = match entry::entry_path(args.value_of("entry").unwrap(), &mut config) {
+			Ok(day_time) => day_time,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		let mut splits = day_time.split('/');
+		let (Some(day), Some(time)) = (splits.next(), splits.next()) else {
+			err!("You must enter at least a day and time to print");
+			return;
+		};
+		let file = splits.next();
+
+		let date_time = format!("{day}/{time}");
+		let mut dir = sync_dir();
+		dir.push(&day_time);
+
+		if !dir.exists() {
+			err!("Entry/file '{day_time}' does not exist or is not downloaded");
+			return;
+		}
+
+		match cat::cat(&date_time, file, &config.highlight, color) {
+			// whatever we're piped into (like `head`) is allowed to stop reading early
+			Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => {
+				err!("Failed to print {day_time}: {err}");
+			}
+			_ => (),
+		}
+	} else if let Some(args) = matches.subcommand_matches("diff") {
+		let Ok(context) = args.value_of("context").unwrap_or("3").parse() else {
+			err!("The 'context' argument must be passed in as an integer");
+			return;
+		};
+
+		let options = diff::DiffOptions {
+			side_by_side: args
+				.get_one::<bool>("side-by-side")
+				.copied()
+				.unwrap_or(false),
+			context,
+			exact: args.get_one::<bool>("exact").copied().unwrap_or(false),
+		};
+
+		let config_file = args.value_of("config").map(ToOwned::to_owned);
+		let Some(mut config) = config::Config::from_file(&config_file).map(Arc::new) else {
+			return;
+		};
+
+		// safe to unwrap 'cause Clap would catch if they weren't included
+		let (old, new) = match (
+			entry::entry_path(args.value_of("old").unwrap(), &mut config),
+			entry::entry_path(args.value_of("new").unwrap(), &mut config),
+		) {
+			(Ok(old), Ok(new)) => (old, new),
+			(Err(err), _) | (_, Err(err)) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		if let Err(err) = diff::diff(&old, &new, &config, options) {
+			err!("{err}");
+		}
+	} else if let Some(args) = matches.subcommand_matches("tui") {
+		let config_file = args.value_of("config").map(ToOwned::to_owned);
+		let Some(config) = config::Config::from_file(&config_file) else {
+			return;
+		};
+
+		// the arguments are written out the same way they'd be typed into the filter bar
+		let mut filter = ["user", "os", "when", "before", "after", "term"]
+			.into_iter()
+			.filter_map(|key| args.value_of(key).map(|val| tui::filter_token(key, val)))
+			.collect::<Vec<_>>();
+
+		for flag in ["any", "reject-unsure"] {
+			if *args.get_one::<bool>(flag).unwrap_or(&false) {
+				filter.push(flag.to_owned());
+			}
+		}
+
+		if let Err(err) = tui::tui(filter.join(" "), config).await {
+			err!("{err}");
+		}
 	} else if let Some(args) = matches.subcommand_matches("prune") {
 		// get the filter and the config file
 		let (filter, config) =
 			filter_and_config(args, false).expect("Can't read configuration from given file");
 
 		prune::remove_with_terms(filter, config).await;
+	} else if let Some(args) = matches.subcommand_matches("export") {
+		let (mut filter, config) =
+			filter_and_config(args, false).expect("Can't read configuration from given file");
+
+		filter.range = match time_range(args) {
+			Ok(range) => range,
+			Err(err) => {
+				err!("{err}");
+				return;
+			}
+		};
+
+		let mut config = Arc::new(config);
+		let entry_regex = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}/\d{6}$").unwrap();
+		let mut day_times = Vec::new();
+
+		for arg in args.get_many::<String>("entries").into_iter().flatten() {
+			match entry::entry_path(arg, &mut config) {
+				Ok(day_time) if entry_regex.is_match(&day_time) => day_times.push(day_time),
+				Ok(_) => {
+					err!("Please input each entry in the format of `day/time` (e.g. `2021-07-08/161300`)");
+					return;
+				}
+				Err(err) => {
+					err!("{err}");
+					return;
+				}
+			}
+		}
+
+		let output = args.value_of("output").map_or_else(
+			|| {
+				format!(
+					"rager-export-{}.tar.zst",
+					chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+				)
+			},
+			ToOwned::to_owned,
+		);
+
+		let overwrite = args.get_flag("overwrite");
+
+		if let Err(err) =
+			bundle::export(&day_times, filter, config, output.as_ref(), overwrite).await
+		{
+			err!("Couldn't export entries: {err}");
+		}
+	} else if let Some(args) = matches.subcommand_matches("import") {
+		let config_file = args.value_of("config").map(ToOwned::to_owned);
+		let Some(config) = config::Config::from_file(&config_file) else {
+			return;
+		};
+
+		// safe to unwrap 'cause Clap would catch if it wasn't included
+		let path = args.value_of("bundle").unwrap();
+		let overwrite = args.get_flag("overwrite");
+
+		if let Err(err) = bundle::import(path.as_ref(), overwrite, &Arc::new(config)) {
+			err!("Couldn't import {path}: {err}");
+		}
 	} else if let Some(args) = matches.subcommand_matches("complete") {
-		if args.is_present("install") {
-			completion::install_completion();
+		if let Some(shell) = args.value_of("script") {
+			match completion::Shell::try_from(shell) {
+				Ok(shell) => completion::print_script(shell, &cli()),
+				Err(err) => err!("{err}"),
+			}
+		} else if args.contains_id("install") {
+			completion::install_completion(args.value_of("install"));
+		} else if args.contains_id("uninstall") {
+			completion::uninstall_completion(args.value_of("uninstall"));
+		} else if let Some(kind) = args.value_of("values") {
+			completion::list_values(kind, args.value_of("input").unwrap_or_default());
 		} else if let Some(input) = args.value_of("input") {
 			completion::list_completions(input);
 		}
-	} else if let Some(args) = matches.subcommand_matches("linear") {
-		if let Some(issue) = args.value_of("issue") {
-			let mut issue_splits = issue.split('-');
-			let (Some(team), Some(num)) = (issue_splits.next(), issue_splits.next()) else {
-				err!("Please input the issue in the format of `TEAM-NUM` (e.g. `IOS-1000`)");
+	} else if let Some(args) = matches.subcommand_matches("listen") {
+		// safe to unwrap since it has a default value
+		let Ok(bind) = args
+			.value_of("bind")
+			.unwrap()
+			.parse::<std::net::SocketAddr>()
+		else {
+			err!("The 'bind' argument must be an address like '127.0.0.1:9110'");
+			return;
+		};
+
+		let (filter, config) =
+			filter_and_config(args, true).expect("Can't read configuration from given file");
+
+		let secret = args
+			.value_of("secret")
+			.map(ToOwned::to_owned)
+			.or_else(|| config.listen_secret.clone());
+
+		if secret.is_none() && !bind.ip().is_loopback() {
+			warn!("You're listening on a public address without a secret; anyone who can reach it can make rager download entries");
+		}
+
+		if let Err(err) = listen::listen(bind, secret, Arc::new(filter), Arc::new(config)).await {
+			err!("Couldn't listen on {bind}: {err}");
+		}
+	} else if let Some(args) = matches.subcommand_matches("serve") {
+		// safe to unwrap since it has a default value
+		let Ok(bind) = args
+			.value_of("bind")
+			.unwrap()
+			.parse::<std::net::SocketAddr>()
+		else {
+			err!("The 'bind' argument must be an address like '127.0.0.1:9120'");
+			return;
+		};
+
+		let auth = if args.get_flag("no-auth") {
+			None
+		} else {
+			let config_file = args.value_of("config").map(ToOwned::to_owned);
+			let Some(config) = config::Config::from_file(&config_file) else {
+				return;
+			};
+
+			Some((
+				args.value_of("username")
+					.map_or(config.username, ToOwned::to_owned),
+				args.value_of("password")
+					.map_or(config.password, ToOwned::to_owned),
+			))
+		};
+
+		let dir = args
+			.value_of("dir")
+			.map_or_else(sync_dir, std::path::PathBuf::from);
+
+		if let Err(err) = serve::serve(bind, dir, auth).await {
+			err!("Couldn't serve on {bind}: {err}");
+		}
+	} else if let Some(args) = matches.subcommand_matches("issue") {
+		if let Some(refs_args) = args.subcommand_matches("refs") {
+			let mut config = config::Config::from_file(&None)
+				.map(Arc::new)
+				.expect("Couldn't create config from default file");
+
+			let Some(day_time) = entry_arg(refs_args, &mut config) else {
 				return;
 			};
 
-			let Ok(num) = num.parse() else {
-				err!("Please input the issue in the format of `TEAM-NUM` (e.g. `IOS-1000`)");
+			if let Err(err) = tracker::linear::refs(&day_time, &config).await {
+				err!("Error finding issues that link to {day_time}: {:?}", err);
+			}
+		} else if let Some(create_args) = args.subcommand_matches("create") {
+			let mut config = config::Config::from_file(&None)
+				.map(Arc::new)
+				.expect("Couldn't create config from default file");
+
+			let Some(day_time) = entry_arg(create_args, &mut config) else {
 				return;
 			};
 
+			let options = tracker::CreateOptions {
+				// safe to unwrap 'cause Clap would catch if it wasn't included
+				target: create_args.value_of("in").unwrap().to_owned(),
+				labels: create_args
+					.get_many::<String>("label")
+					.map(|l| l.cloned().collect())
+					.unwrap_or_default(),
+				assignee: create_args.value_of("assignee").map(ToOwned::to_owned),
+				project: create_args.value_of("project").map(ToOwned::to_owned),
+				term: create_args.value_of("term").map(ToOwned::to_owned),
+				dry_run: *create_args.get_one::<bool>("dry-run").unwrap_or(&false),
+			};
+
+			if let Err(err) = tracker::create(&day_time, options, &config).await {
+				err!("Error creating issue for {day_time}: {err}");
+			}
+		} else if let Some(sync_args) = args.subcommand_matches("sync") {
 			let config =
 				config::Config::from_file(&None).expect("Couldn't create config from default file");
 
-			if let Err(err) = linear::find_issue(team, num, config).await {
-				err!("Error finding linear issue: {:?}", err);
+			let options = tracker::linear::SyncOptions {
+				cycle: sync_args.value_of("cycle").map(ToOwned::to_owned),
+				project: sync_args.value_of("project").map(ToOwned::to_owned),
+				label: sync_args.value_of("label").map(ToOwned::to_owned),
+				query: sync_args.value_of("query").map(ToOwned::to_owned),
+				team: sync_args.value_of("team").map(ToOwned::to_owned),
+			};
+
+			if let Err(err) = tracker::linear::sync(&options, Arc::new(config)).await {
+				err!("Error syncing entries from linear: {err}");
+			}
+		} else if let Some(issue) = args.value_of("issue") {
+			let config =
+				config::Config::from_file(&None).expect("Couldn't create config from default file");
+
+			let all = *args.get_one::<bool>("all").unwrap_or(&false);
+
+			if let Err(err) = tracker::find_issue(issue, config, all).await {
+				err!("Error finding issue: {err}");
 			}
 		}
 	} else {
@@ -334,6 +1137,27 @@ async fn main() {
 	}
 }
 
+// the `entry` argument (which can also be a link to it), as long as it's a `day/time` like issues
+// would link to
+fn entry_arg(args: &clap::ArgMatches, config: &mut Arc<config::Config>) -> Option<String> {
+	// safe to unwrap 'cause Clap would catch if it wasn't included
+	let day_time = match entry::entry_path(args.value_of("entry").unwrap(), config) {
+		Ok(day_time) => day_time,
+		Err(err) => {
+			err!("{err}");
+			return None;
+		}
+	};
+
+	let entry_regex = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}/\d{6}$").unwrap();
+	if !entry_regex.is_match(&day_time) {
+		err!("Please input the entry in the format of `day/time` (e.g. `2021-07-08/161300`)");
+		return None;
+	}
+
+	Some(day_time)
+}
+
 pub fn filter_and_config(
 	terms: &clap::ArgMatches,
 	syncing: bool,
@@ -363,9 +1187,13 @@ pub fn filter_and_config(
 			.expect("OS specified in config file is not valid")
 	});
 
-	let sync_since_last: bool = *terms
-		.get_one::<bool>("sync-since-last-day")
-		.unwrap_or(&true);
+	// not every subcommand that syncs has this flag (e.g. `listen`), so don't panic if it's missing
+	let sync_since_last: bool = terms
+		.try_get_one::<bool>("sync-since-last-day")
+		.ok()
+		.flatten()
+		.copied()
+		.unwrap_or(false);
 
 	let ret_filter = if syncing {
 		let mut ret_filter = filter::Filter::from_config_file(&config_file);
@@ -408,6 +1236,7 @@ pub fn filter_and_config(
 			when,
 			user,
 			term,
+			range: timestamp::TimeRange::default(),
 			any,
 			reject_unsure,
 		}
@@ -416,23 +1245,55 @@ pub fn filter_and_config(
 	Some((ret_filter, config))
 }
 
+// the range of time given with `--from`/`--to` or `--around`/`--window`
+fn time_range(args: &clap::ArgMatches) -> Result<timestamp::TimeRange, String> {
+	let window = args
+		.value_of("window")
+		.map(|w| {
+			parse_duration(w)
+				.ok_or_else(|| format!("The window '{w}' must be a duration like '90s' or '5m'"))
+		})
+		.transpose()?;
+
+	timestamp::TimeRange::new(
+		args.value_of("from"),
+		args.value_of("to"),
+		args.value_of("around"),
+		window,
+		args.get_one::<bool>("local").copied().unwrap_or(false),
+	)
+}
+
 async fn req_with_auth<U: reqwest::IntoUrl>(
 	url: U,
 	conf: &config::Config,
 ) -> reqwest::Result<reqwest::Response> {
 	let client = reqwest::Client::new();
 
-	let req = client
+	let mut req = client
 		.get(url)
-		.basic_auth(&conf.username, Some(&conf.password))
-		.build()?;
+		.basic_auth(&conf.username, Some(&conf.password));
 
-	client.execute(req).await
+	if let Some(timeout) = conf.request_timeout {
+		req = req.timeout(timeout);
+	}
+
+	// we don't want to treat the body of a 404 or 500 as the contents of a file
+	client.execute(req.build()?).await?.error_for_status()
+}
+
+// where rager keeps everything it downloads and caches. `RAGER_DATA_DIR` moves it somewhere else,
+// since `XDG_DATA_HOME` only does that on linux
+fn data_dir() -> std::path::PathBuf {
+	std::env::var_os("RAGER_DATA_DIR").map_or_else(
+		// documentation says this always returns some so we can safely unwrap
+		|| dirs::data_dir().unwrap(),
+		std::path::PathBuf::from,
+	)
 }
 
 fn sync_dir() -> std::path::PathBuf {
-	// documentation says this always returns some so we can safely unwrap
-	let mut sync_dir = dirs::data_dir().unwrap();
+	let mut sync_dir = data_dir();
 	sync_dir.push("rageshake");
 	sync_dir
 }
@@ -445,6 +1306,26 @@ fn get_links(output: &str) -> Vec<&str> {
 		.collect::<Vec<&str>>()
 }
 
+// Parses a duration like `30`, `90s`, `5m`, or `1h` (bare numbers are seconds)
+pub fn parse_duration(input: &str) -> Option<std::time::Duration> {
+	let input = input.trim();
+	let split = input
+		.find(|c: char| !c.is_ascii_digit())
+		.unwrap_or(input.len());
+
+	let (num, unit) = input.split_at(split);
+	let num = num.parse::<u64>().ok()?;
+
+	let secs = match unit.trim() {
+		"" | "s" => num,
+		"m" => num * 60,
+		"h" => num * 60 * 60,
+		_ => return None,
+	};
+
+	(secs > 0).then(|| std::time::Duration::from_secs(secs))
+}
+
 // Gets the most recent day that we actually synced during
 fn get_last_synced_day() -> Option<[u16; 3]> {
 	// iterate over all the entries we've downloaded
@@ -0,0 +1,376 @@
+// A stand-in for a rageshake server (and the issue trackers' APIs) that tests can sync against,
+// plus a throwaway config and sync directory for each test to use.
+
+use crate::{config::Config, serve::listing_html};
+use base64::Engine;
+use hyper::{
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+	convert::Infallible,
+	path::PathBuf,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::Duration,
+};
+
+pub const USERNAME: &str = "rager";
+pub const PASSWORD: &str = "hunter2";
+pub const LINEAR_TOKEN: &str = "lin_api_test";
+
+// the sync directory comes from the environment, so only one test can use it at a time
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Copy, Debug)]
+pub enum Failure {
+	// respond with this status instead of the real response
+	Status(u16),
+	// don't respond until long after the client should've given up
+	Timeout,
+	// promise the whole body but only send half of it
+	Truncated,
+}
+
+#[derive(Default)]
+struct MockState {
+	// the files on the server, keyed by their path under `/api/listing/`
+	files: BTreeMap<String, String>,
+	// failures to inject, keyed by path, used up one per request
+	failures: HashMap<String, VecDeque<Failure>>,
+	// the paths of every listing request that was made, in order
+	requests: Vec<String>,
+	// what to respond to graphql requests with, once the queued responses are used up
+	graphql: String,
+	graphql_queue: VecDeque<String>,
+	// the `Authorization` header and body of every graphql request
+	graphql_requests: Vec<(Option<String>, String)>,
+	// what to respond to requests to the trackers' REST APIs with, keyed by method and path (e.g.
+	// `GET /repos/owner/repo/issues/1`)
+	api: HashMap<String, String>,
+	// extra headers to send along with some of those responses, keyed the same way
+	api_headers: HashMap<String, Vec<(String, String)>>,
+	// the method and path (with any query), credentials, and body of every request to them
+	api_requests: Vec<(String, Option<String>, String)>,
+}
+
+pub struct MockServer {
+	pub url: String,
+	state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+	pub async fn start() -> MockServer {
+		let state: Arc<Mutex<MockState>> = Arc::default();
+
+		let svc_state = state.clone();
+		let make_svc = make_service_fn(move |_| {
+			let state = svc_state.clone();
+			async move {
+				Ok::<_, Infallible>(service_fn(move |req| {
+					let state = state.clone();
+					async move { Ok::<_, Infallible>(handle(req, &state).await) }
+				}))
+			}
+		});
+
+		let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+		let url = format!("http://{}", server.local_addr());
+
+		tokio::spawn(server);
+
+		MockServer { url, state }
+	}
+
+	fn state(&self) -> MutexGuard<'_, MockState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	pub fn add_file(&self, path: &str, contents: &str) {
+		self.state()
+			.files
+			.insert(path.to_owned(), contents.to_owned());
+	}
+
+	// adds an entry with a details file (made from the given lines) and the given log files
+	pub fn add_entry(&self, date_time: &str, details: &[&str], logs: &[(&str, &str)]) {
+		let mut details = details.join("\n");
+		details.push('\n');
+
+		self.add_file(&format!("{date_time}/{}", crate::DETAILS), &details);
+
+		for (name, contents) in logs {
+			self.add_file(&format!("{date_time}/{name}"), contents);
+		}
+	}
+
+	// makes the next request to `path` (e.g. `""` for the list of days, or `2021-07-08/161300`)
+	// fail; calling this multiple times queues up multiple failures
+	pub fn fail(&self, path: &str, failure: Failure) {
+		self.state()
+			.failures
+			.entry(path.to_owned())
+			.or_default()
+			.push_back(failure);
+	}
+
+	pub fn requests(&self) -> Vec<String> {
+		self.state().requests.clone()
+	}
+
+	pub fn set_graphql_response(&self, body: &str) {
+		self.state().graphql = body.to_owned();
+	}
+
+	// responds to the next graphql request with `body`, before going back to the usual response;
+	// calling this multiple times queues up multiple responses
+	pub fn queue_graphql_response(&self, body: &str) {
+		self.state().graphql_queue.push_back(body.to_owned());
+	}
+
+	pub fn graphql_requests(&self) -> Vec<(Option<String>, String)> {
+		self.state().graphql_requests.clone()
+	}
+
+	// `route` can include a query (e.g. `GET /repos/owner/repo/issues/1/comments?page=2`), in which
+	// case it's only used for requests with exactly that query
+	pub fn set_api_response(&self, route: &str, body: &str) {
+		self.state().api.insert(route.to_owned(), body.to_owned());
+	}
+
+	pub fn set_api_headers(&self, route: &str, headers: &[(&str, &str)]) {
+		self.state().api_headers.insert(
+			route.to_owned(),
+			headers
+				.iter()
+				.map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+				.collect(),
+		);
+	}
+
+	pub fn api_requests(&self) -> Vec<(String, Option<String>, String)> {
+		self.state().api_requests.clone()
+	}
+}
+
+async fn handle(req: Request<Body>, state: &Mutex<MockState>) -> Response<Body> {
+	macro_rules! respond {
+		($status:expr, $body:expr) => {
+			return Response::builder()
+				.status($status)
+				.body(Body::from($body))
+				.unwrap_or_default()
+		};
+	}
+
+	let auth = req
+		.headers()
+		.get(hyper::header::AUTHORIZATION)
+		.and_then(|h| h.to_str().ok())
+		.map(ToOwned::to_owned);
+
+	if req.method() == Method::POST && req.uri().path() == "/graphql" {
+		let body = hyper::body::to_bytes(req.into_body())
+			.await
+			.map(|b| String::from_utf8_lossy(&b).to_string())
+			.unwrap_or_default();
+
+		let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+		state.graphql_requests.push((auth, body));
+
+		let response = match state.graphql_queue.pop_front() {
+			Some(response) => response,
+			None => state.graphql.clone(),
+		};
+		respond!(StatusCode::OK, response);
+	}
+
+	let method_and_path = format!(
+		"{} {}",
+		req.method(),
+		req.uri()
+			.path_and_query()
+			.map(ToString::to_string)
+			.unwrap_or_default()
+	);
+	let route = format!("{} {}", req.method(), req.uri().path());
+	let response = {
+		let state = state.lock().unwrap_or_else(PoisonError::into_inner);
+		[&method_and_path, &route].into_iter().find_map(|route| {
+			let body = state.api.get(route)?.clone();
+			let headers = state.api_headers.get(route).cloned().unwrap_or_default();
+			Some((body, headers))
+		})
+	};
+
+	if let Some((response, headers)) = response {
+		// gitlab has its own header for tokens
+		let credentials = auth.or_else(|| {
+			req.headers()
+				.get("PRIVATE-TOKEN")
+				.and_then(|h| h.to_str().ok())
+				.map(ToOwned::to_owned)
+		});
+		let body = hyper::body::to_bytes(req.into_body())
+			.await
+			.map(|b| String::from_utf8_lossy(&b).to_string())
+			.unwrap_or_default();
+
+		let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+		state
+			.api_requests
+			.push((method_and_path, credentials, body));
+
+		return headers
+			.into_iter()
+			.fold(Response::builder(), |builder, (name, value)| {
+				builder.header(name, value)
+			})
+			.status(StatusCode::OK)
+			.body(Body::from(response))
+			.unwrap_or_default();
+	}
+
+	let expected = format!(
+		"Basic {}",
+		base64::engine::general_purpose::STANDARD.encode(format!("{USERNAME}:{PASSWORD}"))
+	);
+
+	if auth.as_deref() != Some(expected.as_str()) {
+		respond!(StatusCode::UNAUTHORIZED, "Unauthorized");
+	}
+
+	let Some(path) = req.uri().path().strip_prefix("/api/listing") else {
+		respond!(StatusCode::NOT_FOUND, "Not found");
+	};
+	let path = path.trim_matches('/').to_owned();
+
+	let (failure, body) = {
+		let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+		state.requests.push(path.clone());
+
+		let failure = state.failures.get_mut(&path).and_then(VecDeque::pop_front);
+		(failure, listing_or_file(&state.files, &path))
+	};
+
+	match (failure, body) {
+		(Some(Failure::Status(status)), _) => respond!(
+			StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+			"Injected failure"
+		),
+		(Some(Failure::Timeout), Some(body)) => {
+			tokio::time::sleep(Duration::from_secs(10)).await;
+			respond!(StatusCode::OK, body);
+		}
+		(Some(Failure::Truncated), Some(body)) => {
+			let half = body[..body.len() / 2].to_owned();
+
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(hyper::header::CONTENT_LENGTH, body.len())
+				.body(Body::from(half))
+				.unwrap_or_default()
+		}
+		(_, Some(body)) => respond!(StatusCode::OK, body),
+		(_, None) => respond!(StatusCode::NOT_FOUND, "Not found"),
+	}
+}
+
+// if `path` is a file, its contents; if it's a directory, the listing of it
+fn listing_or_file(files: &BTreeMap<String, String>, path: &str) -> Option<String> {
+	if let Some(contents) = files.get(path) {
+		return Some(contents.clone());
+	}
+
+	let prefix = if path.is_empty() {
+		String::new()
+	} else {
+		format!("{path}/")
+	};
+
+	let children = files
+		.keys()
+		.filter_map(|f| f.strip_prefix(&prefix))
+		.map(|rest| match rest.split_once('/') {
+			Some((dir, _)) => format!("{dir}/"),
+			None => rest.to_owned(),
+		})
+		.collect::<BTreeSet<String>>();
+
+	(!children.is_empty() || path.is_empty())
+		.then(|| listing_html(&children.into_iter().collect::<Vec<_>>()))
+}
+
+// a config pointing at the mock server, and an empty sync directory
+pub struct TestEnv {
+	_lock: MutexGuard<'static, ()>,
+	// kept around so the directory isn't deleted until the test is done with it, and for any other
+	// config files the test adds next to the first one
+	dir: tempfile::TempDir,
+	pub config: Arc<Config>,
+}
+
+impl TestEnv {
+	// `extra` is appended to the config file, for any other keys the test needs
+	pub fn new(server: &MockServer, extra: &str) -> TestEnv {
+		TestEnv::with_url(&server.url, extra)
+	}
+
+	// the same, but for a server at `url` that isn't the mock one
+	pub fn with_url(url: &str, extra: &str) -> TestEnv {
+		let lock = STORE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+		let dir = tempfile::tempdir().expect("Couldn't create temporary directory");
+
+		std::env::set_var("RAGER_DATA_DIR", dir.path().join("data"));
+
+		let config_file = dir.path().join("rager.toml");
+		let contents = format!(
+			"server = \"{url}\"\n\
+			username = \"{USERNAME}\"\n\
+			password = \"{PASSWORD}\"\n\
+			threads = 4\n\
+			request-timeout = 1\n\
+			linear-token = \"{LINEAR_TOKEN}\"\n\
+			linear-api-url = \"{url}/graphql\"\n\
+			{extra}\n"
+		);
+		std::fs::write(&config_file, contents).expect("Couldn't write config file");
+
+		let config = Config::from_file(&config_file.to_str().map(ToOwned::to_owned))
+			.expect("Couldn't parse test config");
+
+		TestEnv {
+			_lock: lock,
+			dir,
+			config: Arc::new(config),
+		}
+	}
+
+	// writes another config file next to the test one, like the one someone would keep for a
+	// second server
+	pub fn add_config(&self, name: &str, server: &str) {
+		let contents = format!(
+			"server = \"{server}\"\n\
+			username = \"{USERNAME}\"\n\
+			password = \"{PASSWORD}\"\n\
+			threads = 4\n"
+		);
+		std::fs::write(self.dir.path().join(name), contents).expect("Couldn't write config file");
+	}
+
+	// the path that the given `day/time[/file]` would be synced to
+	pub fn store_path(&self, path: &str) -> PathBuf {
+		let mut dir = crate::sync_dir();
+		dir.push(path);
+		dir
+	}
+
+	// puts a file in the sync directory as if it had already been synced
+	pub fn add_local_file(&self, path: &str, contents: &str) {
+		let path = self.store_path(path);
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent).expect("Couldn't create entry directory");
+		}
+		std::fs::write(path, contents).expect("Couldn't write local file");
+	}
+}
@@ -1,11 +1,20 @@
 use crate::{config::Config, entry::Entry, filter::Filter, *};
 use requestty::{question::*, OnEsc, PromptModule};
 use std::{
+	collections::{hash_map, HashMap},
 	fs,
 	sync::{Arc, Mutex},
 };
 
-pub async fn search(filter: Filter, config: Config, view: bool) {
+// `with` is the command to open the chosen file with, if not the built-in pager, and `linked`
+// is whether to show the linear issues that link to each entry
+pub async fn search(
+	filter: Filter,
+	config: Config,
+	view: bool,
+	with: Option<String>,
+	linked: bool,
+) {
 	let conf_arc = Arc::new(config);
 	let filter_arc = Arc::new(filter);
 
@@ -28,50 +37,94 @@ pub async fn search(filter: Filter, config: Config, view: bool) {
 		}
 	}
 
-	let descriptions = finds
-		.iter_mut()
-		.map(|e| e.selectable_description())
-		.collect::<Vec<String>>();
+	let mut choices = finds
+		.iter()
+		.map(Entry::selectable_description)
+		.collect::<Vec<_>>();
+
+	if linked {
+		match tracker::linear::linked_issues(&finds, &conf_arc).await {
+			Ok(issues) => {
+				for (choice, issues) in choices.iter_mut().zip(issues) {
+					if !issues.is_empty() {
+						let ids = issues.iter().map(|i| i.identifier.as_str());
+						choice.push_str(&format!(" [{}]", ids.collect::<Vec<_>>().join(", ")));
+					}
+				}
+			}
+			Err(err) => err!("Couldn't check which entries are linked to from linear: {err}"),
+		}
+	}
 
-	let question = Question::select("")
-		.message("Matching Entries:")
-		.choices(descriptions)
-		.on_esc(OnEsc::Terminate)
-		.default(0)
-		.build();
+	let options = view::ViewOptions {
+		range: filter_arc.range.clone(),
+		term: filter_arc.term.clone(),
+		with,
+		..view::ViewOptions::default()
+	};
 
-	let choice = PromptModule::new(vec![question])
-		.prompt_all()
-		.ok()
-		.and_then(|p| p[""].as_list_item().map(|l| l.index));
+	// the files in each entry that contain the term, so they're only looked for the first time
+	// each entry is picked
+	let mut matching_files: HashMap<usize, Option<Vec<String>>> = HashMap::new();
+	let mut last = 0;
+
+	// keep coming back to the list after each entry until they escape out of it
+	loop {
+		let Some(ch) = pick_entry(choices.clone(), "Matching Entries:", last) else {
+			return;
+		};
 
-	if let Some(ch) = choice {
-		let mut entry = finds.remove(ch);
+		last = ch;
+		let entry = &mut finds[ch];
 
-		if view {
-			// get the entries that contain the specified term so we can pass it to the view fn
-			let entries = match filter_arc.term.as_ref() {
-				Some(term) => entry.files_containing_term(term).await.ok(),
+		if !view {
+			println!("{}", entry.description());
+			return;
+		}
+
+		let files = match matching_files.entry(ch) {
+			hash_map::Entry::Occupied(files) => files.into_mut(),
+			// get the files that contain the specified term so we can pass them to the view fn
+			hash_map::Entry::Vacant(slot) => slot.insert(match filter_arc.term.as_ref() {
+				Some(term) => entry
+					.files_containing_term(term, &filter_arc.range)
+					.await
+					.ok(),
 				_ => None,
-			};
+			}),
+		};
 
-			if let Err(err) = view::view(entry, None, entries).await {
-				match err {
-					ViewingBeforeDownloading => {
-						err!("Cannot view a file before downloading the entry");
-					}
-					FileRetrievalFailed => err!("Failed to determine list of files in entry"),
-					FileReadingFailed => err!("Failed to read specified file"),
-					ViewPagingFailed => err!("Failed to display file on page"),
-					_ => (),
+		if let Err(err) = view::view(entry, None, files.as_ref(), &options).await {
+			match err {
+				ViewingBeforeDownloading => {
+					err!("Cannot view a file before downloading the entry");
 				}
+				FileRetrievalFailed => err!("Failed to determine list of files in entry"),
+				FileReadingFailed => err!("Failed to read specified file"),
+				ViewPagingFailed => err!("Failed to display file on page"),
+				ExternalViewerFailed(err) => err!("{err}"),
+				_ => (),
 			}
-		} else {
-			println!("{}", entry.description());
 		}
 	}
 }
 
+// asks the user which of the entries (described by `choices`) they'd like, starting on the one at
+// `default`. Returns the index of the entry
+pub fn pick_entry(choices: Vec<String>, message: &str, default: usize) -> Option<usize> {
+	let question = Question::select("")
+		.message(message)
+		.choices(choices)
+		.on_esc(OnEsc::Terminate)
+		.default(default)
+		.build();
+
+	PromptModule::new(vec![question])
+		.prompt_all()
+		.ok()
+		.and_then(|p| p[""].as_list_item().map(|l| l.index))
+}
+
 pub async fn entries_with_filter(filter: &Arc<Filter>, config: &Arc<Config>) -> Option<Vec<Entry>> {
 	let sync_dir = sync_dir();
 
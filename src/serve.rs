@@ -0,0 +1,265 @@
+use base64::Engine;
+use hyper::{
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+	convert::Infallible,
+	fs,
+	future::Future,
+	net::SocketAddr,
+	path::{Component, Path, PathBuf},
+	sync::Arc,
+};
+
+const LISTING_PREFIX: &str = "/api/listing";
+
+// serves the entries in `root` with the same layout and listing format as a rageshake server,
+// so that other copies of rager can sync from it
+pub async fn serve(
+	bind: SocketAddr,
+	root: PathBuf,
+	auth: Option<(String, String)>,
+) -> Result<(), hyper::Error> {
+	let (addr, server) = start(bind, root, auth)?;
+
+	println!(
+		"📡 Serving entries at \x1b[1mhttp://{addr}\x1b[0m (point `server` in another rager config here)"
+	);
+
+	server.await
+}
+
+// binds to `bind` (whose port can be 0, for any free one), and gives back the address that was
+// bound along with the server, which doesn't do anything until it's awaited
+fn start(
+	bind: SocketAddr,
+	root: PathBuf,
+	auth: Option<(String, String)>,
+) -> Result<(SocketAddr, impl Future<Output = Result<(), hyper::Error>>), hyper::Error> {
+	// we compare against the whole header instead of decoding what they send us
+	let expected_auth = auth.map(|(user, pass)| {
+		format!(
+			"Basic {}",
+			base64::engine::general_purpose::STANDARD.encode(format!("{user}:{pass}"))
+		)
+	});
+
+	let root = Arc::new(root);
+	let expected_auth = Arc::new(expected_auth);
+
+	let make_svc = make_service_fn(move |_| {
+		let root = root.clone();
+		let expected_auth = expected_auth.clone();
+
+		async move {
+			Ok::<_, Infallible>(service_fn(move |req| {
+				let root = root.clone();
+				let expected_auth = expected_auth.clone();
+
+				async move { Ok::<_, Infallible>(handle(&req, &root, expected_auth.as_deref())) }
+			}))
+		}
+	});
+
+	let server = Server::try_bind(&bind)?.serve(make_svc);
+	Ok((server.local_addr(), server))
+}
+
+fn handle(req: &Request<Body>, root: &Path, expected_auth: Option<&str>) -> Response<Body> {
+	macro_rules! respond {
+		($status:expr, $body:expr) => {
+			return Response::builder()
+				.status($status)
+				.body(Body::from($body))
+				.unwrap_or_default()
+		};
+	}
+
+	if let Some(expected) = expected_auth {
+		let given = req
+			.headers()
+			.get(hyper::header::AUTHORIZATION)
+			.and_then(|h| h.to_str().ok());
+
+		if given != Some(expected) {
+			return Response::builder()
+				.status(StatusCode::UNAUTHORIZED)
+				.header(hyper::header::WWW_AUTHENTICATE, "Basic realm=\"rager\"")
+				.body(Body::from("Unauthorized"))
+				.unwrap_or_default();
+		}
+	}
+
+	if req.method() != Method::GET {
+		respond!(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported");
+	}
+
+	let Some(rest) = req.uri().path().strip_prefix(LISTING_PREFIX) else {
+		respond!(StatusCode::NOT_FOUND, "Not found");
+	};
+
+	let rel = Path::new(rest.trim_start_matches('/'));
+
+	// don't let anyone climb out of the sync directory with `..` or absolute paths
+	if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+		respond!(StatusCode::NOT_FOUND, "Not found");
+	}
+
+	let path = root.join(rel);
+
+	if path.is_dir() {
+		match listing(&path) {
+			Some(html) => respond!(StatusCode::OK, html),
+			None => respond!(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't read directory"),
+		}
+	}
+
+	match fs::read(&path) {
+		Ok(contents) => respond!(StatusCode::OK, contents),
+		Err(_) => respond!(StatusCode::NOT_FOUND, "Not found"),
+	}
+}
+
+// builds the same sort of listing that the rageshake server sends back, with one link per line,
+// which is what `get_links` expects
+fn listing(dir: &Path) -> Option<String> {
+	let mut names = fs::read_dir(dir)
+		.ok()?
+		.filter_map(Result::ok)
+		.filter_map(|e| {
+			let name = e.file_name().to_str()?.to_owned();
+
+			Some(if e.path().is_dir() {
+				name + "/"
+			} else {
+				name
+			})
+		})
+		.collect::<Vec<String>>();
+
+	names.sort();
+
+	Some(listing_html(&names))
+}
+
+pub fn listing_html(names: &[String]) -> String {
+	let links = names
+		.iter()
+		.map(|n| format!("<a href=\"{n}\">{n}</a>\n"))
+		.collect::<String>();
+
+	format!("<pre>\n{links}</pre>\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		mock::{TestEnv, PASSWORD, USERNAME},
+		sync::{sync_logs, SyncTracker},
+	};
+	use std::sync::Mutex;
+
+	// serves a store with a couple of entries in it, giving back its url
+	fn serve_store(root: &Path) -> String {
+		for (path, contents) in [
+			(format!("2021-07-08/161300/{}", crate::DETAILS), "crashed\n"),
+			("2021-07-08/161300/logs.log".to_owned(), "first log"),
+			("2021-07-09/010203/console.log".to_owned(), "ios log"),
+		] {
+			let path = root.join(path);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, contents).unwrap();
+		}
+
+		let (addr, server) = start(
+			([127, 0, 0, 1], 0).into(),
+			root.to_owned(),
+			Some((USERNAME.to_owned(), PASSWORD.to_owned())),
+		)
+		.unwrap();
+		tokio::spawn(server);
+
+		format!("http://{addr}")
+	}
+
+	#[tokio::test]
+	async fn syncs_from_another_store() {
+		let store = tempfile::tempdir().unwrap();
+		let url = serve_store(store.path());
+		let env = TestEnv::with_url(&url, "");
+
+		let state = Arc::new(Mutex::new(SyncTracker {
+			prefix: "Testing:".to_owned(),
+			started: 0,
+			done: 0,
+			total: 0,
+			completed: Vec::new(),
+		}));
+		sync_logs(&Arc::default(), &env.config, &state)
+			.await
+			.expect("Sync failed");
+
+		for (path, contents) in [
+			("2021-07-08/161300/logs.log", "first log"),
+			("2021-07-09/010203/console.log", "ios log"),
+		] {
+			assert_eq!(fs::read_to_string(env.store_path(path)).unwrap(), contents);
+		}
+	}
+
+	#[tokio::test]
+	async fn needs_auth_and_stays_in_the_store() {
+		// only `store` is served, so what's next to it has to stay out of reach
+		let dir = tempfile::tempdir().unwrap();
+		fs::write(dir.path().join("secret"), "hunter2").unwrap();
+		let url = serve_store(&dir.path().join("store"));
+		let client = reqwest::Client::new();
+
+		let res = client
+			.get(format!("{url}/api/listing/"))
+			.send()
+			.await
+			.unwrap();
+		assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+		let get = |path: &str| {
+			client
+				.get(format!("{url}{path}"))
+				.basic_auth(USERNAME, Some(PASSWORD))
+				.send()
+		};
+
+		let res = get("/api/listing/2021-07-08/").await.unwrap();
+		assert_eq!(res.status(), reqwest::StatusCode::OK);
+		assert_eq!(
+			res.text().await.unwrap(),
+			listing_html(&["161300/".to_owned()])
+		);
+
+		// `..` has to get to the server as it is, which it wouldn't through a `Url`
+		for path in [
+			"/api/listing/../secret",
+			"/api/listing/2021-07-08/../../secret",
+		] {
+			let mut stream = tokio::net::TcpStream::connect(url.trim_start_matches("http://"))
+				.await
+				.unwrap();
+			let auth =
+				base64::engine::general_purpose::STANDARD.encode(format!("{USERNAME}:{PASSWORD}"));
+			let req = format!(
+				"GET {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {auth}\r\nConnection: close\r\n\r\n"
+			);
+
+			tokio::io::AsyncWriteExt::write_all(&mut stream, req.as_bytes())
+				.await
+				.unwrap();
+			let mut res = String::new();
+			tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut res)
+				.await
+				.unwrap();
+			assert!(res.starts_with("HTTP/1.1 404"), "{path}: {res}");
+		}
+	}
+}
@@ -208,6 +208,10 @@ pub async fn sync_logs(
 			}
 
 			if entry_ok {
+				// only entries that had no logs here before this sync count as new, not old ones
+				// that this is just finishing off
+				let new_entry = !entry.has_logs();
+
 				// Before now, the filter check just loaded in the list of files
 				// from the device, but since this is an entry we want, we must force sync them
 				if let Err(err) = entry.retrieve_file_list(true).await {
@@ -231,6 +235,7 @@ pub async fn sync_logs(
 								helper.to_download.push(Download {
 									subdir: format!("{}/{}", entry.date_time(), f),
 									is_cache: false,
+									new_entry,
 									state: time_state.clone(),
 									config: time_conf.clone(),
 								});
@@ -247,6 +252,7 @@ pub async fn sync_logs(
 						helper.to_download.push(Download {
 							subdir: format!("{}/{}", entry.date_time(), crate::DETAILS),
 							is_cache: true,
+							new_entry: false,
 							state: time_state.clone(),
 							config: time_conf.clone(),
 						});
@@ -306,6 +312,14 @@ pub async fn download_files(
 		state.total = files.len();
 	}
 
+	// the new entries that we're downloading files for, so that we can tell which ones are
+	// complete once we're done
+	let mut entries = files
+		.iter()
+		.filter(|f| f.new_entry)
+		.filter_map(|f| f.subdir.rsplit_once('/').map(|(e, _)| e.to_owned()))
+		.collect::<Vec<String>>();
+
 	let failed_files: Arc<Mutex<Vec<Download>>> = Arc::default();
 
 	// iterate through all the files that we need to download and download them.
@@ -391,12 +405,28 @@ pub async fn download_files(
 
 	// if we did fail to download some files, pull the inner value out of the Arc<Mutex<_>>
 	// and return that with the error
-	match Arc::try_unwrap(failed_files)
+	let failed = Arc::try_unwrap(failed_files)
 		.expect("failed_files was passed to a buffer that did not finish")
 		.into_inner()
-	{
-		Ok(files) if !files.is_empty() => Err(FilesDownloadFailed(files)),
-		_ => Ok(()),
+		.unwrap_or_default();
+
+	// an entry is only complete if none of its files failed
+	entries.sort();
+	entries.dedup();
+	entries.retain(|e| {
+		!failed
+			.iter()
+			.any(|f| f.subdir.rsplit_once('/').is_some_and(|(d, _)| d == e))
+	});
+
+	if let Ok(mut state) = state.lock() {
+		state.completed.extend(entries);
+	}
+
+	if failed.is_empty() {
+		Ok(())
+	} else {
+		Err(FilesDownloadFailed(failed))
 	}
 }
 
@@ -420,6 +450,9 @@ pub fn desync_all() {
 pub struct Download {
 	pub subdir: String,
 	pub is_cache: bool,
+	// whether the entry had no logs on the device when the sync started, so that it's counted as
+	// completed (and passed to the hooks) once all its files are here
+	pub new_entry: bool,
 	pub state: Arc<Mutex<SyncTracker>>,
 	pub config: Arc<config::Config>,
 }
@@ -430,6 +463,8 @@ pub struct SyncTracker {
 	pub done: usize,
 	pub total: usize,
 	pub prefix: String,
+	// the new entries that have had all their files downloaded; this isn't cleared by `reset`
+	pub completed: Vec<String>,
 }
 
 impl SyncTracker {
@@ -484,3 +519,196 @@ pub struct SyncHelper {
 	pub to_download: Vec<Download>,
 	pub times_to_check: Vec<(String, String)>,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{Failure, MockServer, TestEnv};
+
+	fn tracker() -> Arc<Mutex<SyncTracker>> {
+		Arc::new(Mutex::new(SyncTracker {
+			prefix: "Testing:".to_owned(),
+			started: 0,
+			done: 0,
+			total: 0,
+			completed: Vec::new(),
+		}))
+	}
+
+	async fn server_with_entries() -> MockServer {
+		let server = MockServer::start().await;
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&[
+				"crashed on launch",
+				"Application: riot-android",
+				"user_id: @bob:example.org",
+			],
+			&[
+				("logs-0000.log", "first log"),
+				("logs-0001.log", "second log"),
+			],
+		);
+		server.add_entry(
+			"2021-07-09/010203",
+			&[
+				"can't send messages",
+				"Application: riot-ios",
+				"user_id: @alice:example.org",
+			],
+			&[("console.log", "ios log")],
+		);
+
+		server
+	}
+
+	#[tokio::test]
+	async fn syncs_every_file_of_every_entry() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+		let state = tracker();
+
+		sync_logs(&Arc::default(), &env.config, &state)
+			.await
+			.expect("Sync failed");
+
+		for (path, contents) in [
+			("2021-07-08/161300/logs-0000.log", "first log"),
+			("2021-07-08/161300/logs-0001.log", "second log"),
+			("2021-07-09/010203/console.log", "ios log"),
+		] {
+			assert_eq!(fs::read_to_string(env.store_path(path)).unwrap(), contents);
+		}
+
+		let mut completed = state.lock().unwrap().completed.clone();
+		completed.sort();
+		assert_eq!(completed, ["2021-07-08/161300", "2021-07-09/010203"]);
+	}
+
+	#[tokio::test]
+	async fn skips_files_that_are_already_downloaded() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+		env.add_local_file("2021-07-08/161300/logs-0000.log", "local copy");
+
+		sync_logs(&Arc::default(), &env.config, &tracker())
+			.await
+			.expect("Sync failed");
+
+		let local = env.store_path("2021-07-08/161300/logs-0000.log");
+		assert_eq!(fs::read_to_string(local).unwrap(), "local copy");
+		assert!(!server
+			.requests()
+			.contains(&"2021-07-08/161300/logs-0000.log".to_owned()));
+	}
+
+	#[tokio::test]
+	async fn entries_that_were_partly_synced_are_not_new() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+		env.add_local_file("2021-07-08/161300/logs-0000.log", "local copy");
+		let state = tracker();
+
+		sync_logs(&Arc::default(), &env.config, &state)
+			.await
+			.expect("Sync failed");
+
+		assert!(env.store_path("2021-07-08/161300/logs-0001.log").exists());
+		assert_eq!(state.lock().unwrap().completed, ["2021-07-09/010203"]);
+	}
+
+	#[tokio::test]
+	async fn only_downloads_entries_that_match_the_filter() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+
+		let filter = Arc::new(Filter {
+			oses: Some(vec![crate::entry::EntryOS::iOS]),
+			..Filter::default()
+		});
+
+		sync_logs(&filter, &env.config, &tracker())
+			.await
+			.expect("Sync failed");
+
+		assert!(env.store_path("2021-07-09/010203/console.log").exists());
+		assert!(!env.store_path("2021-07-08/161300").exists());
+	}
+
+	#[tokio::test]
+	async fn failing_to_list_days_or_times_is_a_listing_failure() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+
+		server.fail("", Failure::Status(500));
+		let res = sync_logs(&Arc::default(), &env.config, &tracker()).await;
+		assert!(matches!(res, Err(ListingFailed)));
+
+		server.fail("2021-07-09", Failure::Status(503));
+		let res = sync_logs(&Arc::default(), &env.config, &tracker()).await;
+		assert!(matches!(res, Err(ListingFailed)));
+	}
+
+	#[tokio::test]
+	async fn failed_downloads_are_returned_and_can_be_retried() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+		let state = tracker();
+
+		server.fail("2021-07-08/161300/logs-0001.log", Failure::Status(500));
+
+		let Err(FilesDownloadFailed(failed)) =
+			sync_logs(&Arc::default(), &env.config, &state).await
+		else {
+			panic!("Sync should've failed to download a file");
+		};
+
+		let subdirs = failed.iter().map(|d| d.subdir.as_str()).collect::<Vec<_>>();
+		assert_eq!(subdirs, ["2021-07-08/161300/logs-0001.log"]);
+
+		// the body of the error response shouldn't be saved as if it were the file
+		assert!(!env.store_path("2021-07-08/161300/logs-0001.log").exists());
+
+		// and the entry isn't complete until the retry gets the rest of its files
+		assert_eq!(state.lock().unwrap().completed, ["2021-07-09/010203"]);
+
+		download_files(failed, &state, &env.config)
+			.await
+			.expect("Retry failed");
+
+		let retried = env.store_path("2021-07-08/161300/logs-0001.log");
+		assert_eq!(fs::read_to_string(retried).unwrap(), "second log");
+		assert!(state
+			.lock()
+			.unwrap()
+			.completed
+			.contains(&"2021-07-08/161300".to_owned()));
+	}
+
+	#[tokio::test]
+	async fn timeouts_and_truncated_bodies_are_failed_downloads() {
+		let server = server_with_entries().await;
+		let env = TestEnv::new(&server, "");
+
+		server.fail("2021-07-08/161300/logs-0000.log", Failure::Timeout);
+		server.fail("2021-07-09/010203/console.log", Failure::Truncated);
+
+		let Err(FilesDownloadFailed(failed)) =
+			sync_logs(&Arc::default(), &env.config, &tracker()).await
+		else {
+			panic!("Sync should've failed to download some files");
+		};
+
+		let mut subdirs = failed.iter().map(|d| d.subdir.as_str()).collect::<Vec<_>>();
+		subdirs.sort_unstable();
+		assert_eq!(
+			subdirs,
+			[
+				"2021-07-08/161300/logs-0000.log",
+				"2021-07-09/010203/console.log"
+			]
+		);
+		assert!(!env.store_path("2021-07-09/010203/console.log").exists());
+	}
+}
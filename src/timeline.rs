@@ -0,0 +1,231 @@
+use crate::{timestamp::Timestamp, view::Line};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+// what each file's tag is colored with, in order; they're reused if there are more files
+const TAG_STYLES: [&str; 8] = ["36", "35", "32", "34", "33", "96", "95", "92"];
+
+// files that are in entries but aren't logs, so there's nothing to interleave
+const SKIPPED_EXTENSIONS: [&str; 4] = [".png", ".jpg", ".jpeg", ".gif"];
+
+// whether the file is a log, and not something like a screenshot
+pub fn is_text(file: &str) -> bool {
+	!SKIPPED_EXTENSIONS.iter().any(|ext| file.ends_with(ext))
+}
+
+// whether the file should be part of an entry's timeline
+pub fn includes(file: &str) -> bool {
+	file != crate::DETAILS && is_text(file)
+}
+
+// the colored tag to put before each line from each of these files, e.g. `logs-0000 │ `. They're
+// all padded to the same width so the lines themselves still line up
+pub fn tags(files: &[String]) -> Vec<String> {
+	let names = files
+		.iter()
+		.map(|f| {
+			let mut name = f.as_str();
+			while let Some(stripped) = [".gz", ".log", ".txt"]
+				.iter()
+				.find_map(|ext| name.strip_suffix(ext))
+			{
+				name = stripped;
+			}
+			name
+		})
+		.collect::<Vec<_>>();
+
+	let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+
+	names
+		.iter()
+		.enumerate()
+		.map(|(idx, name)| {
+			format!(
+				"\x1b[{}m{name:<width$}\x1b[0m \x1b[2m│\x1b[0m ",
+				TAG_STYLES[idx % TAG_STYLES.len()]
+			)
+		})
+		.collect()
+}
+
+// a line with a timestamp, and all the lines after it that don't have one (like the rest of a
+// stack trace), which have to stay together
+struct Record {
+	// `None` for lines at the start of a file before anything with a timestamp, which go first
+	key: Option<NaiveDateTime>,
+	lines: Vec<String>,
+}
+
+struct Source<I> {
+	lines: I,
+	// the line after the current record, since we had to read it to know the record was over
+	lookahead: Option<(Option<Timestamp>, String)>,
+}
+
+impl<I: Iterator<Item = String>> Source<I> {
+	fn read(&mut self, date: NaiveDate) -> Option<(Option<Timestamp>, String)> {
+		let mut line = self.lines.next()?;
+
+		// the last line of a file may not have a newline, which would make it run into whatever
+		// line from another file comes after it
+		if !line.ends_with('\n') {
+			line.push('\n');
+		}
+
+		Some((Timestamp::parse(&line, date), line))
+	}
+
+	fn next_record(&mut self, date: NaiveDate) -> Option<Record> {
+		let (timestamp, first) = self.lookahead.take().or_else(|| self.read(date))?;
+		let mut lines = vec![first];
+
+		loop {
+			match self.read(date) {
+				Some((None, line)) => lines.push(line),
+				next => {
+					self.lookahead = next;
+					break;
+				}
+			}
+		}
+
+		Some(Record {
+			key: timestamp.map(|t| t.utc()),
+			lines,
+		})
+	}
+}
+
+// merges the lines of multiple files into one stream in the order they were logged. Each file is
+// only read as far as it needs to be, so this works on files of any size
+pub struct Timeline<I> {
+	date: NaiveDate,
+	sources: Vec<Source<I>>,
+	// the record that each source will give next, if it has any left
+	records: Vec<Option<Vec<String>>>,
+	// the key of each source's next record, so the earliest can be popped off the top. The
+	// source's index breaks ties, so lines logged at the same time stay in file order
+	heap: BinaryHeap<Reverse<(Option<NaiveDateTime>, usize)>>,
+	// the rest of the record that's currently being given out, and which source it's from
+	pending: std::vec::IntoIter<String>,
+	pending_source: usize,
+}
+
+impl<I: Iterator<Item = String>> Timeline<I> {
+	// `date` is the day of the entry, for lines that only give a time
+	pub fn new(files: Vec<I>, date: NaiveDate) -> Timeline<I> {
+		let mut timeline = Timeline {
+			date,
+			sources: files
+				.into_iter()
+				.map(|lines| Source {
+					lines,
+					lookahead: None,
+				})
+				.collect(),
+			records: Vec::new(),
+			heap: BinaryHeap::new(),
+			pending: Vec::new().into_iter(),
+			pending_source: 0,
+		};
+
+		timeline.records = (0..timeline.sources.len()).map(|_| None).collect();
+
+		for idx in 0..timeline.sources.len() {
+			timeline.advance(idx);
+		}
+
+		timeline
+	}
+
+	// reads the next record of the given source and queues it up
+	fn advance(&mut self, idx: usize) {
+		if let Some(record) = self.sources[idx].next_record(self.date) {
+			self.heap.push(Reverse((record.key, idx)));
+			self.records[idx] = Some(record.lines);
+		}
+	}
+}
+
+impl<I: Iterator<Item = String>> Iterator for Timeline<I> {
+	type Item = Line;
+
+	fn next(&mut self) -> Option<Line> {
+		loop {
+			if let Some(text) = self.pending.next() {
+				return Some(Line {
+					source: self.pending_source,
+					text,
+				});
+			}
+
+			let Reverse((_, idx)) = self.heap.pop()?;
+
+			self.pending = self.records[idx].take().unwrap_or_default().into_iter();
+			self.pending_source = idx;
+			self.advance(idx);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lines(text: &str) -> std::vec::IntoIter<String> {
+		text.split_inclusive('\n')
+			.map(ToOwned::to_owned)
+			.collect::<Vec<_>>()
+			.into_iter()
+	}
+
+	#[test]
+	fn interleaves_by_timestamp() {
+		let date = NaiveDate::from_ymd_opt(2021, 7, 8).unwrap();
+		let android = lines(
+			"header without a time\n\
+			07-08 16:13:01.000  1  2 E Sync: failed\n\
+			\tat Sync.run\n\
+			07-08 16:13:03.000  1  2 I Sync: retrying",
+		);
+		// two hours ahead of UTC, so 16:13:02 UTC
+		let ios = lines("2021-07-08 18:13:02.000+0200 [info] got push\n");
+		let rust = lines(
+			"2021-07-08T16:13:00.500Z INFO started\n\
+			2021-07-08T16:13:03.000Z INFO same time as android\n",
+		);
+
+		let merged = Timeline::new(vec![android, ios, rust], date)
+			.map(|l| (l.source, l.text))
+			.collect::<Vec<_>>();
+
+		let expected = [
+			(0, "header without a time\n"),
+			(2, "2021-07-08T16:13:00.500Z INFO started\n"),
+			(0, "07-08 16:13:01.000  1  2 E Sync: failed\n"),
+			(0, "\tat Sync.run\n"),
+			(1, "2021-07-08 18:13:02.000+0200 [info] got push\n"),
+			(0, "07-08 16:13:03.000  1  2 I Sync: retrying\n"),
+			(2, "2021-07-08T16:13:03.000Z INFO same time as android\n"),
+		];
+
+		assert_eq!(
+			merged,
+			expected
+				.iter()
+				.map(|(s, t)| (*s, (*t).to_owned()))
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn tags_are_short_and_aligned() {
+		let tags = tags(&["logs-0000.log.gz".to_owned(), "console.txt".to_owned()]);
+
+		assert_eq!(tags[0], "\x1b[36mlogs-0000\x1b[0m \x1b[2m│\x1b[0m ");
+		assert_eq!(tags[1], "\x1b[35mconsole  \x1b[0m \x1b[2m│\x1b[0m ");
+		assert!(!includes(crate::DETAILS));
+		assert!(!includes("screenshot.png"));
+	}
+}
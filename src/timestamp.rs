@@ -0,0 +1,355 @@
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// timestamps are always near the start of the line, so we don't bother looking past this
+const SEARCH_LEN: usize = 64;
+
+lazy_static! {
+	// covers ISO-8601 and friends (`2021-07-08T16:13:00.123Z`, `2021-07-08 16:13:00,123+0200`),
+	// logcat (`07-08 16:13:00.123`), and just times (`16:13:00.123`)
+	static ref TIMESTAMP_REGEX: Regex = Regex::new(
+		r"(?x)
+		(?:(?:(?P<year>\d{4})-)?(?P<month>\d{2})-(?P<day>\d{2})[T\ ])?
+		(?P<hour>\d{2}):(?P<min>\d{2}):(?P<sec>\d{2})(?:[.,](?P<frac>\d{1,9}))?
+		(?:\ ?(?P<tz>Z|[+-]\d{2}:?\d{2}))?
+		"
+	)
+	.unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+	// the time as it was written in the log
+	pub naive: NaiveDateTime,
+	// the timezone it was written in, if it said
+	pub offset: Option<FixedOffset>,
+}
+
+impl Timestamp {
+	// finds the timestamp at the start of the line. Anything the line doesn't say (the year, or
+	// the whole date) is taken from `date`, which should be the day of the entry
+	pub fn parse(line: &str, date: NaiveDate) -> Option<Timestamp> {
+		let mut end = line.len().min(SEARCH_LEN);
+		while !line.is_char_boundary(end) {
+			end -= 1;
+		}
+
+		let caps = TIMESTAMP_REGEX.captures(&line[..end])?;
+		let num = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u32>().ok());
+
+		let date = match (num("month"), num("day")) {
+			(Some(month), Some(day)) => {
+				let year = caps
+					.name("year")
+					.and_then(|y| y.as_str().parse().ok())
+					.unwrap_or_else(|| date.year());
+				NaiveDate::from_ymd_opt(year, month, day)?
+			}
+			_ => date,
+		};
+
+		let nanos = caps.name("frac").map_or(0, |f| {
+			// `.5` is half a second, not five nanoseconds
+			let digits = f.as_str();
+			digits.parse::<u32>().unwrap_or(0) * 10_u32.pow(9 - digits.len() as u32)
+		});
+
+		let time = NaiveTime::from_hms_nano_opt(num("hour")?, num("min")?, num("sec")?, nanos)?;
+
+		let offset = caps.name("tz").and_then(|tz| parse_offset(tz.as_str()));
+
+		Some(Timestamp {
+			naive: date.and_time(time),
+			offset,
+		})
+	}
+
+	// the time in UTC, for comparing with timestamps from other files. Timestamps that didn't
+	// say what timezone they're in are assumed to be UTC
+	pub fn utc(&self) -> NaiveDateTime {
+		match self.offset {
+			Some(offset) => self.naive - offset,
+			None => self.naive,
+		}
+	}
+
+	// the time in the given timezone, or the time as written if we don't know the timezone to
+	// convert from
+	pub fn in_zone<Tz: TimeZone>(&self, zone: &Tz) -> NaiveDateTime {
+		match self.offset {
+			Some(_) => zone.from_utc_datetime(&self.utc()).naive_local(),
+			None => self.naive,
+		}
+	}
+}
+
+// parses `Z`, `+02:00`, or `-0500`
+pub fn parse_offset(tz: &str) -> Option<FixedOffset> {
+	if tz == "Z" {
+		return FixedOffset::east_opt(0);
+	}
+
+	let sign = if tz.starts_with('-') {
+		-1
+	} else {
+		1
+	};
+	let digits = tz[1..].replace(':', "");
+	let hours = digits.get(..2)?.parse::<i32>().ok()?;
+	let mins = digits.get(2..)?.parse::<i32>().ok()?;
+
+	FixedOffset::east_opt(sign * (hours * 3600 + mins * 60))
+}
+
+// a time that was passed in on the command line, e.g. `14:05`, `14:05:30`, or
+// `2021-07-08 14:05`. Without a date, it's on the day of whatever entry it's used with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallClock {
+	date: Option<NaiveDate>,
+	time: NaiveTime,
+	// whether the seconds were given, so `--to 14:05` can include everything up to 14:06
+	has_seconds: bool,
+}
+
+impl WallClock {
+	fn on(&self, date: NaiveDate) -> NaiveDateTime {
+		self.date.unwrap_or(date).and_time(self.time)
+	}
+}
+
+impl TryFrom<&str> for WallClock {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, String> {
+		let val = val.trim();
+		let (date, time) = match val.rsplit_once(['T', ' ']) {
+			Some((date, time)) => (
+				Some(
+					NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+						.map_err(|_| format!("'{date}' is not a date like '2021-07-08'"))?,
+				),
+				time,
+			),
+			None => (None, val),
+		};
+
+		let (time, has_seconds) = NaiveTime::parse_from_str(time, "%H:%M:%S%.f")
+			.map(|t| (t, true))
+			.or_else(|_| NaiveTime::parse_from_str(time, "%H:%M").map(|t| (t, false)))
+			.map_err(|_| format!("'{time}' is not a time like '14:05' or '14:05:30'"))?;
+
+		Ok(WallClock {
+			date,
+			time,
+			has_seconds,
+		})
+	}
+}
+
+// the part of a log that the user wants to see, from `--from`/`--to` or `--around`/`--window`
+#[derive(Debug, Clone, Default)]
+pub struct TimeRange {
+	from: Option<WallClock>,
+	to: Option<WallClock>,
+	around: Option<(WallClock, chrono::Duration)>,
+	// compare against timestamps converted to our timezone instead of the ones they were logged in
+	pub local: bool,
+}
+
+impl TimeRange {
+	pub fn new(
+		from: Option<&str>,
+		to: Option<&str>,
+		around: Option<&str>,
+		window: Option<std::time::Duration>,
+		local: bool,
+	) -> Result<TimeRange, String> {
+		let around = around
+			.map(|around| -> Result<_, String> {
+				let window = window.unwrap_or(std::time::Duration::from_secs(5 * 60));
+				let window = chrono::Duration::from_std(window)
+					.map_err(|_| "The window is too long".to_owned())?;
+
+				Ok((WallClock::try_from(around)?, window))
+			})
+			.transpose()?;
+
+		Ok(TimeRange {
+			from: from.map(WallClock::try_from).transpose()?,
+			to: to.map(WallClock::try_from).transpose()?,
+			around,
+			local,
+		})
+	}
+
+	// whether this lets every line through
+	pub fn is_empty(&self) -> bool {
+		self.from.is_none() && self.to.is_none() && self.around.is_none()
+	}
+
+	// the first and last times to show for an entry from the given day
+	fn bounds(&self, date: NaiveDate) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+		if let Some((around, window)) = self.around {
+			let center = around.on(date);
+			return (Some(center - window), Some(center + window));
+		}
+
+		let to = self.to.map(|to| {
+			let end = to.on(date);
+			if to.has_seconds {
+				end
+			} else {
+				end + chrono::Duration::minutes(1) - chrono::Duration::nanoseconds(1)
+			}
+		});
+
+		(self.from.map(|from| from.on(date)), to)
+	}
+}
+
+// decides, line by line, whether each line of a file is within a `TimeRange`. Lines without a
+// timestamp (like the rest of a stack trace) go with the last line that had one, and anything
+// before the first timestamp is left out
+pub struct TimeSlice {
+	date: NaiveDate,
+	from: Option<NaiveDateTime>,
+	to: Option<NaiveDateTime>,
+	local: bool,
+	current: bool,
+}
+
+impl TimeSlice {
+	// `date` is the day of the entry the lines are from
+	pub fn new(range: &TimeRange, date: NaiveDate) -> TimeSlice {
+		let (from, to) = range.bounds(date);
+
+		TimeSlice {
+			date,
+			from,
+			to,
+			local: range.local,
+			current: false,
+		}
+	}
+
+	pub fn keep(&mut self, line: &str) -> bool {
+		if let Some(timestamp) = Timestamp::parse(line, self.date) {
+			let time = if self.local {
+				timestamp.in_zone(&Local)
+			} else {
+				timestamp.naive
+			};
+
+			self.current =
+				self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time <= to);
+		}
+
+		self.current
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn day() -> NaiveDate {
+		NaiveDate::from_ymd_opt(2021, 7, 8).unwrap()
+	}
+
+	fn at(h: u32, m: u32, s: u32, ms: u32) -> NaiveDateTime {
+		day().and_hms_milli_opt(h, m, s, ms).unwrap()
+	}
+
+	#[test]
+	fn parses_client_formats() {
+		let cases = [
+			(
+				"2021-07-08T16:13:00.123Z INFO sync",
+				at(16, 13, 0, 123),
+				Some(0),
+			),
+			(
+				"2021-07-08 16:13:00,5+0200 [error] x",
+				at(16, 13, 0, 500),
+				Some(7200),
+			),
+			(
+				"07-08 16:13:00.123  1234  5678 E Tag: x",
+				at(16, 13, 0, 123),
+				None,
+			),
+			("[16:13:00] something", at(16, 13, 0, 0), None),
+			(
+				"2021-07-08 16:13:00 -05:00 x",
+				at(16, 13, 0, 0),
+				Some(-18000),
+			),
+		];
+
+		for (line, naive, offset) in cases {
+			let ts = Timestamp::parse(line, day()).unwrap_or_else(|| panic!("{line}"));
+			assert_eq!(ts.naive, naive, "{line}");
+			assert_eq!(ts.offset.map(|o| o.local_minus_utc()), offset, "{line}");
+		}
+
+		assert!(Timestamp::parse("    at com.example.Foo", day()).is_none());
+	}
+
+	#[test]
+	fn converts_between_zones() {
+		let ts = Timestamp::parse("2021-07-08T16:13:00+02:00", day()).unwrap();
+		assert_eq!(ts.utc(), at(14, 13, 0, 0));
+
+		let east = FixedOffset::east_opt(3600).unwrap();
+		assert_eq!(ts.in_zone(&east), at(15, 13, 0, 0));
+
+		let naive = Timestamp::parse("16:13:00", day()).unwrap();
+		assert_eq!(naive.utc(), at(16, 13, 0, 0));
+		assert_eq!(naive.in_zone(&east), at(16, 13, 0, 0));
+	}
+
+	#[test]
+	fn slices_by_time_range() {
+		let lines = [
+			"header",
+			"2021-07-08T14:03:59Z INFO a",
+			"2021-07-08T14:04:00Z ERROR b",
+			"\tat stack.trace",
+			"2021-07-08T14:05:59.9Z INFO c",
+			"2021-07-08T14:06:00Z INFO d",
+		];
+
+		let run = |range: TimeRange| {
+			let mut slice = TimeSlice::new(&range, day());
+			lines
+				.iter()
+				.filter(|l| slice.keep(l))
+				.copied()
+				.collect::<Vec<_>>()
+		};
+
+		let range = TimeRange::new(Some("14:04"), Some("14:05"), None, None, false).unwrap();
+		assert_eq!(
+			run(range),
+			[
+				"2021-07-08T14:04:00Z ERROR b",
+				"\tat stack.trace",
+				"2021-07-08T14:05:59.9Z INFO c"
+			]
+		);
+
+		let window = std::time::Duration::from_secs(60);
+		let range = TimeRange::new(None, None, Some("14:05"), Some(window), false).unwrap();
+		assert_eq!(
+			run(range.clone()),
+			run(TimeRange::new(Some("14:04"), Some("14:06:00"), None, None, false).unwrap())
+		);
+
+		let range = TimeRange::new(Some("2021-07-08 14:06:00"), None, None, None, false).unwrap();
+		assert_eq!(run(range), ["2021-07-08T14:06:00Z INFO d"]);
+
+		assert!(TimeRange::new(Some("2pm"), None, None, None, false).is_err());
+		assert!(TimeRange::default().is_empty());
+	}
+}
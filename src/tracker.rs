@@ -0,0 +1,625 @@
+// Finding the entries that issues link to, and filing issues for entries, on whichever tracker
+// the issue lives on. Each tracker has its own module that knows how to talk to its API; this is
+// everything that's the same between them.
+
+mod github;
+mod gitlab;
+mod jira;
+pub mod linear;
+
+use crate::{
+	config::Config,
+	entry::Entry,
+	err,
+	search::pick_entry,
+	sync_dir,
+	timestamp::TimeRange,
+	view::{view, ViewOptions},
+};
+use lazy_static::lazy_static;
+use std::{fmt, fs, sync::Arc};
+
+lazy_static! {
+	// what links to issues look like on each tracker, with either the issue's key or its repo (or
+	// project) and number captured. Only linear's has to be on its own site, since the others can
+	// be self-hosted; gitlab's comes before github's since they'd both match its links
+	static ref ISSUE_LINKS: [(Tracker, regex::Regex); 4] = [
+		(Tracker::Linear, regex::Regex::new(r"^https?://linear\.app/[^/]+/issue/([A-Za-z0-9]+-\d+)").unwrap()),
+		(Tracker::GitLab, regex::Regex::new(r"^https?://[^/]+/(.+?)/-/issues/(\d+)").unwrap()),
+		(Tracker::GitHub, regex::Regex::new(r"^https?://[^/]+/([^/]+/[^/]+)/issues/(\d+)").unwrap()),
+		(Tracker::Jira, regex::Regex::new(r"^https?://[^/]+/browse/([A-Z][A-Z0-9_]*-\d+)").unwrap()),
+	];
+}
+
+// how much of the logs that match `--term` to put in the description of a new issue
+const EXCERPT_FILES: usize = 5;
+const EXCERPT_LINES: usize = 10;
+// titles longer than this get cut off, since some reasons are whole paragraphs
+const TITLE_LEN: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tracker {
+	Linear,
+	GitHub,
+	GitLab,
+	Jira,
+}
+
+impl Tracker {
+	// splits `tracker:rest` into the tracker and the rest, falling back to the default tracker
+	// when it doesn't start with one
+	pub fn split<'a>(reference: &'a str, config: &Config) -> (Tracker, &'a str) {
+		reference
+			.split_once(':')
+			.and_then(|(name, rest)| Tracker::try_from(name).ok().map(|t| (t, rest)))
+			.unwrap_or((config.default_tracker, reference))
+	}
+
+	// like `split`, but also takes links to issues, e.g. `https://linear.app/org/issue/IOS-1000/title`
+	// or `https://github.com/owner/repo/issues/1000`
+	pub fn from_reference(reference: &str, config: &Config) -> (Tracker, String) {
+		let link = ISSUE_LINKS.iter().find_map(|(tracker, regex)| {
+			let captures = regex.captures(reference)?;
+			let issue = match (captures.get(1), captures.get(2)) {
+				(Some(path), Some(num)) => format!("{}#{}", path.as_str(), num.as_str()),
+				(Some(key), None) => key.as_str().to_owned(),
+				_ => return None,
+			};
+
+			Some((*tracker, issue))
+		});
+
+		link.unwrap_or_else(|| {
+			let (tracker, issue) = Tracker::split(reference, config);
+			(tracker, issue.to_owned())
+		})
+	}
+
+	fn config(self, config: &Config) -> &TrackerConfig {
+		match self {
+			Tracker::Linear => &config.linear,
+			Tracker::GitHub => &config.github,
+			Tracker::GitLab => &config.gitlab,
+			Tracker::Jira => &config.jira,
+		}
+	}
+
+	// where to get a token, for the trackers that only live in one place
+	fn token_url(self) -> Option<&'static str> {
+		match self {
+			Tracker::Linear => Some("https://linear.app/settings/api"),
+			Tracker::GitHub => Some("https://github.com/settings/tokens"),
+			Tracker::Jira => Some("https://id.atlassian.com/manage-profile/security/api-tokens"),
+			Tracker::GitLab => None,
+		}
+	}
+
+	// all the text of the issue that might link to entries (its description, comments, etc.)
+	async fn issue_text(
+		self,
+		issue: &str,
+		config: &Config,
+	) -> Result<String, Box<dyn std::error::Error>> {
+		match self {
+			Tracker::Linear => linear::issue_text(issue, config).await,
+			Tracker::GitHub => github::issue_text(issue, config).await,
+			Tracker::GitLab => gitlab::issue_text(issue, config).await,
+			Tracker::Jira => jira::issue_text(issue, config).await,
+		}
+	}
+
+	// works out the request that would file `issue` at `target` (a team, repo, or project,
+	// depending on the tracker), looking up any ids that it needs along the way
+	async fn prepare(
+		self,
+		target: &str,
+		issue: &NewIssue,
+		config: &Config,
+	) -> Result<Prepared, Box<dyn std::error::Error>> {
+		match self {
+			Tracker::Linear => linear::prepare(target, issue, config).await,
+			Tracker::GitHub => github::prepare(target, issue, config).await,
+			Tracker::GitLab => gitlab::prepare(target, issue, config).await,
+			Tracker::Jira => jira::prepare(target, issue, config).await,
+		}
+	}
+
+	async fn submit(
+		self,
+		target: &str,
+		prepared: &Prepared,
+		config: &Config,
+	) -> Result<IssueRef, Box<dyn std::error::Error>> {
+		match self {
+			Tracker::Linear => linear::submit(prepared, config).await,
+			Tracker::GitHub => github::submit(target, prepared, config).await,
+			Tracker::GitLab => gitlab::submit(target, prepared, config).await,
+			Tracker::Jira => jira::submit(prepared, config).await,
+		}
+	}
+}
+
+impl TryFrom<&str> for Tracker {
+	type Error = String;
+
+	fn try_from(val: &str) -> Result<Self, String> {
+		match val {
+			"linear" => Ok(Tracker::Linear),
+			"github" => Ok(Tracker::GitHub),
+			"gitlab" => Ok(Tracker::GitLab),
+			"jira" => Ok(Tracker::Jira),
+			_ => Err(format!(
+				"Tracker must be one of 'linear', 'github', 'gitlab', or 'jira', not '{val}'"
+			)),
+		}
+	}
+}
+
+impl fmt::Display for Tracker {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Tracker::Linear => "linear",
+			Tracker::GitHub => "github",
+			Tracker::GitLab => "gitlab",
+			Tracker::Jira => "jira",
+		})
+	}
+}
+
+// how to talk to one of the trackers, from its section of the config file
+#[derive(Debug, Default)]
+pub struct TrackerConfig {
+	// the token to authenticate with
+	pub token: Option<String>,
+	// the base url of its api, so self-hosted ones (or stand-ins) can be used
+	pub api_url: String,
+	// who the token belongs to, which jira cloud needs along with it
+	pub email: Option<String>,
+	// what kind of issue to file, for the trackers that have them
+	pub issue_type: String,
+}
+
+impl TrackerConfig {
+	pub fn from_config(section: Option<&toml::Value>, default_url: &str) -> TrackerConfig {
+		let get = |key: &str| {
+			section
+				.and_then(|s| s.get(key))
+				.and_then(toml::Value::as_str)
+				.map(ToOwned::to_owned)
+		};
+
+		TrackerConfig {
+			token: get("token"),
+			api_url: get("api-url")
+				.as_deref()
+				.unwrap_or(default_url)
+				.trim_end_matches('/')
+				.to_owned(),
+			email: get("email"),
+			issue_type: get("issue-type").unwrap_or_else(|| "Bug".to_owned()),
+		}
+	}
+}
+
+// an issue that links to an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRef {
+	pub identifier: String,
+	pub title: String,
+	pub state: String,
+	pub url: String,
+}
+
+impl IssueRef {
+	// reads the issue out of the shape linear uses, which is also how they're cached
+	fn from_json(issue: &serde_json::Value) -> Option<IssueRef> {
+		Some(IssueRef {
+			identifier: issue["identifier"].as_str()?.to_owned(),
+			title: issue["title"].as_str().unwrap_or_default().to_owned(),
+			state: issue["state"]["name"]
+				.as_str()
+				.unwrap_or("unknown")
+				.to_owned(),
+			url: issue["url"].as_str().unwrap_or_default().to_owned(),
+		})
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		serde_json::json!({
+			"identifier": self.identifier,
+			"title": self.title,
+			"state": { "name": self.state },
+			"url": self.url,
+		})
+	}
+}
+
+// everything about the issue to create for an entry besides the entry itself
+pub struct CreateOptions {
+	// where to file it, e.g. `linear:IOS` or `github:owner/repo`
+	pub target: String,
+	// the names of the labels to give it
+	pub labels: Vec<String>,
+	// the name or email of who to assign it to, or `me`
+	pub assignee: Option<String>,
+	// the name of the project to put it in
+	pub project: Option<String>,
+	// include the lines of the logs that match this
+	pub term: Option<String>,
+	// print what would be sent instead of creating it
+	pub dry_run: bool,
+}
+
+// what goes in a new issue, whichever tracker it's going to
+pub struct NewIssue {
+	pub title: String,
+	// in markdown
+	pub description: String,
+	pub labels: Vec<String>,
+	pub assignee: Option<String>,
+	pub project: Option<String>,
+}
+
+// the request that files an issue, kept separate from sending it so `--dry-run` can print it
+pub struct Prepared {
+	pub url: String,
+	pub body: serde_json::Value,
+}
+
+// finds the entries linked to from the issue, and either lets the user pick one to view or, with
+// `all`, downloads every one of them
+pub async fn find_issue(
+	reference: &str,
+	config: Config,
+	all: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let (tracker, issue) = Tracker::from_reference(reference, &config);
+
+	// public issues can be read without a token everywhere but linear
+	if tracker == Tracker::Linear && !has_token(tracker, &config) {
+		return Ok(());
+	}
+
+	let mut entries = find_issue_entries(tracker, &issue, &Arc::new(config)).await?;
+
+	if entries.is_empty() {
+		err!("It appears that this issue contains no links to rageshake logs");
+		return Ok(());
+	}
+
+	if all {
+		println!("✨ Found {} entries", entries.len());
+
+		for entry in &mut entries {
+			if let Err(err) = entry.ensure_all_files_downloaded().await {
+				err!("Failed to download {}: {err}", entry.date_time());
+			}
+		}
+
+		return Ok(());
+	}
+
+	let idx = if entries.len() == 1 {
+		0
+	} else {
+		// the picker shows who sent each one and why, which comes from their details
+		for entry in &mut entries {
+			if let Err(err) = entry.set_download_values().await {
+				err!("Unable to get details of {}: {err}", entry.date_time());
+			}
+		}
+
+		let choices = entries.iter().map(Entry::selectable_description).collect();
+
+		match pick_entry(choices, "Linked Entries:", 0) {
+			Some(idx) => idx,
+			None => return Ok(()),
+		}
+	};
+
+	let entry = &mut entries[idx];
+	println!("✨ Found logs! (\x1b[1m{}\x1b[0m)", entry.date_time());
+
+	view(entry, None, None, &ViewOptions::default()).await?;
+
+	Ok(())
+}
+
+// finds every entry that's linked to from the issue, in the order they show up in it and without
+// any repeats
+pub async fn find_issue_entries(
+	tracker: Tracker,
+	issue: &str,
+	config: &Arc<Config>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+	let text = tracker.issue_text(issue, config).await?;
+	entries_in(&text, config)
+}
+
+fn entries_in(text: &str, config: &Arc<Config>) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+	let filtered_url = config.server.replace('.', "\\.");
+	let rageshake_url_regex =
+		regex::Regex::new(&(filtered_url + "/api/listing/\\d{4,}\\-\\d{2,}\\-\\d{2,}/\\d{6,}"))?;
+
+	let mut entries: Vec<Entry> = Vec::new();
+
+	for url in rageshake_url_regex.find_iter(text).map(|m| m.as_str()) {
+		let len = url.len();
+		// get the day
+		let day = &url[len - 17..len - 7];
+		let time = &url[len - 6..];
+
+		if !entries.iter().any(|e| e.day == day && e.time == time) {
+			entries.push(Entry::new(day, time, config.clone()));
+		}
+	}
+
+	Ok(entries)
+}
+
+// files an issue for the entry at `day_time`, with its details (and possibly some of its logs) in
+// the description
+pub async fn create(
+	day_time: &str,
+	options: CreateOptions,
+	config: &Arc<Config>,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let (tracker, target) = Tracker::split(&options.target, config);
+
+	if !has_token(tracker, config) {
+		return Ok(());
+	}
+
+	let (day, time) = day_time.split_once('/').unwrap_or_default();
+	let mut entry = Entry::new(day, time, config.clone());
+	entry.set_download_values().await?;
+
+	let excerpts = match options.term.as_deref() {
+		Some(term) => {
+			entry.ensure_all_files_downloaded().await?;
+			excerpts(&mut entry, term).await?
+		}
+		None => Vec::new(),
+	};
+
+	let issue = NewIssue {
+		title: issue_title(&entry),
+		description: issue_description(&entry, options.term.as_deref(), &excerpts),
+		labels: options.labels,
+		assignee: options.assignee,
+		project: options.project,
+	};
+
+	let prepared = tracker.prepare(target, &issue, config).await?;
+
+	if options.dry_run {
+		println!(
+			"POST {}\n{}",
+			prepared.url,
+			serde_json::to_string_pretty(&prepared.body)?
+		);
+		return Ok(());
+	}
+
+	let created = tracker.submit(target, &prepared, config).await?;
+	println!(
+		"✨ Created \x1b[1m{}\x1b[0m: {}",
+		created.identifier, created.url
+	);
+
+	// only linear keeps track of which issues link to which entries, for `search --linked`
+	if tracker == Tracker::Linear {
+		linear::remember(day_time, created);
+	}
+
+	Ok(())
+}
+
+fn has_token(tracker: Tracker, config: &Config) -> bool {
+	let has_token = tracker.config(config).token.is_some();
+
+	if !has_token {
+		let from = tracker
+			.token_url()
+			.map(|url| format!("Get one from \x1b[1m{url}\x1b[0m and then add"))
+			.unwrap_or_else(|| "Add one".to_owned());
+
+		err!("Looks like you're missing a token to interact with the {tracker} API.\n{from} it to the config file under the \x1b[1mtoken\x1b[0m key of the \x1b[1m[{tracker}]\x1b[0m section");
+	}
+
+	has_token
+}
+
+// sends a request to one of the trackers' REST apis and reads its response, turning the
+// statuses that mean it didn't work into errors
+async fn send(
+	request: reqwest::RequestBuilder,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+	send_with_headers(request).await.map(|(_, body)| body)
+}
+
+// the same as `send`, but with the headers of the response as well
+async fn send_with_headers(
+	request: reqwest::RequestBuilder,
+) -> Result<(reqwest::header::HeaderMap, serde_json::Value), Box<dyn std::error::Error>> {
+	let response = request.send().await?;
+	let status = response.status();
+	let headers = response.headers().clone();
+	let text = response.text().await?;
+
+	if !status.is_success() {
+		return Err(format!("{status}: {text}").into());
+	}
+
+	Ok((headers, serde_json::from_str(&text)?))
+}
+
+// the url of the next page from a `Link` header, which github and gitlab both send, like
+// `<https://api.github.com/...&page=2>; rel="next", <https://api.github.com/...&page=5>; rel="last"`
+fn next_page(headers: &reqwest::header::HeaderMap) -> Option<String> {
+	headers
+		.get(reqwest::header::LINK)?
+		.to_str()
+		.ok()?
+		.split(',')
+		.find_map(|link| {
+			let (url, params) = link.split_once(';')?;
+			params
+				.split(';')
+				.any(|param| param.trim() == r#"rel="next""#)
+				.then(|| {
+					url.trim()
+						.trim_start_matches('<')
+						.trim_end_matches('>')
+						.to_owned()
+				})
+		})
+}
+
+// joins all the bits of text (descriptions, comment bodies, etc.) that are strings
+fn join_text<'a>(values: impl Iterator<Item = &'a serde_json::Value>) -> String {
+	values
+		.filter_map(serde_json::Value::as_str)
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+// the first few lines that match `term` in the first few files that have any
+async fn excerpts(
+	entry: &mut Entry,
+	term: &str,
+) -> Result<Vec<(String, Vec<String>)>, Box<dyn std::error::Error>> {
+	let regex = regex::Regex::new(term)?;
+
+	let mut dir = sync_dir();
+	dir.push(entry.date_time());
+
+	Ok(entry
+		.files_containing_term(term, &TimeRange::default())
+		.await?
+		.into_iter()
+		.take(EXCERPT_FILES)
+		.filter_map(|file| {
+			let text = fs::read_to_string(dir.join(&file)).ok()?;
+			let lines = text
+				.lines()
+				.filter(|l| regex.is_match(l))
+				.take(EXCERPT_LINES)
+				.map(ToOwned::to_owned)
+				.collect();
+
+			Some((file, lines))
+		})
+		.collect())
+}
+
+fn issue_title(entry: &Entry) -> String {
+	let reason = entry
+		.reason
+		.as_deref()
+		.and_then(|r| r.lines().map(str::trim).find(|l| !l.is_empty()));
+
+	match reason {
+		Some(reason) if reason.chars().count() > TITLE_LEN => {
+			reason.chars().take(TITLE_LEN - 1).collect::<String>() + "…"
+		}
+		Some(reason) => reason.to_owned(),
+		None => format!(
+			"Rageshake from {} at {}",
+			entry.user_id.as_deref().unwrap_or("unknown"),
+			entry.date_time()
+		),
+	}
+}
+
+// the markdown description of the issue
+fn issue_description(
+	entry: &Entry,
+	term: Option<&str>,
+	excerpts: &[(String, Vec<String>)],
+) -> String {
+	let unknown = "unknown".to_owned();
+
+	let mut description = format!(
+		"{}\n\n\
+		- **User:** {}\n\
+		- **OS:** {}\n\
+		- **Version:** {}\n\
+		- **Rageshake:** {}/api/listing/{}\n",
+		entry.reason.as_ref().unwrap_or(&unknown).trim(),
+		entry.user_id.as_ref().unwrap_or(&unknown),
+		entry
+			.os
+			.as_ref()
+			.map_or_else(|| unknown.clone(), ToString::to_string),
+		entry.version.as_ref().unwrap_or(&unknown),
+		entry.config.server,
+		entry.date_time()
+	);
+
+	if let Some(term) = term.filter(|_| !excerpts.is_empty()) {
+		description += &format!("\n### Lines matching `{term}`\n");
+
+		for (file, lines) in excerpts {
+			description += &format!("\n**{file}**\n```\n{}\n```\n", lines.join("\n"));
+		}
+	}
+
+	description
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	#[tokio::test]
+	async fn splits_tracker_from_reference() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "default-tracker = \"jira\"");
+
+		assert_eq!(
+			Tracker::split("github:owner/repo#12", &env.config),
+			(Tracker::GitHub, "owner/repo#12")
+		);
+		assert_eq!(
+			Tracker::split("linear:IOS-1000", &env.config),
+			(Tracker::Linear, "IOS-1000")
+		);
+		assert_eq!(
+			Tracker::split("ELEMENT-3", &env.config),
+			(Tracker::Jira, "ELEMENT-3")
+		);
+		// things that look like a tracker but aren't one are left alone
+		assert_eq!(
+			Tracker::split("bugzilla:123", &env.config),
+			(Tracker::Jira, "bugzilla:123")
+		);
+	}
+
+	#[tokio::test]
+	async fn reads_issue_links() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		let read = |link: &str| Tracker::from_reference(link, &env.config);
+
+		assert_eq!(
+			read("https://linear.app/element/issue/IOS-70000/crash-when-opening-room"),
+			(Tracker::Linear, "IOS-70000".to_owned())
+		);
+		assert_eq!(
+			read("https://github.com/element-hq/element-ios/issues/1000#issuecomment-1"),
+			(Tracker::GitHub, "element-hq/element-ios#1000".to_owned())
+		);
+		assert_eq!(
+			read("https://gitlab.example.org/element/mobile/ios/-/issues/12"),
+			(Tracker::GitLab, "element/mobile/ios#12".to_owned())
+		);
+		assert_eq!(
+			read("https://element.atlassian.net/browse/ELEMENT-3"),
+			(Tracker::Jira, "ELEMENT-3".to_owned())
+		);
+		assert_eq!(read("IOS-1000"), (Tracker::Linear, "IOS-1000".to_owned()));
+	}
+}
@@ -0,0 +1,296 @@
+// GitHub's REST API, where issues are referred to as `owner/repo#123`
+
+use super::{join_text, next_page, send, send_with_headers, IssueRef, NewIssue, Prepared};
+use crate::config::Config;
+use reqwest::Method;
+
+fn request(method: Method, url: &str, config: &Config) -> reqwest::RequestBuilder {
+	let request = reqwest::Client::new()
+		.request(method, url)
+		.header("Accept", "application/vnd.github+json")
+		// github turns away requests that don't say what they're from
+		.header("User-Agent", concat!("rager/", env!("CARGO_PKG_VERSION")));
+
+	match &config.github.token {
+		Some(token) => request.bearer_auth(token),
+		None => request,
+	}
+}
+
+// splits `owner/repo#123` into the repo and the number
+fn split_issue(issue: &str) -> Result<(&str, u64), String> {
+	issue
+		.split_once('#')
+		.filter(|(repo, _)| repo.contains('/'))
+		.and_then(|(repo, num)| Some((repo, num.parse().ok()?)))
+		.ok_or_else(|| {
+			"Please input the issue in the format of `github:OWNER/REPO#NUM` (e.g. `github:element-hq/element-ios#1000`)".to_owned()
+		})
+}
+
+// the body of the issue and of every comment on it
+pub async fn issue_text(
+	issue: &str,
+	config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+	let (repo, num) = split_issue(issue)?;
+	let url = format!("{}/repos/{repo}/issues/{num}", config.github.api_url);
+
+	let issue = send(request(Method::GET, &url, config)).await?;
+
+	// github only gives out 100 comments at a time, and says where the rest are in the headers
+	let mut comments = Vec::new();
+	let mut page = Some(format!("{url}/comments?per_page=100"));
+
+	while let Some(url) = page {
+		let (headers, body) = send_with_headers(request(Method::GET, &url, config)).await?;
+		page = next_page(&headers);
+
+		if let serde_json::Value::Array(body) = body {
+			comments.extend(body);
+		}
+	}
+
+	Ok(join_text(
+		std::iter::once(&issue["body"]).chain(comments.iter().map(|c| &c["body"])),
+	))
+}
+
+pub async fn prepare(
+	repo: &str,
+	issue: &NewIssue,
+	config: &Config,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+	if !repo.contains('/') {
+		return Err(format!(
+			"Please give the repo to file the issue in as `github:OWNER/REPO`, not '{repo}'"
+		)
+		.into());
+	}
+
+	if issue.project.is_some() {
+		return Err("Issues can't be put in a project on github; leave out --project".into());
+	}
+
+	let mut body = serde_json::json!({ "title": issue.title, "body": issue.description });
+
+	if !issue.labels.is_empty() {
+		body["labels"] = issue.labels.clone().into();
+	}
+
+	if let Some(assignee) = issue.assignee.as_deref() {
+		let login = if assignee == "me" {
+			let user = send(request(
+				Method::GET,
+				&format!("{}/user", config.github.api_url),
+				config,
+			))
+			.await?;
+			user["login"]
+				.as_str()
+				.ok_or("github didn't say who you are; give --assignee your username instead")?
+				.to_owned()
+		} else {
+			assignee.to_owned()
+		};
+
+		body["assignees"] = serde_json::json!([login]);
+	}
+
+	Ok(Prepared {
+		url: format!("{}/repos/{repo}/issues", config.github.api_url),
+		body,
+	})
+}
+
+pub async fn submit(
+	repo: &str,
+	prepared: &Prepared,
+	config: &Config,
+) -> Result<IssueRef, Box<dyn std::error::Error>> {
+	let created = send(request(Method::POST, &prepared.url, config).json(&prepared.body)).await?;
+
+	Ok(IssueRef {
+		identifier: format!("{repo}#{}", created["number"]),
+		title: created["title"].as_str().unwrap_or_default().to_owned(),
+		state: created["state"].as_str().unwrap_or("unknown").to_owned(),
+		url: created["html_url"].as_str().unwrap_or_default().to_owned(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		mock::{MockServer, TestEnv},
+		tracker::{create, find_issue_entries, CreateOptions, Tracker},
+	};
+
+	fn github_env(server: &MockServer) -> TestEnv {
+		TestEnv::new(
+			server,
+			&format!(
+				"[github]\ntoken = \"gh-token\"\napi-url = \"{}/\"",
+				server.url
+			),
+		)
+	}
+
+	#[tokio::test]
+	async fn finds_links_in_issue_and_comments() {
+		let server = MockServer::start().await;
+		let env = github_env(&server);
+
+		server.set_api_response(
+			"GET /repos/element-hq/element-ios/issues/12",
+			&format!(
+				r#"{{"number":12,"body":"[Logs]({}/api/listing/2021-07-08/161300)"}}"#,
+				server.url
+			),
+		);
+		server.set_api_response(
+			"GET /repos/element-hq/element-ios/issues/12/comments",
+			&format!(
+				r#"[{{"body":"another: {url}/api/listing/2021-07-09/010203"}},{{"body":null}}]"#,
+				url = server.url
+			),
+		);
+
+		let entries = find_issue_entries(Tracker::GitHub, "element-hq/element-ios#12", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(|e| e.date_time()).collect::<Vec<_>>(),
+			["2021-07-08/161300", "2021-07-09/010203"]
+		);
+
+		let requests = server.api_requests();
+		assert_eq!(
+			requests[1].0,
+			"GET /repos/element-hq/element-ios/issues/12/comments?per_page=100"
+		);
+		assert_eq!(requests[0].1.as_deref(), Some("Bearer gh-token"));
+
+		assert!(
+			find_issue_entries(Tracker::GitHub, "element-ios#12", &env.config)
+				.await
+				.is_err()
+		);
+	}
+
+	#[tokio::test]
+	async fn follows_every_page_of_comments() {
+		let server = MockServer::start().await;
+		let env = github_env(&server);
+
+		let comments = "/repos/element-hq/element-ios/issues/12/comments";
+
+		server.set_api_response(
+			"GET /repos/element-hq/element-ios/issues/12",
+			r#"{"number":12,"body":null}"#,
+		);
+		server.set_api_response(
+			&format!("GET {comments}"),
+			&format!(
+				r#"[{{"body":"{}/api/listing/2021-07-08/161300"}}]"#,
+				server.url
+			),
+		);
+		server.set_api_headers(
+			&format!("GET {comments}"),
+			&[(
+				"Link",
+				&format!(
+					r#"<{url}{comments}?per_page=100&page=2>; rel="next", <{url}{comments}?per_page=100&page=2>; rel="last""#,
+					url = server.url
+				),
+			)],
+		);
+		server.set_api_response(
+			&format!("GET {comments}?per_page=100&page=2"),
+			&format!(
+				r#"[{{"body":"{}/api/listing/2021-07-09/010203"}}]"#,
+				server.url
+			),
+		);
+
+		let entries = find_issue_entries(Tracker::GitHub, "element-hq/element-ios#12", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(|e| e.date_time()).collect::<Vec<_>>(),
+			["2021-07-08/161300", "2021-07-09/010203"]
+		);
+		assert_eq!(server.api_requests().len(), 3);
+	}
+
+	#[tokio::test]
+	async fn creates_issue() {
+		let server = MockServer::start().await;
+		let env = github_env(&server);
+
+		server.add_entry(
+			"2021-07-08/161300",
+			&["app crashed", "user_id: @bob:example.org"],
+			&[],
+		);
+		server.set_api_response("GET /user", r#"{"login":"bob"}"#);
+		server.set_api_response(
+			"POST /repos/element-hq/element-ios/issues",
+			r#"{"number":34,"title":"app crashed","state":"open","html_url":"https://github.com/element-hq/element-ios/issues/34"}"#,
+		);
+
+		let options = CreateOptions {
+			target: "github:element-hq/element-ios".to_owned(),
+			labels: vec!["T-Defect".to_owned()],
+			assignee: Some("me".to_owned()),
+			project: None,
+			term: None,
+			dry_run: false,
+		};
+
+		create("2021-07-08/161300", options, &env.config)
+			.await
+			.unwrap();
+
+		let requests = server.api_requests();
+		assert_eq!(requests.len(), 2);
+		assert_eq!(requests[1].0, "POST /repos/element-hq/element-ios/issues");
+
+		let body = serde_json::from_str::<serde_json::Value>(&requests[1].2).unwrap();
+		assert_eq!(body["title"], "app crashed");
+		assert_eq!(body["labels"], serde_json::json!(["T-Defect"]));
+		assert_eq!(body["assignees"], serde_json::json!(["bob"]));
+		assert!(body["body"]
+			.as_str()
+			.unwrap()
+			.contains("- **User:** @bob:example.org"));
+	}
+
+	#[tokio::test]
+	async fn needs_to_know_who_me_is() {
+		let server = MockServer::start().await;
+		let env = github_env(&server);
+
+		server.add_entry("2021-07-08/161300", &["app crashed"], &[]);
+		server.set_api_response("GET /user", r#"{"message":"Bad credentials"}"#);
+
+		let options = CreateOptions {
+			target: "github:element-hq/element-ios".to_owned(),
+			labels: Vec::new(),
+			assignee: Some("me".to_owned()),
+			project: None,
+			term: None,
+			dry_run: false,
+		};
+
+		assert!(create("2021-07-08/161300", options, &env.config)
+			.await
+			.is_err());
+		assert!(!server
+			.api_requests()
+			.iter()
+			.any(|(route, _, _)| route.starts_with("POST")));
+	}
+}
@@ -0,0 +1,276 @@
+// GitLab's REST API, where issues are referred to as `group/project#123`
+
+use super::{join_text, next_page, send, send_with_headers, IssueRef, NewIssue, Prepared};
+use crate::config::Config;
+use reqwest::Method;
+
+fn request(method: Method, url: &str, config: &Config) -> reqwest::RequestBuilder {
+	let request = reqwest::Client::new().request(method, url);
+
+	match &config.gitlab.token {
+		Some(token) => request.header("PRIVATE-TOKEN", token),
+		None => request,
+	}
+}
+
+// the url of the project, which gitlab wants with its slashes escaped
+fn project_url(project: &str, config: &Config) -> String {
+	format!(
+		"{}/projects/{}",
+		config.gitlab.api_url,
+		project.replace('/', "%2F")
+	)
+}
+
+// splits `group/project#123` into the project and the number
+fn split_issue(issue: &str) -> Result<(&str, u64), String> {
+	issue
+		.split_once('#')
+		.filter(|(project, _)| project.contains('/'))
+		.and_then(|(project, num)| Some((project, num.parse().ok()?)))
+		.ok_or_else(|| {
+			"Please input the issue in the format of `gitlab:GROUP/PROJECT#NUM` (e.g. `gitlab:element/element-ios#1000`)".to_owned()
+		})
+}
+
+// the description of the issue and the body of every comment on it
+pub async fn issue_text(
+	issue: &str,
+	config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+	let (project, num) = split_issue(issue)?;
+	let url = format!("{}/issues/{num}", project_url(project, config));
+
+	let issue = send(request(Method::GET, &url, config)).await?;
+
+	// gitlab only gives out 100 notes at a time, and says where the rest are in the headers
+	let mut notes = Vec::new();
+	let mut page = Some(format!("{url}/notes?per_page=100"));
+
+	while let Some(page_url) = page {
+		let (headers, body) = send_with_headers(request(Method::GET, &page_url, config)).await?;
+
+		// `x-next-page` is only the number, and it's left empty on the last page
+		page = next_page(&headers).or_else(|| {
+			headers
+				.get("x-next-page")
+				.and_then(|p| p.to_str().ok())
+				.and_then(|p| p.parse::<u64>().ok())
+				.map(|p| format!("{url}/notes?per_page=100&page={p}"))
+		});
+
+		if let serde_json::Value::Array(body) = body {
+			notes.extend(body);
+		}
+	}
+
+	Ok(join_text(
+		std::iter::once(&issue["description"]).chain(notes.iter().map(|n| &n["body"])),
+	))
+}
+
+pub async fn prepare(
+	project: &str,
+	issue: &NewIssue,
+	config: &Config,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+	if !project.contains('/') {
+		return Err(format!("Please give the project to file the issue in as `gitlab:GROUP/PROJECT`, not '{project}'").into());
+	}
+
+	if issue.project.is_some() {
+		return Err("The project is where the issue is filed on gitlab (e.g. `gitlab:group/project`); leave out --project".into());
+	}
+
+	let mut body = serde_json::json!({ "title": issue.title, "description": issue.description });
+
+	if !issue.labels.is_empty() {
+		body["labels"] = issue.labels.join(",").into();
+	}
+
+	if let Some(assignee) = issue.assignee.as_deref() {
+		// gitlab wants the user's id, not their name
+		let id = if assignee == "me" {
+			let user = send(request(
+				Method::GET,
+				&format!("{}/user", config.gitlab.api_url),
+				config,
+			))
+			.await?;
+			user["id"].clone()
+		} else {
+			let users = send(
+				request(
+					Method::GET,
+					&format!("{}/users", config.gitlab.api_url),
+					config,
+				)
+				.query(&[("username", assignee)]),
+			)
+			.await?;
+			users[0]["id"].clone()
+		};
+
+		if id.is_null() {
+			return Err(format!("there's no one on gitlab called '{assignee}'").into());
+		}
+
+		body["assignee_ids"] = serde_json::json!([id]);
+	}
+
+	Ok(Prepared {
+		url: format!("{}/issues", project_url(project, config)),
+		body,
+	})
+}
+
+pub async fn submit(
+	project: &str,
+	prepared: &Prepared,
+	config: &Config,
+) -> Result<IssueRef, Box<dyn std::error::Error>> {
+	let created = send(request(Method::POST, &prepared.url, config).json(&prepared.body)).await?;
+
+	Ok(IssueRef {
+		identifier: format!("{project}#{}", created["iid"]),
+		title: created["title"].as_str().unwrap_or_default().to_owned(),
+		state: created["state"].as_str().unwrap_or("unknown").to_owned(),
+		url: created["web_url"].as_str().unwrap_or_default().to_owned(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		mock::{MockServer, TestEnv},
+		tracker::{create, find_issue_entries, CreateOptions, Tracker},
+	};
+
+	fn gitlab_env(server: &MockServer) -> TestEnv {
+		TestEnv::new(
+			server,
+			&format!(
+				"[gitlab]\ntoken = \"gl-token\"\napi-url = \"{}\"",
+				server.url
+			),
+		)
+	}
+
+	#[tokio::test]
+	async fn finds_links_in_issue_and_notes() {
+		let server = MockServer::start().await;
+		let env = gitlab_env(&server);
+
+		server.set_api_response(
+			"GET /projects/element%2Fios/issues/7",
+			r#"{"iid":7,"description":"no logs here"}"#,
+		);
+		server.set_api_response(
+			"GET /projects/element%2Fios/issues/7/notes",
+			&format!(
+				r#"[{{"body":"{}/api/listing/2021-07-08/161300"}}]"#,
+				server.url
+			),
+		);
+
+		let entries = find_issue_entries(Tracker::GitLab, "element/ios#7", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(|e| e.date_time()).collect::<Vec<_>>(),
+			["2021-07-08/161300"]
+		);
+		assert_eq!(server.api_requests()[0].1.as_deref(), Some("gl-token"));
+	}
+
+	#[tokio::test]
+	async fn follows_every_page_of_notes() {
+		let server = MockServer::start().await;
+		let env = gitlab_env(&server);
+
+		let notes = "/projects/element%2Fios/issues/7/notes";
+		let note =
+			|day_time: &str| format!(r#"[{{"body":"{}/api/listing/{day_time}"}}]"#, server.url);
+
+		server.set_api_response(
+			"GET /projects/element%2Fios/issues/7",
+			r#"{"iid":7,"description":null}"#,
+		);
+
+		// the first page says where the next one is with `Link`, and the second with only
+		// `x-next-page`
+		server.set_api_response(&format!("GET {notes}"), &note("2021-07-08/161300"));
+		server.set_api_headers(
+			&format!("GET {notes}"),
+			&[(
+				"Link",
+				&format!(r#"<{}{notes}?per_page=100&page=2>; rel="next""#, server.url),
+			)],
+		);
+		server.set_api_response(
+			&format!("GET {notes}?per_page=100&page=2"),
+			&note("2021-07-09/010203"),
+		);
+		server.set_api_headers(
+			&format!("GET {notes}?per_page=100&page=2"),
+			&[("x-next-page", "3")],
+		);
+		server.set_api_response(
+			&format!("GET {notes}?per_page=100&page=3"),
+			&note("2021-07-10/121212"),
+		);
+		server.set_api_headers(
+			&format!("GET {notes}?per_page=100&page=3"),
+			&[("x-next-page", "")],
+		);
+
+		let entries = find_issue_entries(Tracker::GitLab, "element/ios#7", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(|e| e.date_time()).collect::<Vec<_>>(),
+			[
+				"2021-07-08/161300",
+				"2021-07-09/010203",
+				"2021-07-10/121212"
+			]
+		);
+		assert_eq!(server.api_requests().len(), 4);
+	}
+
+	#[tokio::test]
+	async fn creates_issue() {
+		let server = MockServer::start().await;
+		let env = gitlab_env(&server);
+
+		server.add_entry("2021-07-08/161300", &["app crashed"], &[]);
+		server.set_api_response("GET /users", r#"[{"id":42,"username":"alice"}]"#);
+		server.set_api_response(
+			"POST /projects/element%2Fios/issues",
+			r#"{"iid":8,"title":"app crashed","state":"opened","web_url":"https://gitlab.com/element/ios/-/issues/8"}"#,
+		);
+
+		let options = CreateOptions {
+			target: "gitlab:element/ios".to_owned(),
+			labels: vec!["bug".to_owned(), "crash".to_owned()],
+			assignee: Some("alice".to_owned()),
+			project: None,
+			term: None,
+			dry_run: false,
+		};
+
+		create("2021-07-08/161300", options, &env.config)
+			.await
+			.unwrap();
+
+		let requests = server.api_requests();
+		assert_eq!(requests[0].0, "GET /users?username=alice");
+
+		let body = serde_json::from_str::<serde_json::Value>(&requests[1].2).unwrap();
+		assert_eq!(body["title"], "app crashed");
+		assert_eq!(body["labels"], "bug,crash");
+		assert_eq!(body["assignee_ids"], serde_json::json!([42]));
+	}
+}
@@ -0,0 +1,215 @@
+// Jira's REST API (version 2, which takes descriptions as plain text), where issues are referred
+// to as `PROJ-123`
+
+use super::{join_text, send, IssueRef, NewIssue, Prepared};
+use crate::config::Config;
+use reqwest::Method;
+
+fn request(method: Method, url: &str, config: &Config) -> reqwest::RequestBuilder {
+	let request = reqwest::Client::new().request(method, url);
+
+	// jira cloud wants the email and token as basic auth, but self-hosted ones take the token on
+	// its own
+	match (&config.jira.email, &config.jira.token) {
+		(Some(email), token) => request.basic_auth(email, token.as_ref()),
+		(None, Some(token)) => request.bearer_auth(token),
+		(None, None) => request,
+	}
+}
+
+// jira's always self-hosted or on its own subdomain, so there's no url to fall back to
+fn api_url(config: &Config) -> Result<&str, String> {
+	if config.jira.api_url.is_empty() {
+		return Err("There's no api-url in the [jira] section of the config file (e.g. `https://example.atlassian.net`)".to_owned());
+	}
+
+	Ok(&config.jira.api_url)
+}
+
+// the description of the issue and the body of every comment on it
+pub async fn issue_text(
+	issue: &str,
+	config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+	let key_regex = regex::Regex::new(r"^[A-Z][A-Z0-9_]*-\d+$")?;
+	if !key_regex.is_match(issue) {
+		return Err(
+			"Please input the issue in the format of `jira:PROJ-NUM` (e.g. `jira:ELEMENT-1000`)"
+				.into(),
+		);
+	}
+
+	let url = format!(
+		"{}/rest/api/2/issue/{issue}?fields=description,comment",
+		api_url(config)?
+	);
+	let issue = send(request(Method::GET, &url, config)).await?;
+
+	let fields = &issue["fields"];
+	let comments = fields["comment"]["comments"].as_array();
+
+	Ok(join_text(
+		std::iter::once(&fields["description"])
+			.chain(comments.into_iter().flatten().map(|c| &c["body"])),
+	))
+}
+
+pub async fn prepare(
+	project: &str,
+	issue: &NewIssue,
+	config: &Config,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+	let api_url = api_url(config)?;
+
+	if issue.project.is_some() {
+		return Err("The project is where the issue is filed on jira (e.g. `jira:PROJ`); leave out --project".into());
+	}
+
+	let mut fields = serde_json::json!({
+		"project": { "key": project },
+		"summary": issue.title,
+		"description": issue.description,
+		"issuetype": { "name": config.jira.issue_type },
+	});
+
+	if !issue.labels.is_empty() {
+		fields["labels"] = issue.labels.clone().into();
+	}
+
+	if let Some(assignee) = issue.assignee.as_deref() {
+		// jira cloud only knows people by their account id
+		let account_id = if assignee == "me" {
+			let user = send(request(
+				Method::GET,
+				&format!("{api_url}/rest/api/2/myself"),
+				config,
+			))
+			.await?;
+			user["accountId"].clone()
+		} else {
+			let users = send(
+				request(
+					Method::GET,
+					&format!("{api_url}/rest/api/2/user/search"),
+					config,
+				)
+				.query(&[("query", assignee)]),
+			)
+			.await?;
+			users[0]["accountId"].clone()
+		};
+
+		if account_id.is_null() {
+			return Err(format!("there's no one on jira called '{assignee}'").into());
+		}
+
+		fields["assignee"] = serde_json::json!({ "accountId": account_id });
+	}
+
+	Ok(Prepared {
+		url: format!("{api_url}/rest/api/2/issue"),
+		body: serde_json::json!({ "fields": fields }),
+	})
+}
+
+pub async fn submit(
+	prepared: &Prepared,
+	config: &Config,
+) -> Result<IssueRef, Box<dyn std::error::Error>> {
+	let created = send(request(Method::POST, &prepared.url, config).json(&prepared.body)).await?;
+
+	let Some(key) = created["key"].as_str() else {
+		return Err(format!("jira didn't say which issue it created: {created}").into());
+	};
+
+	Ok(IssueRef {
+		identifier: key.to_owned(),
+		title: prepared.body["fields"]["summary"]
+			.as_str()
+			.unwrap_or_default()
+			.to_owned(),
+		// new issues start out in whatever the project's workflow says, which jira doesn't tell us
+		state: "unknown".to_owned(),
+		url: format!("{}/browse/{key}", api_url(config)?),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		mock::{MockServer, TestEnv},
+		tracker::{create, find_issue_entries, CreateOptions, Tracker},
+	};
+	use base64::Engine;
+
+	fn jira_env(server: &MockServer) -> TestEnv {
+		TestEnv::new(
+			server,
+			&format!(
+				"[jira]\ntoken = \"jira-token\"\nemail = \"me@example.org\"\nissue-type = \"Task\"\napi-url = \"{}\"",
+				server.url
+			),
+		)
+	}
+
+	#[tokio::test]
+	async fn finds_links_in_issue_and_comments() {
+		let server = MockServer::start().await;
+		let env = jira_env(&server);
+
+		server.set_api_response(
+			"GET /rest/api/2/issue/ELEMENT-3",
+			&format!(
+				r#"{{"key":"ELEMENT-3","fields":{{"description":"{url}/api/listing/2021-07-08/161300","comment":{{"comments":[{{"body":"{url}/api/listing/2021-07-10/101010"}}]}}}}}}"#,
+				url = server.url
+			),
+		);
+
+		let entries = find_issue_entries(Tracker::Jira, "ELEMENT-3", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(|e| e.date_time()).collect::<Vec<_>>(),
+			["2021-07-08/161300", "2021-07-10/101010"]
+		);
+
+		let expected = format!(
+			"Basic {}",
+			base64::engine::general_purpose::STANDARD.encode("me@example.org:jira-token")
+		);
+		assert_eq!(server.api_requests()[0].1, Some(expected));
+	}
+
+	#[tokio::test]
+	async fn creates_issue() {
+		let server = MockServer::start().await;
+		let env = jira_env(&server);
+
+		server.add_entry("2021-07-08/161300", &["app crashed"], &[]);
+		server.set_api_response(
+			"POST /rest/api/2/issue",
+			r#"{"id":"10001","key":"ELEMENT-4","self":"https://example.atlassian.net/rest/api/2/issue/10001"}"#,
+		);
+
+		let options = CreateOptions {
+			target: "jira:ELEMENT".to_owned(),
+			labels: Vec::new(),
+			assignee: None,
+			project: None,
+			term: None,
+			dry_run: false,
+		};
+
+		create("2021-07-08/161300", options, &env.config)
+			.await
+			.unwrap();
+
+		let requests = server.api_requests();
+		let body = serde_json::from_str::<serde_json::Value>(&requests[0].2).unwrap();
+
+		assert_eq!(body["fields"]["project"]["key"], "ELEMENT");
+		assert_eq!(body["fields"]["summary"], "app crashed");
+		assert_eq!(body["fields"]["issuetype"]["name"], "Task");
+	}
+}
@@ -0,0 +1,1042 @@
+// Linear's GraphQL API, where issues are referred to as `TEAM-NUM`. It's also the only tracker
+// that we keep track of the links from, for `refs` and `search --linked`.
+
+use super::{entries_in, has_token, IssueRef, NewIssue, Prepared, Tracker};
+use crate::{
+	config::Config,
+	entry::Entry,
+	err,
+	errors::SyncErrors,
+	sync::{download_files, Download, SyncTracker},
+	sync_dir, warn,
+};
+use futures::StreamExt;
+use std::{
+	fs,
+	path::PathBuf,
+	sync::{Arc, Mutex},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+// how long to believe what's cached about the issues that link to an entry before asking linear
+// again, since links can be added and removed, and the issues' titles and states change
+const REFS_RECHECK_SECS: u64 = 60 * 60 * 24;
+// how many entries to ask about in each query when checking which are linked to
+const REFS_PER_QUERY: usize = 20;
+
+// how many issues to ask for at a time when syncing
+const ISSUES_PER_PAGE: usize = 50;
+
+const ISSUES_PAGE: &str = "query Issues($filter: IssueFilter, $first: Int, $after: String) { issues(filter: $filter, first: $first, after: $after) { pageInfo { hasNextPage endCursor } nodes { identifier title description comments { nodes { body } } attachments { nodes { url } } } } }";
+const ISSUE_CREATE: &str = "mutation IssueCreate($input: IssueCreateInput!) { issueCreate(input: $input) { success issue { identifier title url state { name } } } }";
+
+// the whole response to asking for the issue's description, comments, and attachments, since
+// that's all the text there is to find links in
+pub async fn issue_text(
+	issue: &str,
+	config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+	let format = "Please input the issue in the format of `TEAM-NUM` (e.g. `IOS-1000`)";
+
+	let Some((team, num)) = issue.rsplit_once('-') else {
+		return Err(format.into());
+	};
+	let num = num.parse::<u64>().map_err(|_| format)?;
+
+	let query = format!("{{ issues(filter: {{ number: {{ eq: {num} }} team: {{ key: {{ eq: \"{team}\" }} }} }}) {{ nodes {{ description comments {{ nodes {{ body }} }} attachments {{ nodes {{ url title subtitle }} }} }} }} }}");
+	Ok(graphql(&serde_json::json!({ "query": query }), config).await?)
+}
+
+// lists the issues whose description or comments link to the entry at `day_time`
+pub async fn refs(day_time: &str, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+	if !has_token(Tracker::Linear, config) {
+		return Ok(());
+	}
+
+	let issues = fetch_refs(&[day_time.to_owned()], config)
+		.await?
+		.pop()
+		.unwrap_or_default();
+
+	// may as well save what we found so `search --linked` doesn't have to ask again
+	let mut cache = read_refs_cache();
+	cache_refs(&mut cache, day_time, &issues);
+	if let Err(err) = write_refs_cache(&cache) {
+		warn!(
+			"Couldn't save linked issues to {:?}: {err}",
+			refs_cache_file()
+		);
+	}
+
+	if issues.is_empty() {
+		println!("No issues on linear reference {day_time}");
+	}
+
+	for issue in issues {
+		println!(
+			"\x1b[1m{}\x1b[0m [{}] {}",
+			issue.identifier, issue.state, issue.title
+		);
+	}
+
+	Ok(())
+}
+
+// which issues to sync the linked entries of; an issue has to match every one that's given
+#[derive(Default)]
+pub struct SyncOptions {
+	// `current`, `previous`, `next`, or the cycle's number
+	pub cycle: Option<String>,
+	pub project: Option<String>,
+	pub label: Option<String>,
+	// text that has to be somewhere in the issue's title, description, or comments
+	pub query: Option<String>,
+	pub team: Option<String>,
+}
+
+// the `IssueFilter` that linear should use to find the issues for `options`
+fn issue_filter(options: &SyncOptions) -> Result<serde_json::Value, String> {
+	let mut filter = serde_json::Map::new();
+
+	if let Some(cycle) = options.cycle.as_deref() {
+		let cycle_filter = match cycle {
+			"current" => serde_json::json!({ "isActive": { "eq": true } }),
+			"previous" => serde_json::json!({ "isPrevious": { "eq": true } }),
+			"next" => serde_json::json!({ "isNext": { "eq": true } }),
+			num => match num.parse::<u64>() {
+				Ok(num) => serde_json::json!({ "number": { "eq": num } }),
+				Err(_) => {
+					return Err(format!(
+						"The cycle must be `current`, `previous`, `next`, or a cycle number, not `{num}`"
+					))
+				}
+			},
+		};
+		filter.insert("cycle".to_owned(), cycle_filter);
+	}
+
+	if let Some(project) = &options.project {
+		filter.insert(
+			"project".to_owned(),
+			serde_json::json!({ "name": { "eqIgnoreCase": project } }),
+		);
+	}
+
+	if let Some(label) = &options.label {
+		filter.insert(
+			"labels".to_owned(),
+			serde_json::json!({ "some": { "name": { "eqIgnoreCase": label } } }),
+		);
+	}
+
+	if let Some(query) = &options.query {
+		filter.insert(
+			"searchableContent".to_owned(),
+			serde_json::json!({ "contains": query }),
+		);
+	}
+
+	// the team on its own would be every issue they've ever had, so it only narrows the others
+	if filter.is_empty() {
+		return Err(
+			"Please choose which issues to sync with --cycle, --project, --label, or --query"
+				.to_owned(),
+		);
+	}
+
+	if let Some(team) = &options.team {
+		filter.insert(
+			"team".to_owned(),
+			serde_json::json!({ "key": { "eq": team } }),
+		);
+	}
+
+	Ok(serde_json::Value::Object(filter))
+}
+
+// downloads every entry linked from the issues that match `options`, then shows which issue
+// linked to which entries
+pub async fn sync(
+	options: &SyncOptions,
+	config: Arc<Config>,
+) -> Result<(), Box<dyn std::error::Error>> {
+	if !has_token(Tracker::Linear, &config) {
+		return Ok(());
+	}
+
+	let filter = issue_filter(options)?;
+
+	// each issue's identifier and title, with the entries that it links to
+	let mut issues: Vec<(String, String, Vec<Entry>)> = Vec::new();
+	let mut after = serde_json::Value::Null;
+
+	// linear only gives out so many issues at a time, so keep asking until it's given them all
+	loop {
+		let body = serde_json::json!({
+			"query": ISSUES_PAGE,
+			"variables": { "filter": filter, "first": ISSUES_PER_PAGE, "after": after },
+		});
+		let response = serde_json::from_str::<serde_json::Value>(&graphql(&body, &config).await?)?;
+
+		if let Some(errors) = response.get("errors") {
+			return Err(format!("linear responded with errors: {errors}").into());
+		}
+
+		let page = &response["data"]["issues"];
+
+		for issue in page["nodes"].as_array().into_iter().flatten() {
+			issues.push((
+				issue["identifier"].as_str().unwrap_or_default().to_owned(),
+				issue["title"].as_str().unwrap_or_default().to_owned(),
+				entries_in(&issue.to_string(), &config)?,
+			));
+		}
+
+		match &page["pageInfo"] {
+			info if info["hasNextPage"].as_bool() == Some(true) => {
+				after = info["endCursor"].clone();
+			}
+			_ => break,
+		}
+	}
+
+	println!("🔎 Found {} matching issues on linear", issues.len());
+
+	// the same entry can be linked from a few issues, but only needs downloading once
+	let mut present: Vec<String> = Vec::new();
+	let mut to_download: Vec<Entry> = Vec::new();
+
+	for entry in issues.iter().flat_map(|(_, _, entries)| entries) {
+		let date_time = entry.date_time();
+
+		if present.contains(&date_time) || to_download.iter().any(|e| e.date_time() == date_time) {
+			continue;
+		}
+
+		if entry.has_logs() {
+			present.push(date_time);
+		} else {
+			to_download.push(Entry::new(&entry.day, &entry.time, config.clone()));
+		}
+	}
+
+	let failed = download_entries(to_download, &config).await;
+
+	for (identifier, title, entries) in &issues {
+		println!("\x1b[1m{identifier}\x1b[0m {title}");
+
+		if entries.is_empty() {
+			println!("\tno linked entries");
+		}
+
+		for date_time in entries.iter().map(Entry::date_time) {
+			if failed.contains(&date_time) {
+				println!("\t❌ {date_time} (couldn't be downloaded)");
+			} else if present.contains(&date_time) {
+				println!("\t📁 {date_time} (already downloaded)");
+			} else {
+				println!("\t✅ {date_time}");
+			}
+		}
+	}
+
+	let total = issues
+		.iter()
+		.flat_map(|(_, _, entries)| entries.iter().map(Entry::date_time))
+		.fold(Vec::new(), |mut all, date_time| {
+			if !all.contains(&date_time) {
+				all.push(date_time);
+			}
+			all
+		})
+		.len();
+
+	println!(
+		"✨ Downloaded {} of {total} linked entries ({} already downloaded, {} failed)",
+		total - present.len() - failed.len(),
+		present.len(),
+		failed.len()
+	);
+
+	Ok(())
+}
+
+// downloads all the files of every entry with the sync machinery, so no more than `threads`
+// requests are made at once. Returns the entries that couldn't be fully downloaded
+async fn download_entries(mut entries: Vec<Entry>, config: &Arc<Config>) -> Vec<String> {
+	let mut failed: Vec<String> = Vec::new();
+
+	if entries.is_empty() {
+		return failed;
+	}
+
+	// we need to know what files each entry has before any of them can be downloaded
+	let listed = futures::stream::iter(entries.iter_mut().map(|entry| async move {
+		let res = entry.retrieve_file_list(true).await;
+		(entry, res)
+	}))
+	.buffer_unordered(config.threads)
+	.collect::<Vec<_>>()
+	.await;
+
+	let state = Arc::new(Mutex::new(SyncTracker {
+		prefix: "Downloading files:".to_owned(),
+		started: 0,
+		done: 0,
+		total: 0,
+		completed: Vec::new(),
+	}));
+	let mut downloads: Vec<Download> = Vec::new();
+
+	for (entry, res) in listed {
+		let date_time = entry.date_time();
+
+		if let Err(err) = res {
+			err!("Couldn't get the list of files in {date_time}: {err}");
+			failed.push(date_time);
+			continue;
+		}
+
+		if let Err(err) = fs::create_dir_all(sync_dir().join(&date_time)) {
+			err!("Couldn't create the directory for {date_time}: {err}");
+			failed.push(date_time);
+			continue;
+		}
+
+		downloads.extend(entry.files.iter().flatten().map(|f| Download {
+			subdir: format!("{date_time}/{f}"),
+			is_cache: false,
+			// only syncing finds new entries
+			new_entry: false,
+			state: state.clone(),
+			config: config.clone(),
+		}));
+	}
+
+	match download_files(downloads, &state, config).await {
+		Ok(()) => (),
+		Err(SyncErrors::FilesDownloadFailed(files)) => {
+			for (date_time, _) in files.iter().filter_map(|f| f.subdir.rsplit_once('/')) {
+				if !failed.iter().any(|f| f == date_time) {
+					failed.push(date_time.to_owned());
+				}
+			}
+		}
+		Err(err) => err!("Couldn't download the linked entries: {err}"),
+	}
+
+	failed
+}
+
+// the issues that link to each of the entries, in the same order as the entries. These come from
+// the cache where possible, and everything that isn't is asked for and then cached
+pub async fn linked_issues(
+	entries: &[Entry],
+	config: &Config,
+) -> Result<Vec<Vec<IssueRef>>, Box<dyn std::error::Error>> {
+	if config.linear.token.is_none() {
+		return Err("there's no token in the [linear] section of the config file".into());
+	}
+
+	let mut cache = read_refs_cache();
+	let now = now_secs();
+
+	let uncached = entries
+		.iter()
+		.map(Entry::date_time)
+		.filter(|date_time| {
+			cache.get(date_time).is_none_or(|cached| {
+				let checked = cached["checked"].as_u64().unwrap_or_default();
+				now.saturating_sub(checked) > REFS_RECHECK_SECS
+			})
+		})
+		.collect::<Vec<_>>();
+
+	if !uncached.is_empty() {
+		for chunk in uncached.chunks(REFS_PER_QUERY) {
+			for (date_time, issues) in chunk.iter().zip(fetch_refs(chunk, config).await?) {
+				cache_refs(&mut cache, date_time, &issues);
+			}
+		}
+
+		if let Err(err) = write_refs_cache(&cache) {
+			warn!(
+				"Couldn't save linked issues to {:?}: {err}",
+				refs_cache_file()
+			);
+		}
+	}
+
+	Ok(entries
+		.iter()
+		.map(|entry| {
+			cache
+				.get(&entry.date_time())
+				.and_then(|cached| cached["issues"].as_array())
+				.into_iter()
+				.flatten()
+				.filter_map(IssueRef::from_json)
+				.collect()
+		})
+		.collect())
+}
+
+// looks up the ids of the team, labels, assignee, and project, since that's what linear wants
+// when creating an issue
+pub async fn prepare(
+	team: &str,
+	issue: &NewIssue,
+	config: &Config,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+	// the names are put in the query as json strings, which graphql reads the same way
+	let quote = |s: &str| serde_json::Value::from(s).to_string();
+
+	let mut query = format!(
+		"teams(filter: {{ key: {{ eq: {} }} }}) {{ nodes {{ id }} }}",
+		quote(team)
+	);
+
+	if !issue.labels.is_empty() {
+		let names = issue.labels.iter().map(|l| quote(l)).collect::<Vec<_>>();
+		query += &format!(
+			" issueLabels(filter: {{ name: {{ in: [{}] }} }}) {{ nodes {{ id name team {{ key }} }} }}",
+			names.join(", ")
+		);
+	}
+
+	match issue.assignee.as_deref() {
+		Some("me") => query += " viewer { id }",
+		Some(assignee) => {
+			let assignee = quote(assignee);
+			query += &format!(" users(filter: {{ or: [{{ email: {{ eqIgnoreCase: {assignee} }} }}, {{ displayName: {{ eqIgnoreCase: {assignee} }} }}, {{ name: {{ eqIgnoreCase: {assignee} }} }}] }}) {{ nodes {{ id }} }}");
+		}
+		None => (),
+	}
+
+	if let Some(project) = issue.project.as_deref() {
+		query += &format!(
+			" projects(filter: {{ name: {{ eqIgnoreCase: {} }} }}) {{ nodes {{ id }} }}",
+			quote(project)
+		);
+	}
+
+	let text = graphql(
+		&serde_json::json!({ "query": format!("{{ {query} }}") }),
+		config,
+	)
+	.await?;
+	let response = serde_json::from_str::<serde_json::Value>(&text)?;
+
+	if let Some(errors) = response.get("errors") {
+		return Err(format!("linear responded with errors: {errors}").into());
+	}
+
+	let data = &response["data"];
+	let first_id = |field: &str| {
+		data[field]["nodes"][0]["id"]
+			.as_str()
+			.map(ToOwned::to_owned)
+	};
+
+	let Some(team_id) = first_id("teams") else {
+		return Err(format!("there's no team on linear with the key '{team}'").into());
+	};
+
+	let mut input = serde_json::json!({ "teamId": team_id });
+
+	if !issue.labels.is_empty() {
+		let found = data["issueLabels"]["nodes"]
+			.as_array()
+			.cloned()
+			.unwrap_or_default();
+
+		let label_ids = issue
+			.labels
+			.iter()
+			.map(|name| {
+				// labels either belong to the whole workspace or to a single team, and we can only
+				// use the ones that this team can see
+				found
+					.iter()
+					.find(|l| {
+						l["name"].as_str() == Some(name.as_str())
+							&& l["team"]["key"].as_str().is_none_or(|key| key == team)
+					})
+					.and_then(|l| l["id"].as_str())
+					.ok_or_else(|| format!("there's no label called '{name}' for {team}"))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		input["labelIds"] = label_ids.into();
+	}
+
+	if let Some(assignee) = issue.assignee.as_deref() {
+		let id = if assignee == "me" {
+			data["viewer"]["id"].as_str().map(ToOwned::to_owned)
+		} else {
+			first_id("users")
+		};
+
+		input["assigneeId"] = id
+			.ok_or_else(|| format!("there's no one on linear called '{assignee}'"))?
+			.into();
+	}
+
+	if let Some(project) = issue.project.as_deref() {
+		input["projectId"] = first_id("projects")
+			.ok_or_else(|| format!("there's no project on linear called '{project}'"))?
+			.into();
+	}
+
+	input["title"] = issue.title.as_str().into();
+	input["description"] = issue.description.as_str().into();
+
+	Ok(Prepared {
+		url: config.linear.api_url.clone(),
+		body: serde_json::json!({ "query": ISSUE_CREATE, "variables": { "input": input } }),
+	})
+}
+
+pub async fn submit(
+	prepared: &Prepared,
+	config: &Config,
+) -> Result<IssueRef, Box<dyn std::error::Error>> {
+	let text = graphql(&prepared.body, config).await?;
+	let response = serde_json::from_str::<serde_json::Value>(&text)?;
+
+	if let Some(errors) = response.get("errors") {
+		return Err(format!("linear responded with errors: {errors}").into());
+	}
+
+	IssueRef::from_json(&response["data"]["issueCreate"]["issue"])
+		.ok_or_else(|| format!("linear didn't say which issue it created: {text}").into())
+}
+
+// adds a newly created issue to the ones that are known to link to the entry. If we don't know
+// what else links to it yet, caching just this one would hide the others until the cache goes
+// stale, so it's left for the next lookup to ask linear about all of them
+pub fn remember(day_time: &str, issue: IssueRef) {
+	let mut cache = read_refs_cache();
+
+	let Some(issues) = cache
+		.get_mut(day_time)
+		.and_then(|cached| cached["issues"].as_array_mut())
+	else {
+		return;
+	};
+	issues.push(issue.to_json());
+
+	if let Err(err) = write_refs_cache(&cache) {
+		warn!(
+			"Couldn't save linked issues to {:?}: {err}",
+			refs_cache_file()
+		);
+	}
+}
+
+// asks linear for the issues whose description or comments contain the listing url of each of the
+// `date_times`, all in one query
+async fn fetch_refs(
+	date_times: &[String],
+	config: &Config,
+) -> Result<Vec<Vec<IssueRef>>, Box<dyn std::error::Error>> {
+	let fields = "identifier title url state { name }";
+
+	// every entry gets its own aliases so they can all be asked about at once
+	let query = date_times
+		.iter()
+		.enumerate()
+		.map(|(idx, date_time)| {
+			let url = format!("{}/api/listing/{date_time}", config.server);
+			format!("i{idx}: issues(filter: {{ description: {{ contains: \"{url}\" }} }}) {{ nodes {{ {fields} }} }} \
+				c{idx}: comments(filter: {{ body: {{ contains: \"{url}\" }} }}) {{ nodes {{ issue {{ {fields} }} }} }}")
+		})
+		.collect::<Vec<_>>()
+		.join(" ");
+
+	let text = graphql(
+		&serde_json::json!({ "query": format!("{{ {query} }}") }),
+		config,
+	)
+	.await?;
+	let response = serde_json::from_str::<serde_json::Value>(&text)?;
+
+	if let Some(errors) = response.get("errors") {
+		return Err(format!("linear responded with errors: {errors}").into());
+	}
+
+	let data = &response["data"];
+
+	Ok((0..date_times.len())
+		.map(|idx| {
+			let described = data[format!("i{idx}").as_str()]["nodes"].as_array();
+			let commented = data[format!("c{idx}").as_str()]["nodes"].as_array();
+
+			let mut issues: Vec<IssueRef> = Vec::new();
+
+			for issue in described
+				.into_iter()
+				.flatten()
+				.chain(commented.into_iter().flatten().map(|c| &c["issue"]))
+				.filter_map(IssueRef::from_json)
+			{
+				if !issues.iter().any(|i| i.identifier == issue.identifier) {
+					issues.push(issue);
+				}
+			}
+
+			issues
+		})
+		.collect())
+}
+
+// sends the graphql request `body` (the query and any variables) to linear and returns the text
+// of its response
+async fn graphql(body: &serde_json::Value, config: &Config) -> Result<String, reqwest::Error> {
+	let linear_token = config.linear.token.as_deref().unwrap_or_default();
+
+	reqwest::Client::new()
+		.post(&config.linear.api_url)
+		.header("Content-Type", "application/json")
+		.json(body)
+		.header("Authorization", linear_token)
+		.send()
+		.await?
+		.text()
+		.await
+}
+
+// the cache of which issues link to which entries is a json object of `day/time`s to
+// `{ "checked": <unix secs>, "issues": [...] }`
+fn refs_cache_file() -> PathBuf {
+	let mut file = crate::data_dir();
+	file.push("rager");
+	file.push("linear-refs.json");
+	file
+}
+
+fn read_refs_cache() -> serde_json::Map<String, serde_json::Value> {
+	fs::read_to_string(refs_cache_file())
+		.ok()
+		.and_then(|text| serde_json::from_str(&text).ok())
+		.unwrap_or_default()
+}
+
+fn write_refs_cache(cache: &serde_json::Map<String, serde_json::Value>) -> std::io::Result<()> {
+	let file = refs_cache_file();
+
+	if let Some(dir) = file.parent() {
+		fs::create_dir_all(dir)?;
+	}
+
+	fs::write(file, serde_json::Value::Object(cache.clone()).to_string())
+}
+
+fn cache_refs(
+	cache: &mut serde_json::Map<String, serde_json::Value>,
+	date_time: &str,
+	issues: &[IssueRef],
+) {
+	cache.insert(
+		date_time.to_owned(),
+		serde_json::json!({
+			"checked": now_secs(),
+			"issues": issues.iter().map(IssueRef::to_json).collect::<Vec<_>>(),
+		}),
+	);
+}
+
+fn now_secs() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		mock::{MockServer, TestEnv, LINEAR_TOKEN},
+		tracker::{create, find_issue_entries, CreateOptions},
+	};
+
+	#[tokio::test]
+	async fn finds_every_linked_entry() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.set_graphql_response(&format!(
+			r#"{{"data":{{"issues":{{"nodes":[{{"description":"Logs: {url}/api/listing/2021-07-08/161300 and {url}/api/listing/2021-07-09/010203","comments":{{"nodes":[{{"body":"Again: {url}/api/listing/2021-07-10/101010"}},{{"body":"Same as {url}/api/listing/2021-07-08/161300"}}]}},"attachments":{{"nodes":[{{"url":"{url}/api/listing/2021-07-11/111111","title":"rageshake","subtitle":null}}]}}}}]}}}}}}"#,
+			url = server.url
+		));
+
+		let entries = find_issue_entries(Tracker::Linear, "IOS-1000", &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			entries.iter().map(Entry::date_time).collect::<Vec<_>>(),
+			[
+				"2021-07-08/161300",
+				"2021-07-09/010203",
+				"2021-07-10/101010",
+				"2021-07-11/111111"
+			]
+		);
+
+		let requests = server.graphql_requests();
+		assert_eq!(requests.len(), 1);
+
+		let (auth, body) = &requests[0];
+		assert_eq!(auth.as_deref(), Some(LINEAR_TOKEN));
+		assert!(body.contains("number: { eq: 1000 }"), "{body}");
+		assert!(body.contains(r#"key: { eq: \"IOS\" }"#), "{body}");
+		assert!(body.contains("comments { nodes { body } }"), "{body}");
+		assert!(
+			body.contains("attachments { nodes { url title subtitle } }"),
+			"{body}"
+		);
+	}
+
+	#[tokio::test]
+	async fn asks_for_large_issue_numbers() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.set_graphql_response(r#"{"data":{"issues":{"nodes":[]}}}"#);
+
+		find_issue_entries(Tracker::Linear, "IOS-70000", &env.config)
+			.await
+			.unwrap();
+
+		let (_, body) = &server.graphql_requests()[0];
+		assert!(body.contains("number: { eq: 70000 }"), "{body}");
+	}
+
+	#[tokio::test]
+	async fn ignores_links_to_other_servers() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.set_graphql_response(
+			r#"{"data":{"issues":{"nodes":[{"description":"https://rageshake.example.org/api/listing/2021-07-08/161300"}]}}}"#,
+		);
+
+		assert!(find_issue_entries(Tracker::Linear, "IOS-1000", &env.config)
+			.await
+			.unwrap()
+			.is_empty());
+	}
+
+	#[tokio::test]
+	async fn finds_issues_linking_to_entry() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.set_graphql_response(
+			r#"{"data":{"i0":{"nodes":[{"identifier":"IOS-1000","title":"Crash when opening room","url":"https://linear.app/issue/IOS-1000","state":{"name":"Todo"}}]},"c0":{"nodes":[{"issue":{"identifier":"IOS-1010","title":"Timeline is blank","url":"https://linear.app/issue/IOS-1010","state":{"name":"Done"}}},{"issue":{"identifier":"IOS-1000","title":"Crash when opening room","state":{"name":"Todo"}}}]}}}"#,
+		);
+
+		let issues = fetch_refs(&["2021-07-08/161300".to_owned()], &env.config)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			issues,
+			[[
+				IssueRef {
+					identifier: "IOS-1000".to_owned(),
+					title: "Crash when opening room".to_owned(),
+					state: "Todo".to_owned(),
+					url: "https://linear.app/issue/IOS-1000".to_owned(),
+				},
+				IssueRef {
+					identifier: "IOS-1010".to_owned(),
+					title: "Timeline is blank".to_owned(),
+					state: "Done".to_owned(),
+					url: "https://linear.app/issue/IOS-1010".to_owned(),
+				},
+			]]
+		);
+
+		let (_, body) = &server.graphql_requests()[0];
+		let url = format!("{}/api/listing/2021-07-08/161300", server.url);
+		assert!(
+			body.contains(&format!(r#"description: {{ contains: \"{url}\" }}"#)),
+			"{body}"
+		);
+		assert!(
+			body.contains(&format!(r#"body: {{ contains: \"{url}\" }}"#)),
+			"{body}"
+		);
+	}
+
+	#[tokio::test]
+	async fn caches_linked_issues() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		server.set_graphql_response(
+			r#"{"data":{"i0":{"nodes":[{"identifier":"IOS-1000","title":"Crash","state":{"name":"Todo"}}]},"c0":{"nodes":[]},"i1":{"nodes":[]},"c1":{"nodes":[]}}}"#,
+		);
+
+		let entries = [
+			Entry::new("2021-07-08", "161300", env.config.clone()),
+			Entry::new("2021-07-09", "010203", env.config.clone()),
+		];
+
+		for _ in 0..2 {
+			let issues = linked_issues(&entries, &env.config).await.unwrap();
+
+			assert_eq!(issues[0].len(), 1);
+			assert_eq!(issues[0][0].identifier, "IOS-1000");
+			assert!(issues[1].is_empty());
+		}
+
+		// the second time around, both should've come straight from the cache
+		assert_eq!(server.graphql_requests().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn rechecks_stale_linked_issues() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		// linked to an issue a long time ago, which has since been unlinked
+		let mut cache = serde_json::Map::new();
+		cache.insert(
+			"2021-07-08/161300".to_owned(),
+			serde_json::json!({
+				"checked": 0,
+				"issues": [{ "identifier": "IOS-1000", "title": "Crash", "state": { "name": "Todo" } }],
+			}),
+		);
+		write_refs_cache(&cache).unwrap();
+
+		server.set_graphql_response(r#"{"data":{"i0":{"nodes":[]},"c0":{"nodes":[]}}}"#);
+
+		let entries = [Entry::new("2021-07-08", "161300", env.config.clone())];
+		let issues = linked_issues(&entries, &env.config).await.unwrap();
+
+		assert!(issues[0].is_empty());
+		assert_eq!(server.graphql_requests().len(), 1);
+	}
+
+	// linear answers every request the same way, so this has what's needed both to look the ids
+	// up and to create the issue
+	const CREATE_RESPONSE: &str = r#"{"data":{"teams":{"nodes":[{"id":"team-1"}]},"issueLabels":{"nodes":[{"id":"label-other","name":"crash","team":{"key":"AND"}},{"id":"label-1","name":"crash","team":null}]},"viewer":{"id":"user-1"},"issueCreate":{"success":true,"issue":{"identifier":"IOS-1234","title":"app crashed when opening room","url":"https://linear.app/issue/IOS-1234","state":{"name":"Triage"}}}}}"#;
+
+	#[tokio::test]
+	async fn syncs_entries_from_every_page_of_issues() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		add_crashed_entry(&server);
+		server.add_entry(
+			"2021-07-09/010203",
+			&["already here"],
+			&[("logs.log", "hi\n")],
+		);
+		env.add_local_file("2021-07-09/010203/logs.log", "hi\n");
+
+		server.queue_graphql_response(&format!(
+			r#"{{"data":{{"issues":{{"pageInfo":{{"hasNextPage":true,"endCursor":"cursor-1"}},"nodes":[{{"identifier":"IOS-1","title":"Crash when opening room","description":"{url}/api/listing/2021-07-08/161300","comments":{{"nodes":[]}},"attachments":{{"nodes":[]}}}}]}}}}}}"#,
+			url = server.url
+		));
+		server.queue_graphql_response(&format!(
+			r#"{{"data":{{"issues":{{"pageInfo":{{"hasNextPage":false,"endCursor":"cursor-2"}},"nodes":[{{"identifier":"IOS-2","title":"Same crash","description":null,"comments":{{"nodes":[{{"body":"{url}/api/listing/2021-07-08/161300 and {url}/api/listing/2021-07-09/010203"}}]}},"attachments":{{"nodes":[]}}}}]}}}}}}"#,
+			url = server.url
+		));
+
+		let options = SyncOptions {
+			cycle: Some("current".to_owned()),
+			team: Some("IOS".to_owned()),
+			..SyncOptions::default()
+		};
+		sync(&options, env.config.clone()).await.unwrap();
+
+		let requests = server.graphql_requests();
+		assert_eq!(requests.len(), 2);
+
+		let first = serde_json::from_str::<serde_json::Value>(&requests[0].1).unwrap();
+		assert_eq!(
+			first["variables"]["filter"],
+			serde_json::json!({ "cycle": { "isActive": { "eq": true } }, "team": { "key": { "eq": "IOS" } } })
+		);
+		assert!(first["variables"]["after"].is_null());
+
+		let second = serde_json::from_str::<serde_json::Value>(&requests[1].1).unwrap();
+		assert_eq!(second["variables"]["after"], "cursor-1");
+
+		// the entry linked from both issues is downloaded once, and the one that was already
+		// downloaded isn't listed again
+		assert!(env.store_path("2021-07-08/161300/logs.log").exists());
+		assert!(env.store_path("2021-07-08/161300/other.log").exists());
+		assert!(!server
+			.requests()
+			.iter()
+			.any(|r| r.contains("2021-07-09/010203")));
+	}
+
+	#[tokio::test]
+	async fn sync_needs_a_filter() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		let options = SyncOptions {
+			team: Some("IOS".to_owned()),
+			..SyncOptions::default()
+		};
+		assert!(sync(&options, env.config.clone()).await.is_err());
+		assert!(server.graphql_requests().is_empty());
+
+		let options = SyncOptions {
+			cycle: Some("last".to_owned()),
+			..SyncOptions::default()
+		};
+		assert!(sync(&options, env.config.clone()).await.is_err());
+	}
+
+	fn add_crashed_entry(server: &MockServer) {
+		server.add_entry(
+			"2021-07-08/161300",
+			&[
+				"app crashed when opening room",
+				"Application: element-ios",
+				"user_id: @bob:example.org",
+				"Version: 1.8.2",
+			],
+			&[
+				(
+					"logs.log",
+					"opening room\nPANIC: index out of range\nrestarting\n",
+				),
+				("other.log", "nothing to see here\n"),
+			],
+		);
+	}
+
+	#[tokio::test]
+	async fn creates_issue_for_entry() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		add_crashed_entry(&server);
+		server.set_graphql_response(CREATE_RESPONSE);
+
+		// another issue that's already known to link to it
+		let mut cache = serde_json::Map::new();
+		cache_refs(
+			&mut cache,
+			"2021-07-08/161300",
+			&[IssueRef {
+				identifier: "IOS-1000".to_owned(),
+				title: "Crash".to_owned(),
+				state: "Todo".to_owned(),
+				url: "https://linear.app/issue/IOS-1000".to_owned(),
+			}],
+		);
+		write_refs_cache(&cache).unwrap();
+
+		let options = CreateOptions {
+			target: "linear:IOS".to_owned(),
+			labels: vec!["crash".to_owned()],
+			assignee: Some("me".to_owned()),
+			project: None,
+			term: Some("PANIC".to_owned()),
+			dry_run: false,
+		};
+
+		create("2021-07-08/161300", options, &env.config)
+			.await
+			.unwrap();
+
+		let requests = server.graphql_requests();
+		assert_eq!(requests.len(), 2);
+
+		let lookup = serde_json::from_str::<serde_json::Value>(&requests[0].1).unwrap();
+		let lookup = lookup["query"].as_str().unwrap();
+		assert!(lookup.contains(r#"key: { eq: "IOS" }"#), "{lookup}");
+		assert!(lookup.contains(r#"name: { in: ["crash"] }"#), "{lookup}");
+		assert!(lookup.contains("viewer { id }"), "{lookup}");
+
+		let mutation = serde_json::from_str::<serde_json::Value>(&requests[1].1).unwrap();
+		assert_eq!(mutation["query"], ISSUE_CREATE);
+
+		let input = &mutation["variables"]["input"];
+		assert_eq!(input["teamId"], "team-1");
+		assert_eq!(input["labelIds"], serde_json::json!(["label-1"]));
+		assert_eq!(input["assigneeId"], "user-1");
+		assert_eq!(input["title"], "app crashed when opening room");
+		assert_eq!(
+			input["description"],
+			format!(
+				"app crashed when opening room\n\n\
+				- **User:** @bob:example.org\n\
+				- **OS:** iOS\n\
+				- **Version:** 1.8.2\n\
+				- **Rageshake:** {}/api/listing/2021-07-08/161300\n\
+				\n### Lines matching `PANIC`\n\
+				\n**logs.log**\n```\nPANIC: index out of range\n```\n",
+				server.url
+			)
+		);
+
+		// and the new issue should show up as linking to the entry without asking linear
+		let entry = Entry::new("2021-07-08", "161300", env.config.clone());
+		let issues = linked_issues(&[entry], &env.config).await.unwrap();
+		assert_eq!(
+			issues[0]
+				.iter()
+				.map(|i| i.identifier.as_str())
+				.collect::<Vec<_>>(),
+			["IOS-1000", "IOS-1234"]
+		);
+		assert_eq!(server.graphql_requests().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn does_not_cache_new_issues_alone() {
+		let server = MockServer::start().await;
+		let _env = TestEnv::new(&server, "");
+
+		remember(
+			"2021-07-08/161300",
+			IssueRef {
+				identifier: "IOS-1234".to_owned(),
+				title: "app crashed".to_owned(),
+				state: "Triage".to_owned(),
+				url: "https://linear.app/issue/IOS-1234".to_owned(),
+			},
+		);
+
+		// so that the next lookup asks linear about every issue linking to it
+		assert!(!read_refs_cache().contains_key("2021-07-08/161300"));
+	}
+
+	#[tokio::test]
+	async fn dry_run_creates_nothing() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		add_crashed_entry(&server);
+		server.set_graphql_response(CREATE_RESPONSE);
+
+		let options = CreateOptions {
+			target: "linear:IOS".to_owned(),
+			labels: Vec::new(),
+			assignee: None,
+			project: None,
+			term: None,
+			dry_run: true,
+		};
+
+		create("2021-07-08/161300", options, &env.config)
+			.await
+			.unwrap();
+
+		// only the ids were looked up, and the logs weren't downloaded since they weren't needed
+		assert_eq!(server.graphql_requests().len(), 1);
+		assert!(!env.store_path("2021-07-08/161300/logs.log").exists());
+	}
+}
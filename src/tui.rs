@@ -0,0 +1,966 @@
+use crate::{
+	config::Config,
+	entry::{Entry, EntryOS},
+	errors::FilterErrors,
+	filter::Filter,
+	highlight::Rule,
+	search, sync_dir, timeline, view,
+};
+use crossterm::{
+	cursor,
+	event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+	execute,
+	terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+	backend::{Backend, CrosstermBackend},
+	layout::{Constraint, Direction, Layout, Rect},
+	style::{Color, Modifier, Style},
+	text::{Span, Spans, Text},
+	widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+	Frame, Terminal,
+};
+use std::{
+	fs,
+	io::{self, BufReader, Stdout},
+	sync::Arc,
+};
+
+// what the basic and bright SGR colors (30-37 and 90-97) are called in ratatui
+const COLORS: [Color; 16] = [
+	Color::Black,
+	Color::Red,
+	Color::Green,
+	Color::Yellow,
+	Color::Blue,
+	Color::Magenta,
+	Color::Cyan,
+	Color::Gray,
+	Color::DarkGray,
+	Color::LightRed,
+	Color::LightGreen,
+	Color::LightYellow,
+	Color::LightBlue,
+	Color::LightMagenta,
+	Color::LightCyan,
+	Color::White,
+];
+
+const HELP: &str = "q quit · tab switch pane · enter open · / filter · s sort · d download";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+	Entries,
+	Files,
+	Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sort {
+	Newest,
+	Oldest,
+	User,
+	Os,
+}
+
+impl Sort {
+	fn next(self) -> Sort {
+		match self {
+			Sort::Newest => Sort::Oldest,
+			Sort::Oldest => Sort::User,
+			Sort::User => Sort::Os,
+			Sort::Os => Sort::Newest,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Sort::Newest => "newest first",
+			Sort::Oldest => "oldest first",
+			Sort::User => "by user",
+			Sort::Os => "by OS",
+		}
+	}
+}
+
+// what the event loop should do after a key is handled
+enum Action {
+	Continue,
+	Download,
+	Quit,
+}
+
+// the file that's open in the log pane
+struct Log {
+	name: String,
+	lines: Vec<String>,
+}
+
+struct App {
+	config: Arc<Config>,
+	// every entry on the device
+	entries: Vec<Entry>,
+	// the indices of the entries that pass the filter, in the order they're shown
+	shown: Vec<usize>,
+	entry_list: ListState,
+	// which entry the files in the file list are from, so the selection is only reset when it
+	// changes
+	files_of: Option<usize>,
+	file_list: ListState,
+	log: Option<Log>,
+	// the first line of the log that's shown, and how many fit, for scrolling by pages
+	scroll: usize,
+	log_height: usize,
+	focus: Focus,
+	sort: Sort,
+	// the filter as it's typed, e.g. `user:@bob os:android when:yesterday term:M_FORBIDDEN`
+	filter: String,
+	// the filter from before they started typing, for going back to with escape
+	old_filter: Option<String>,
+	// the term from the last filter that was applied, to highlight in the log
+	term: Option<Rule>,
+	// what just happened, or what went wrong
+	status: String,
+}
+
+// `rager tui`, with the filter bar starting out as `filter`
+pub async fn tui(filter: String, config: Config) -> Result<(), String> {
+	let config = Arc::new(config);
+
+	let entries = search::entries_with_filter(&Arc::new(Filter::default()), &config)
+		.await
+		.ok_or_else(|| "Couldn't read the logs on your device".to_owned())?;
+
+	let mut app = App::new(config, entries, filter);
+	app.apply_filter(true).await;
+
+	let mut terminal = enter().map_err(|err| format!("Couldn't start the interface: {err}"))?;
+	let res = run(&mut app, &mut terminal).await;
+	let left = leave().map_err(|err| format!("Couldn't restore the terminal: {err}"));
+
+	res.map_err(|err| format!("The interface failed: {err}"))
+		.and(left)
+}
+
+async fn run(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+	loop {
+		terminal.draw(|f| app.draw(f))?;
+
+		let Event::Key(key) = event::read()? else {
+			continue;
+		};
+
+		if key.kind == KeyEventKind::Release {
+			continue;
+		}
+
+		match app.handle(key).await {
+			Action::Continue => (),
+			Action::Quit => return Ok(()),
+			Action::Download => {
+				// downloading prints its progress, so we get out of the way while it does
+				leave()?;
+				app.download().await;
+				*terminal = enter()?;
+			}
+		}
+	}
+}
+
+fn enter() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+	terminal::enable_raw_mode()?;
+	execute!(io::stdout(), EnterAlternateScreen)?;
+
+	let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+	terminal.clear()?;
+	Ok(terminal)
+}
+
+fn leave() -> io::Result<()> {
+	terminal::disable_raw_mode()?;
+	execute!(io::stdout(), LeaveAlternateScreen, cursor::Show)
+}
+
+// the filter bar's text for one of the `search` arguments, quoted if it needs to be
+pub fn filter_token(key: &str, val: &str) -> String {
+	if val.contains(char::is_whitespace) {
+		format!("{key}:\"{val}\"")
+	} else {
+		format!("{key}:{val}")
+	}
+}
+
+// splits the filter on spaces, except for the ones in double quotes
+fn tokens(text: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut current = String::new();
+	let mut quoted = false;
+
+	for c in text.chars() {
+		match c {
+			'"' => quoted = !quoted,
+			c if c.is_whitespace() && !quoted => {
+				if !current.is_empty() {
+					tokens.push(std::mem::take(&mut current));
+				}
+			}
+			c => current.push(c),
+		}
+	}
+
+	if !current.is_empty() {
+		tokens.push(current);
+	}
+
+	tokens
+}
+
+// parses the filter bar into the same filter that `rager search` would use. Each part is either
+// `key:value` (with the same keys as the long names of `search`'s arguments), `any`,
+// `reject-unsure`, or otherwise part of the term. Keys without a value yet are ignored, since
+// they're probably still being typed
+fn parse_filter(text: &str) -> Result<Filter, String> {
+	let mut filter = Filter::default();
+	let mut words = Vec::new();
+
+	for token in tokens(text) {
+		let Some((key, val)) = token.split_once(':') else {
+			match token.as_str() {
+				"any" => filter.any = true,
+				"reject-unsure" => filter.reject_unsure = true,
+				_ => words.push(token),
+			}
+			continue;
+		};
+
+		if val.is_empty() {
+			continue;
+		}
+
+		let date = |val: &str| {
+			Filter::string_to_single_date(val).ok_or_else(|| {
+				format!("'{val}' is not a day like '2021-07-09', 'yesterday', or 'friday'")
+			})
+		};
+
+		match key {
+			"user" => filter.user = Some(val.to_owned()),
+			"term" => filter.term = Some(val.to_owned()),
+			"os" => {
+				filter.oses = Some(
+					val.split(',')
+						.map(EntryOS::try_from)
+						.collect::<Result<_, _>>()?,
+				);
+			}
+			"when" => {
+				filter.when = Some(val.split(',').map(date).collect::<Result<_, _>>()?);
+			}
+			"before" => filter.before = Some(date(val)?),
+			"after" => filter.after = Some(date(val)?),
+			_ => {
+				return Err(format!(
+					"Unknown filter '{key}'; use 'user', 'os', 'when', 'before', 'after', or 'term'"
+				))
+			}
+		}
+	}
+
+	if !words.is_empty() && filter.term.is_none() {
+		filter.term = Some(words.join(" "));
+	}
+
+	Ok(filter)
+}
+
+// turns a line colored with SGR codes (like the ones from `view::color_line`) into styled spans
+fn ansi_spans(line: &str) -> Spans<'static> {
+	let line = line.trim_end_matches(['\n', '\r']).replace('\t', "    ");
+	let mut spans = Vec::new();
+	let mut style = Style::default();
+	let mut rest = line.as_str();
+
+	while let Some(start) = rest.find("\x1b[") {
+		if start > 0 {
+			spans.push(Span::styled(rest[..start].to_owned(), style));
+		}
+
+		let after = &rest[start + 2..];
+		let Some(end) = after.find(|c: char| !c.is_ascii_digit() && c != ';') else {
+			rest = "";
+			break;
+		};
+
+		if after[end..].starts_with('m') {
+			style = apply_sgr(style, &after[..end]);
+		}
+
+		// skip the sequence's final character, whatever it is
+		let final_len = after[end..].chars().next().map_or(0, char::len_utf8);
+		rest = &after[end + final_len..];
+	}
+
+	if !rest.is_empty() {
+		spans.push(Span::styled(rest.to_owned(), style));
+	}
+
+	Spans(spans)
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+	let mut codes = params.split(';').map(|c| c.parse::<u8>().unwrap_or(0));
+
+	while let Some(code) = codes.next() {
+		style = match code {
+			0 => Style::default(),
+			1 => style.add_modifier(Modifier::BOLD),
+			2 => style.add_modifier(Modifier::DIM),
+			3 => style.add_modifier(Modifier::ITALIC),
+			4 => style.add_modifier(Modifier::UNDERLINED),
+			7 => style.add_modifier(Modifier::REVERSED),
+			22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+			23 => style.remove_modifier(Modifier::ITALIC),
+			24 => style.remove_modifier(Modifier::UNDERLINED),
+			27 => style.remove_modifier(Modifier::REVERSED),
+			30..=37 => style.fg(COLORS[usize::from(code - 30)]),
+			90..=97 => style.fg(COLORS[usize::from(code - 90 + 8)]),
+			40..=47 => style.bg(COLORS[usize::from(code - 40)]),
+			100..=107 => style.bg(COLORS[usize::from(code - 100 + 8)]),
+			39 => style.fg(Color::Reset),
+			49 => style.bg(Color::Reset),
+			38 | 48 => {
+				let color = match codes.next() {
+					Some(5) => codes.next().map(Color::Indexed),
+					Some(2) => match (codes.next(), codes.next(), codes.next()) {
+						(Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+						_ => None,
+					},
+					_ => None,
+				};
+
+				match color {
+					Some(color) if code == 38 => style.fg(color),
+					Some(color) => style.bg(color),
+					None => style,
+				}
+			}
+			_ => style,
+		};
+	}
+
+	style
+}
+
+// moves the selection of a list with `len` items by `by`, stopping at either end
+fn step(list: &mut ListState, len: usize, by: isize) {
+	if len == 0 {
+		list.select(None);
+		return;
+	}
+
+	let current = list.selected().unwrap_or(0) as isize;
+	list.select(Some((current + by).clamp(0, len as isize - 1) as usize));
+}
+
+impl App {
+	fn new(config: Arc<Config>, entries: Vec<Entry>, filter: String) -> App {
+		App {
+			config,
+			entries,
+			shown: Vec::new(),
+			entry_list: ListState::default(),
+			files_of: None,
+			file_list: ListState::default(),
+			log: None,
+			scroll: 0,
+			log_height: 0,
+			focus: Focus::Entries,
+			sort: Sort::Newest,
+			filter,
+			old_filter: None,
+			term: None,
+			status: String::new(),
+		}
+	}
+
+	// the index in `entries` of the selected entry
+	fn selected(&self) -> Option<usize> {
+		self.shown.get(self.entry_list.selected()?).copied()
+	}
+
+	fn files(&self) -> &[String] {
+		self.selected()
+			.and_then(|idx| self.entries[idx].files.as_deref())
+			.unwrap_or_default()
+	}
+
+	// filters the entries with what's in the filter bar. Looking for the term means reading
+	// through every log, so that's only done once they're done typing
+	async fn apply_filter(&mut self, with_term: bool) {
+		let mut filter = match parse_filter(&self.filter) {
+			Ok(filter) => filter,
+			Err(err) => {
+				self.status = err;
+				return;
+			}
+		};
+
+		if with_term {
+			self.term = match filter
+				.term
+				.as_deref()
+				.map(|term| Rule::new("term", term, "reverse bold", i64::MAX))
+				.transpose()
+			{
+				Ok(term) => term,
+				Err(_) => {
+					self.status = "The term is not a valid regex".to_owned();
+					return;
+				}
+			};
+		} else {
+			filter.term = None;
+		}
+
+		let selected = self.selected();
+		let mut shown = Vec::new();
+
+		for idx in 0..self.entries.len() {
+			match filter.entry_ok(&mut self.entries[idx], false).await {
+				Ok(true) => shown.push(idx),
+				// entries that only have their details can't contain the term
+				Ok(false) | Err(FilterErrors::TermFilterBeforeDownloading) => (),
+				Err(err) => {
+					self.status =
+						format!("Couldn't check {}: {err}", self.entries[idx].date_time());
+					return;
+				}
+			}
+		}
+
+		self.shown = shown;
+		self.status = format!("{} of {} entries", self.shown.len(), self.entries.len());
+		self.sort_entries(selected);
+		self.load_files().await;
+	}
+
+	// sorts the entries that are shown, keeping the same one selected if it's still there
+	fn sort_entries(&mut self, selected: Option<usize>) {
+		let entries = &self.entries;
+		let newest = |a: &usize, b: &usize| {
+			(&entries[*b].day, &entries[*b].time).cmp(&(&entries[*a].day, &entries[*a].time))
+		};
+
+		match self.sort {
+			Sort::Newest => self.shown.sort_by(newest),
+			Sort::Oldest => self.shown.sort_by(|a, b| newest(b, a)),
+			Sort::User => self.shown.sort_by(|a, b| {
+				entries[*a]
+					.user_id
+					.cmp(&entries[*b].user_id)
+					.then_with(|| newest(a, b))
+			}),
+			Sort::Os => self.shown.sort_by(|a, b| {
+				let os = |idx: &usize| entries[*idx].os.as_ref().map(ToString::to_string);
+				os(a).cmp(&os(b)).then_with(|| newest(a, b))
+			}),
+		}
+
+		let position = selected.and_then(|s| self.shown.iter().position(|idx| *idx == s));
+		self.entry_list
+			.select(position.or((!self.shown.is_empty()).then_some(0)));
+	}
+
+	// lists the files of the selected entry that are on the device
+	async fn load_files(&mut self) {
+		let selected = self.selected();
+
+		if let Some(idx) = selected {
+			let _ = self.entries[idx].retrieve_file_list(false).await;
+		}
+
+		if selected != self.files_of {
+			self.files_of = selected;
+			let len = self.files().len();
+			self.file_list.select((len > 0).then_some(0));
+		}
+	}
+
+	fn open_file(&mut self) {
+		let (Some(idx), Some(file)) = (
+			self.selected(),
+			self.file_list
+				.selected()
+				.and_then(|f| self.files().get(f).cloned()),
+		) else {
+			return;
+		};
+
+		if !timeline::is_text(&file) {
+			self.status = format!("{file} isn't a log, so it can't be shown here");
+			return;
+		}
+
+		let name = format!("{}/{file}", self.entries[idx].date_time());
+
+		match fs::File::open(sync_dir().join(&name)) {
+			Ok(f) => {
+				self.log = Some(Log {
+					name,
+					lines: view::read_lines(BufReader::new(f)).collect(),
+				});
+				self.scroll = 0;
+				self.focus = Focus::Log;
+			}
+			Err(err) => self.status = format!("Couldn't open {name}: {err}"),
+		}
+	}
+
+	async fn download(&mut self) {
+		let Some(idx) = self.selected() else {
+			return;
+		};
+
+		let entry = &mut self.entries[idx];
+		self.status = match entry.ensure_all_files_downloaded().await {
+			Ok(()) => format!("Downloaded {}", entry.date_time()),
+			Err(err) => format!("Couldn't download {}: {err}", entry.date_time()),
+		};
+
+		self.load_files().await;
+	}
+
+	// scrolls the log so the next (or previous) line that matches the term is at the top
+	fn jump_to_match(&mut self, forward: bool) {
+		let (Some(log), Some(term)) = (&self.log, &self.term) else {
+			return;
+		};
+
+		let is_match = |idx: &usize| term.regex.is_match(&log.lines[*idx]);
+		let found = if forward {
+			(self.scroll + 1..log.lines.len()).find(is_match)
+		} else {
+			(0..self.scroll).rev().find(is_match)
+		};
+
+		match found {
+			Some(line) => self.scroll = line,
+			None => self.status = "No more matches".to_owned(),
+		}
+	}
+
+	fn scroll_by(&mut self, by: isize) {
+		let len = self.log.as_ref().map_or(0, |l| l.lines.len());
+		let max = len.saturating_sub(self.log_height.max(1)) as isize;
+		self.scroll = (self.scroll as isize + by).clamp(0, max.max(0)) as usize;
+	}
+
+	async fn handle(&mut self, key: KeyEvent) -> Action {
+		if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+			return Action::Quit;
+		}
+
+		if let Some(old) = &self.old_filter {
+			match key.code {
+				KeyCode::Enter => {
+					self.old_filter = None;
+					self.apply_filter(true).await;
+				}
+				KeyCode::Esc => {
+					self.filter = old.clone();
+					self.old_filter = None;
+					self.apply_filter(true).await;
+				}
+				KeyCode::Backspace => {
+					self.filter.pop();
+					self.apply_filter(false).await;
+				}
+				KeyCode::Char(c) => {
+					self.filter.push(c);
+					self.apply_filter(false).await;
+				}
+				_ => (),
+			}
+
+			return Action::Continue;
+		}
+
+		let page = self.log_height.max(1) as isize;
+		let by = match key.code {
+			KeyCode::Up | KeyCode::Char('k') => -1,
+			KeyCode::Down | KeyCode::Char('j') => 1,
+			KeyCode::PageUp => -page,
+			KeyCode::PageDown | KeyCode::Char(' ') => page,
+			KeyCode::Home | KeyCode::Char('g') => isize::MIN / 2,
+			KeyCode::End | KeyCode::Char('G') => isize::MAX / 2,
+			_ => 0,
+		};
+
+		if by != 0 {
+			match self.focus {
+				Focus::Entries => {
+					step(&mut self.entry_list, self.shown.len(), by);
+					self.load_files().await;
+				}
+				Focus::Files => {
+					let len = self.files().len();
+					step(&mut self.file_list, len, by);
+				}
+				Focus::Log => self.scroll_by(by),
+			}
+
+			return Action::Continue;
+		}
+
+		match key.code {
+			KeyCode::Char('q') => return Action::Quit,
+			KeyCode::Char('d') => return Action::Download,
+			KeyCode::Char('/') => self.old_filter = Some(self.filter.clone()),
+			KeyCode::Char('s') => {
+				self.sort = self.sort.next();
+				self.sort_entries(self.selected());
+				self.load_files().await;
+				self.status = format!("Sorted {}", self.sort.name());
+			}
+			KeyCode::Char('n') => self.jump_to_match(true),
+			KeyCode::Char('p') => self.jump_to_match(false),
+			KeyCode::Tab => {
+				self.focus = match self.focus {
+					Focus::Entries => Focus::Files,
+					Focus::Files if self.log.is_some() => Focus::Log,
+					_ => Focus::Entries,
+				}
+			}
+			KeyCode::BackTab | KeyCode::Esc => {
+				self.focus = match self.focus {
+					Focus::Log => Focus::Files,
+					_ => Focus::Entries,
+				}
+			}
+			KeyCode::Enter => match self.focus {
+				Focus::Entries => self.focus = Focus::Files,
+				Focus::Files => self.open_file(),
+				Focus::Log => (),
+			},
+			_ => (),
+		}
+
+		Action::Continue
+	}
+
+	fn block(&self, title: String, focus: Focus) -> Block<'static> {
+		let style = if self.focus == focus {
+			Style::default().fg(Color::Yellow)
+		} else {
+			Style::default()
+		};
+
+		Block::default()
+			.borders(Borders::ALL)
+			.border_style(style)
+			.title(Span::styled(title, style.add_modifier(Modifier::BOLD)))
+	}
+
+	fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+		let rows = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints([
+				Constraint::Percentage(40),
+				Constraint::Min(3),
+				Constraint::Length(1),
+			])
+			.split(f.size());
+		let top = Layout::default()
+			.direction(Direction::Horizontal)
+			.constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+			.split(rows[0]);
+		let side = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+			.split(top[1]);
+
+		self.draw_entries(f, top[0]);
+		self.draw_details(f, side[0]);
+		self.draw_files(f, side[1]);
+		self.draw_log(f, rows[1]);
+
+		let footer = match &self.old_filter {
+			Some(_) => Spans::from(vec![
+				Span::styled("/ ", Style::default().fg(Color::Yellow)),
+				Span::raw(format!("{}█", self.filter)),
+			]),
+			None => Spans::from(vec![
+				Span::styled(
+					format!("{} ", self.status),
+					Style::default().add_modifier(Modifier::BOLD),
+				),
+				Span::styled(HELP, Style::default().add_modifier(Modifier::DIM)),
+			]),
+		};
+		f.render_widget(Paragraph::new(footer), rows[2]);
+	}
+
+	fn draw_entries<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+		let items = self
+			.shown
+			.iter()
+			.map(|idx| {
+				let entry = &self.entries[*idx];
+				// the ones that only have their details still need to be downloaded
+				let marker = if entry.has_logs() {
+					"●"
+				} else {
+					"○"
+				};
+
+				ListItem::new(format!("{marker} {}", entry.selectable_description()))
+			})
+			.collect::<Vec<_>>();
+
+		let filter = if self.filter.is_empty() {
+			String::new()
+		} else {
+			format!(", {}", self.filter)
+		};
+		let title = format!(
+			"Entries ({}/{}, {}{filter})",
+			self.shown.len(),
+			self.entries.len(),
+			self.sort.name()
+		);
+
+		let list = List::new(items)
+			.block(self.block(title, Focus::Entries))
+			.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+		f.render_stateful_widget(list, area, &mut self.entry_list);
+	}
+
+	fn draw_details<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+		let text = match self.selected().map(|idx| &self.entries[idx]) {
+			Some(entry) => {
+				// this goes first so that it isn't cut off when the pane is short
+				let download = (!entry.has_logs()).then(|| {
+					Spans::from(Span::styled(
+						"Logs not downloaded; press d",
+						Style::default().fg(Color::Yellow),
+					))
+				});
+
+				let lines = download
+					.into_iter()
+					.chain(entry.description().lines().map(ansi_spans))
+					.collect::<Vec<_>>();
+
+				Text::from(lines)
+			}
+			None => Text::raw("No entries match the filter"),
+		};
+
+		let block = Block::default().borders(Borders::ALL).title("Details");
+		f.render_widget(Paragraph::new(text).block(block), area);
+	}
+
+	fn draw_files<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+		let items = self
+			.files()
+			.iter()
+			.map(|file| ListItem::new(file.clone()))
+			.collect::<Vec<_>>();
+
+		let list = List::new(items)
+			.block(self.block("Files".to_owned(), Focus::Files))
+			.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+		f.render_stateful_widget(list, area, &mut self.file_list);
+	}
+
+	fn draw_log<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+		self.log_height = area.height.saturating_sub(2) as usize;
+
+		let Some(log) = &self.log else {
+			let block = self.block("Log".to_owned(), Focus::Log);
+			let hint = Paragraph::new("Pick a file and press enter to show it here").block(block);
+			f.render_widget(hint, area);
+			return;
+		};
+
+		let end = (self.scroll + self.log_height).min(log.lines.len());
+		let lines = log.lines[self.scroll.min(end)..end]
+			.iter()
+			.map(|line| {
+				ansi_spans(&view::color_line(
+					&self.config.highlight,
+					line,
+					self.term.as_ref(),
+				))
+			})
+			.collect::<Vec<_>>();
+
+		let title = format!(
+			"{} (lines {}-{end} of {})",
+			log.name,
+			(self.scroll + 1).min(end),
+			log.lines.len()
+		);
+
+		f.render_widget(
+			Paragraph::new(lines).block(self.block(title, Focus::Log)),
+			area,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+	use ratatui::backend::TestBackend;
+
+	#[test]
+	fn parses_filter_bar() {
+		let filter =
+			parse_filter("user:@bob os:android,ios term:\"sync failed\" any after:").unwrap();
+
+		assert_eq!(filter.user.as_deref(), Some("@bob"));
+		assert_eq!(filter.oses, Some(vec![EntryOS::Android, EntryOS::iOS]));
+		assert_eq!(filter.term.as_deref(), Some("sync failed"));
+		assert!(filter.any);
+		assert!(filter.after.is_none());
+
+		let filter = parse_filter("before:2021-07-09 M_FORBIDDEN boom").unwrap();
+		assert_eq!(filter.before, Some([2021, 7, 9]));
+		assert_eq!(filter.term.as_deref(), Some("M_FORBIDDEN boom"));
+
+		assert!(parse_filter("os:beos").is_err());
+		assert!(parse_filter("when:someday").is_err());
+		assert!(parse_filter("colour:red").is_err());
+
+		assert_eq!(filter_token("term", "sync failed"), "term:\"sync failed\"");
+	}
+
+	#[test]
+	fn converts_sgr_to_styles() {
+		let spans = ansi_spans("\x1b[31mE/x: \x1b[34;1m12\x1b[0m\x1b[31m failed\x1b[0m\tdone\n");
+
+		assert_eq!(
+			spans.0,
+			[
+				Span::styled("E/x: ", Style::default().fg(Color::Red)),
+				Span::styled(
+					"12",
+					Style::default()
+						.fg(Color::Blue)
+						.add_modifier(Modifier::BOLD)
+				),
+				Span::styled(" failed", Style::default().fg(Color::Red)),
+				Span::raw("    done"),
+			]
+		);
+
+		let spans = ansi_spans("\x1b[38;5;208;48;2;1;2;3mx\x1b[Ky");
+		assert_eq!(
+			spans.0,
+			[
+				Span::styled(
+					"x",
+					Style::default()
+						.fg(Color::Indexed(208))
+						.bg(Color::Rgb(1, 2, 3))
+				),
+				Span::styled(
+					"y",
+					Style::default()
+						.fg(Color::Indexed(208))
+						.bg(Color::Rgb(1, 2, 3))
+				),
+			]
+		);
+	}
+
+	fn key(code: KeyCode) -> KeyEvent {
+		KeyEvent::new(code, KeyModifiers::NONE)
+	}
+
+	// everything on the screen, one string per row
+	fn screen(app: &mut App) -> Vec<String> {
+		let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+		terminal.draw(|f| app.draw(f)).unwrap();
+
+		let buffer = terminal.backend().buffer();
+		(0..buffer.area.height)
+			.map(|y| {
+				(0..buffer.area.width)
+					.map(|x| buffer.get(x, y).symbol.as_str())
+					.collect()
+			})
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn browses_entries_and_logs() {
+		let server = MockServer::start().await;
+		let env = TestEnv::new(&server, "");
+
+		env.add_local_file(
+			"2021-07-08/161300/details.log.gz",
+			"crashed\nApplication: riot-android\nuser_id: @bob:example.org\n",
+		);
+		env.add_local_file(
+			"2021-07-08/161300/logs.log",
+			"I/x: started\nE/x: M_FORBIDDEN boom\n",
+		);
+		env.add_local_file(
+			"2021-07-09/120000/details.log.gz",
+			"slow\nApplication: element-ios\nuser_id: @alice:example.org\n",
+		);
+
+		let entries = search::entries_with_filter(&Arc::new(Filter::default()), &env.config)
+			.await
+			.unwrap();
+		let mut app = App::new(env.config.clone(), entries, String::new());
+		app.apply_filter(true).await;
+
+		// newest first, and the one with only its details is marked
+		let rows = screen(&mut app);
+		assert!(rows[1].contains("○ @alice:example.org (iOS, on 2021-07-09"));
+		assert!(rows[2].contains("● @bob:example.org (Android, on 2021-07-08"));
+		assert!(rows
+			.iter()
+			.any(|r| r.contains("Logs not downloaded; press d")));
+
+		// typing the filter applies it as it goes
+		app.handle(key(KeyCode::Char('/'))).await;
+		for c in "os:android".chars() {
+			app.handle(key(KeyCode::Char(c))).await;
+		}
+		assert_eq!(app.shown.len(), 1);
+		app.handle(key(KeyCode::Enter)).await;
+
+		// into the files, then open the log
+		app.handle(key(KeyCode::Enter)).await;
+		app.handle(key(KeyCode::Down)).await;
+		app.handle(key(KeyCode::Enter)).await;
+		assert_eq!(app.focus, Focus::Log);
+
+		let rows = screen(&mut app);
+		assert!(rows
+			.iter()
+			.any(|r| r.contains("2021-07-08/161300/logs.log (lines 1-2 of 2)")));
+		assert!(rows.iter().any(|r| r.contains("E/x: M_FORBIDDEN boom")));
+
+		// a term that isn't in any log leaves nothing
+		app.handle(key(KeyCode::Char('/'))).await;
+		for c in " nope".chars() {
+			app.handle(key(KeyCode::Char(c))).await;
+		}
+		app.handle(key(KeyCode::Enter)).await;
+		assert!(app.shown.is_empty());
+
+		// and escaping out of an edit goes back to the filter from before it
+		app.handle(key(KeyCode::Char('/'))).await;
+		app.handle(key(KeyCode::Backspace)).await;
+		app.handle(key(KeyCode::Esc)).await;
+		assert_eq!(app.filter, "os:android nope");
+	}
+}
@@ -1,51 +1,92 @@
-use crate::{entry::Entry, errors::FilterErrors, sync_dir};
-use lazy_static::lazy_static;
-use regex::Regex;
+use crate::{
+	config::Config,
+	entry::Entry,
+	errors::FilterErrors,
+	external,
+	highlight::{Highlighter, Rule},
+	level::{Level, LevelFilter, SEPARATOR},
+	sync_dir,
+	timeline::{self, Timeline},
+	timestamp::{TimeRange, TimeSlice},
+};
+use minus::{
+	input::{crossterm_event::Event, HashedEventRegister, InputClassifier, InputEvent},
+	PagerState, SearchMode,
+};
 use requestty::{question::Question, OnEsc, PromptModule};
 use std::{
+	collections::hash_map::RandomState,
 	fs,
+	io::{BufRead, BufReader},
 	sync::{
-		atomic::{AtomicUsize, Ordering},
-		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+		Arc, Condvar, Mutex, PoisonError,
 	},
 };
 
-const NUM_REP_STR: &str = "$bfr\x1b[34;1m$num\x1b[0m$aft";
-const NS_REP_STR: &str = "\x1b[32;1m$id\x1b[0m";
-const FN_REP_STR: &str = " \x1b[35;1m$fn\x1b[0m$aft";
-const NULL_REP_STR: &str = "\x1b[31;1m(null)\x1b[0m";
-const HEX_REP_STR: &str = "\x1b[33;1m$hex\x1b[0m";
-const URL_REP_STR: &str = "\x1b[31;3m$url\x1b[0m";
-const ROOM_REP_STR: &str = "\x1b[33;3m$room\x1b[0m";
-const USER_REP_STR: &str = "\x1b[36;1m$user\x1b[0m";
-
-const CHUNK_SIZE: usize = 15;
-
-const TERM_WIDTH: usize = 40;
-const SECTIONS: usize = 4;
-const LOADING_SECTIONS: usize = TERM_WIDTH * SECTIONS;
-const LAST_CHARS: [&str; SECTIONS] = [" ", "▎", "▌", "▊"];
-
-lazy_static! {
-	static ref NULL_REGEX: Regex = Regex::new(r"\(null\)").unwrap();
-	static ref NS_REGEX: Regex = Regex::new(r"(?P<id>\[[a-zA-Z]+\])").unwrap();
-	static ref HEX_REGEX: Regex = Regex::new(r"(?P<hex>0x[a-fA-F0-9]+)").unwrap();
-	static ref NUM_REGEX: Regex =
-		Regex::new(r"(?P<bfr>([^\w]|^))(?P<num>#?\d+((\.|\-|:)\d+)*)(?P<aft>[^\w])").unwrap();
-	static ref FN_REGEX: Regex =
-		Regex::new(r" (?P<fn>[a-z]+[A-Z][a-zA-Z]*)(?P<aft>(:| ))").unwrap();
-	static ref USER_REGEX: Regex = Regex::new(r"(?P<user>@[\w=]+:[^\.]+(\.[a-z]+)+)").unwrap();
-	static ref ROOM_REGEX: Regex = Regex::new(r"(?P<room>![a-zA-Z]+:[a-z]+(\.[a-z]+)+)").unwrap();
-	static ref URL_REGEX: Regex =
-		Regex::new(r"(?P<url>(_matrix|.well-known)(/[\w%\-@:\.!]+)*)").unwrap();
+// how many lines to colorize before handing them to the pager. Small enough that the first
+// screen shows up instantly, big enough that the pager isn't redrawing for every line
+const BATCH_LINES: usize = 500;
+
+// how many screens past the bottom of the pager to keep lines ready for, so that scrolling never
+// has to wait for them to be colorized
+const SCREENS_AHEAD: usize = 5;
+
+// how many of the lines before the first match of the term to show above it when the pager opens
+const LEAD_CONTEXT: usize = 3;
+
+// how the file should be shown once it's picked
+#[derive(Debug, Default, Clone)]
+pub struct ViewOptions {
+	// only show lines of at least this level
+	pub level: Option<Level>,
+	// how many lines to show before and after each line that's shown because of `level`
+	pub context: usize,
+	// show every log in the entry at once, interleaved by when each line was logged
+	pub timeline: bool,
+	// only show the lines logged within this range
+	pub range: TimeRange,
+	// highlight the matches of this regex, and let `n` and `p` jump between them
+	pub term: Option<String>,
+	// open the file with this command instead of the built-in pager
+	pub with: Option<String>,
+}
+
+// a line to show, and which of the files being shown it came from
+pub struct Line {
+	pub source: usize,
+	pub text: String,
+}
+
+impl AsRef<str> for Line {
+	fn as_ref(&self) -> &str {
+		&self.text
+	}
+}
+
+// the lines of `reader`, including their newlines. Logs aren't always valid utf-8, so we can't
+// just use `lines()`
+pub fn read_lines<R: BufRead>(mut reader: R) -> impl Iterator<Item = String> {
+	let mut buf = Vec::new();
+
+	std::iter::from_fn(move || {
+		buf.clear();
+		match reader.read_until(b'\n', &mut buf) {
+			Ok(read) if read > 0 => Some(String::from_utf8_lossy(&buf).into_owned()),
+			_ => None,
+		}
+	})
 }
 
+// shows the file, or if there isn't one, asks which file to show and comes back to ask again once
+// the pager is closed, until the user escapes out of the list
 pub async fn view(
-	mut entry: Entry,
+	entry: &mut Entry,
 	file: Option<String>,
-	matches: Option<Vec<String>>,
+	matches: Option<&Vec<String>>,
+	options: &ViewOptions,
 ) -> Result<(), FilterErrors> {
-	if !entry.is_downloaded() {
+	if !entry.has_logs() {
 		entry
 			.ensure_all_files_downloaded()
 			.await
@@ -69,6 +110,8 @@ pub async fn view(
 			.map_err(|_| FilterErrors::FileRetrievalFailed)?;
 	}
 
+	let entry = &*entry;
+
 	// grab the files, return if there are none
 	let files = match &entry.files {
 		Some(files) if !files.is_empty() => files,
@@ -78,167 +121,590 @@ pub async fn view(
 		}
 	};
 
+	let mut entry_dir = sync_dir();
+	entry_dir.push(entry.date_time());
+
+	// the config's viewer is used unless they asked for the timeline, which only we can show
+	let external = options.with.as_ref().or(entry
+		.config
+		.viewer
+		.as_ref()
+		.filter(|_| !options.timeline));
+
+	let open = |log: &str| {
+		fs::File::open(entry_dir.join(log))
+			.map(BufReader::new)
+			.map_err(|_| FilterErrors::FileReadingFailed)
+	};
+
+	let show = |log: &str| match external {
+		Some(command) => {
+			let highlighter = entry.config.viewer_color.then_some(&entry.config.highlight);
+
+			external::open(command, &entry_dir, &[log.to_owned()], highlighter)
+				.map_err(FilterErrors::ExternalViewerFailed)
+		}
+		None => {
+			let lines = read_lines(open(log)?).map(|text| Line { source: 0, text });
+			page(entry, Box::new(lines), Vec::new(), log, options)
+		}
+	};
+
+	if let Some(command) = external.filter(|c| external::wants_all_files(c)) {
+		let logs = files
+			.iter()
+			.filter(|f| timeline::is_text(f))
+			.cloned()
+			.collect::<Vec<_>>();
+		let highlighter = entry.config.viewer_color.then_some(&entry.config.highlight);
+
+		return external::open(command, &entry_dir, &logs, highlighter)
+			.map_err(FilterErrors::ExternalViewerFailed);
+	}
+
+	if options.timeline {
+		let logs = files
+			.iter()
+			.filter(|f| timeline::includes(f))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		let date = chrono::NaiveDate::parse_from_str(&entry.day, "%Y-%m-%d")
+			.map_err(|_| FilterErrors::FileReadingFailed)?;
+
+		let readers = logs
+			.iter()
+			.map(|log| open(log).map(read_lines))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		return page(
+			entry,
+			Box::new(Timeline::new(readers, date)),
+			timeline::tags(&logs),
+			"timeline",
+			options,
+		);
+	}
+
 	// If the user passed in a file, show that one.
-	// Else prompt them to choose a file to show
-	let to_show = file.or_else(|| {
-		// the list of files, formatted to show a string if they match
-		let string_paths = files.iter().map(|log| {
-			if matches.as_ref().is_some_and(|m| m.contains(log)) {
-				format!("{log} (matches)")
-			} else {
-				log.clone()
-			}
-		});
-
-		// And ask the user what file they'd like to view
-		PromptModule::new(vec![Question::select("")
-			.message("Files:")
-			.choices(string_paths)
-			.on_esc(OnEsc::Terminate)
-			.default(0)
-			.build()])
-		.prompt_all()
-		.ok()
-		.and_then(|ans| ans[""].as_list_item().map(|l| files[l.index].clone()))
+	// Else prompt them to choose a file to show, until they've seen all they want to
+	if let Some(log) = file {
+		return show(&log);
+	}
+
+	let mut last = 0;
+	while let Some(idx) = pick_file(files, matches, last) {
+		last = idx;
+		show(&files[idx])?;
+	}
+
+	Ok(())
+}
+
+// shows the lines in the pager until the user quits it. If there are any `tags`, each line is
+// prefixed with the one for the file it came from, and `title` is what to call them in the prompt
+fn page(
+	entry: &Entry,
+	lines: Box<dyn Iterator<Item = Line> + Send>,
+	tags: Vec<String>,
+	title: &str,
+	options: &ViewOptions,
+) -> Result<(), FilterErrors> {
+	let lines = if options.range.is_empty() {
+		lines
+	} else {
+		let date = chrono::NaiveDate::parse_from_str(&entry.day, "%Y-%m-%d")
+			.map_err(|_| FilterErrors::FileReadingFailed)?;
+		let mut slice = TimeSlice::new(&options.range, date);
+
+		Box::new(lines.filter(move |line| slice.keep(&line.text)))
+	};
+
+	// matches of the term go on top of all the other colors, so they stand out
+	let term = options
+		.term
+		.as_deref()
+		.map(|term| Rule::new("term", term, "reverse bold", i64::MAX))
+		.transpose()
+		.map_err(|_| FilterErrors::BadRegexTerm)?;
+
+	let pager = minus::Pager::new();
+
+	pager
+		.set_line_numbers(minus::LineNumbers::Disabled)
+		.map_err(|_| FilterErrors::ViewPagingFailed)?;
+	// we want to be able to come back from the pager, not just have it exit the process
+	pager
+		.set_exit_strategy(minus::ExitStrategy::PagerQuit)
+		.map_err(|_| FilterErrors::ViewPagingFailed)?;
+
+	// set a nice prompt with all the details that we want them to see
+	let prompt_str = format!(
+		"{}/{} ({}; {})",
+		entry.date_time(),
+		title,
+		entry.user_id.as_deref().unwrap_or("unknown"),
+		entry.reason.as_deref().unwrap_or("unknown")
+	);
+	pager
+		.set_prompt(&prompt_str)
+		.map_err(|_| FilterErrors::ViewPagingFailed)?;
+
+	// the rows of the pager that the matches of the term are on, which the feeder fills in as it
+	// goes
+	let match_rows = Arc::new(Mutex::new(Vec::new()));
+
+	let rows = crossterm::terminal::size().map_or(24, |(_, rows)| rows as usize);
+	let progress = Arc::new(Progress::new(rows));
+
+	let keys = if term.is_some() {
+		match_keys(match_rows.clone())
+	} else {
+		HashedEventRegister::default()
+	};
+	pager
+		.set_input_classifier(Box::new(Scroll {
+			keys,
+			progress: progress.clone(),
+		}))
+		.map_err(|_| FilterErrors::ViewPagingFailed)?;
+
+	// The file is read and colorized on another thread and fed to the pager in batches while
+	// it's already showing, so the first screen shows up right away no matter how big the
+	// file is. The feeder stops a few screens past where the user has scrolled to and waits for
+	// them to scroll further, so the pager only ever holds as much of the file as they've needed.
+	let quit = Arc::new(AtomicBool::new(false));
+	let feeder = {
+		let feeder = Feeder {
+			pager: pager.clone(),
+			config: entry.config.clone(),
+			tags,
+			filter: options
+				.level
+				.map(|level| LevelFilter::new(level, options.context)),
+			term,
+			match_rows: match_rows.clone(),
+			prompt: prompt_str,
+			progress: progress.clone(),
+		};
+		let quit = quit.clone();
+
+		std::thread::spawn(move || feeder.feed(lines, &quit))
+	};
+
+	let res = minus::dynamic_paging(pager);
+
+	// the pager doesn't tell us when it's closed, so we have to tell the feeder to stop (and wake
+	// it up, if it's waiting for the user to scroll)
+	quit.store(true, Ordering::SeqCst);
+	progress.release();
+	let _ = feeder.join();
+
+	res.map_err(|_| FilterErrors::ViewPagingFailed)?;
+
+	Ok(())
+}
+
+// asks the user which file they'd like to view, marking the ones that matched the search, and
+// starting on the one at `default`. Returns the index of the file
+fn pick_file(files: &[String], matches: Option<&Vec<String>>, default: usize) -> Option<usize> {
+	// the list of files, formatted to show a string if they match
+	let string_paths = files.iter().map(|log| {
+		if matches.is_some_and(|m| m.contains(log)) {
+			format!("{log} (matches)")
+		} else {
+			log.clone()
+		}
 	});
 
-	if let Some(log) = to_show {
-		let mut stored_loc = sync_dir();
-		stored_loc.push(entry.date_time());
-		stored_loc.push(&log);
+	PromptModule::new(vec![Question::select("")
+		.message("Files:")
+		.choices(string_paths)
+		.on_esc(OnEsc::Terminate)
+		.default(default)
+		.build()])
+	.prompt_all()
+	.ok()
+	.and_then(|ans| ans[""].as_list_item().map(|l| l.index))
+}
+
+// colors the line with the highlight rules (and `term`, if there is one), and then the whole
+// line by its level
+pub fn color_line(highlighter: &Highlighter, line: &str, term: Option<&Rule>) -> String {
+	let colored = highlighter.colorize_with(line, term);
+
+	match Level::detect(line) {
+		Some(level) => level.colorize(&colored),
+		None => colored,
+	}
+}
+
+// minus' usual keys, except that `n` and `p` jump to the next and previous matches of the term
+// unless the user has started a search of their own with `/` or `?`
+fn match_keys(rows: Arc<Mutex<Vec<usize>>>) -> HashedEventRegister<RandomState> {
+	let mut keys = HashedEventRegister::default();
+
+	let next_rows = rows.clone();
+	keys.add_key_events(&["n"], move |_, ps| match ps.search_mode {
+		SearchMode::Forward => InputEvent::MoveToNextMatch(1),
+		SearchMode::Reverse => InputEvent::MoveToPrevMatch(1),
+		SearchMode::Unknown => next_rows
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.find(|row| **row > ps.upper_mark)
+			.map_or(InputEvent::Ignore, |row| InputEvent::UpdateUpperMark(*row)),
+	});
+
+	keys.add_key_events(&["p"], move |_, ps| match ps.search_mode {
+		SearchMode::Forward => InputEvent::MoveToPrevMatch(1),
+		SearchMode::Reverse => InputEvent::MoveToNextMatch(1),
+		SearchMode::Unknown => rows
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.rev()
+			.find(|row| **row < ps.upper_mark)
+			.map_or(InputEvent::Ignore, |row| InputEvent::UpdateUpperMark(*row)),
+	});
+
+	keys
+}
+
+// how many lines the pager should have, which the feeder waits on once it's pushed that many
+struct Progress {
+	// lines can wrap onto more than one row, so this many lines always fill at least as many
+	// rows as were asked for
+	wanted: Mutex<usize>,
+	scrolled: Condvar,
+}
+
+impl Progress {
+	fn new(rows: usize) -> Progress {
+		Progress {
+			wanted: Mutex::new(rows * (SCREENS_AHEAD + 1)),
+			scrolled: Condvar::new(),
+		}
+	}
+
+	// the user can see down to `bottom` now, on a screen that's `rows` tall
+	fn scrolled_to(&self, bottom: usize, rows: usize) {
+		let mut wanted = self.wanted.lock().unwrap_or_else(PoisonError::into_inner);
+		let more = bottom.saturating_add(rows * SCREENS_AHEAD);
+
+		if more > *wanted {
+			*wanted = more;
+			self.scrolled.notify_one();
+		}
+	}
 
-		println!("Loading in log at {stored_loc:?}...\n");
+	// lets the feeder go for good, since nobody's going to scroll any more
+	fn release(&self) {
+		*self.wanted.lock().unwrap_or_else(PoisonError::into_inner) = usize::MAX;
+		self.scrolled.notify_one();
+	}
 
-		let lines_str =
-			fs::read_to_string(stored_loc).map_err(|_| FilterErrors::FileRetrievalFailed)?;
+	// blocks until the pager wants more than the `pushed` lines that it already has
+	fn wait(&self, pushed: usize) {
+		let mut wanted = self.wanted.lock().unwrap_or_else(PoisonError::into_inner);
+
+		while *wanted <= pushed {
+			wanted = self
+				.scrolled
+				.wait(wanted)
+				.unwrap_or_else(PoisonError::into_inner);
+		}
+	}
+}
+
+// the pager's keys, which also tell the feeder how far down the user has gotten
+struct Scroll {
+	keys: HashedEventRegister<RandomState>,
+	progress: Arc<Progress>,
+}
 
-		let lines = lines_str.lines().collect::<Vec<&str>>();
+impl InputClassifier for Scroll {
+	fn classify_input(&self, ev: Event, ps: &PagerState) -> Option<InputEvent> {
+		let input = self.keys.classify_input(ev, ps);
 
-		let line_len = lines.len();
+		let (top, rows) = match input {
+			Some(InputEvent::UpdateUpperMark(top)) => (top, ps.rows),
+			Some(InputEvent::UpdateTermArea(_, rows)) => (ps.upper_mark, rows),
+			// searching can only find what's already in the pager, so it needs all of it
+			Some(InputEvent::Search(_)) => (usize::MAX, ps.rows),
+			_ => (ps.upper_mark, ps.rows),
+		};
+		self.progress.scrolled_to(top.saturating_add(rows), rows);
 
-		// I tested a bunch of different values for this (ranging from 1 - 1000000) and
-		// 15 seemed to fare the best for large files (I was testing one with 600mb, like 5M
-		// lines of text iirc). With a smaller file (like 80mb, idk how many lines), a value
-		// of 36-50 seemed to fare better but it was already loading in at like 800ms, so
-		// I don't think we should be optimizing for that. And it's not worth my time to
-		// figure out a solution that changes based on the number of lines since the difference
-		// at this point is so small
-		let chunks = lines.chunks(CHUNK_SIZE);
-		let chunk_count = chunks.len();
-		let done = Arc::new(AtomicUsize::new(0));
+		input
+	}
+}
 
-		let lines_vec = vec![None; line_len];
-		let lines_mx: Arc<Mutex<Vec<Option<String>>>> = Arc::new(Mutex::new(lines_vec));
+// colorizes lines and pushes them to the pager a batch at a time, staying a few screens ahead of
+// the user
+struct Feeder {
+	pager: minus::Pager,
+	config: Arc<Config>,
+	// if there are any, each line is prefixed with the one for the file it came from
+	tags: Vec<String>,
+	filter: Option<LevelFilter<Line>>,
+	term: Option<Rule>,
+	match_rows: Arc<Mutex<Vec<usize>>>,
+	// the prompt without the count of matches
+	prompt: String,
+	progress: Arc<Progress>,
+}
 
-		let chunk_joins = chunks
-			.enumerate()
-			.map(|(idx, lns)| {
-				let line_clone = lines_mx.clone();
-				let done_clone = done.clone();
-				let joined = lns.join("\n");
+impl Feeder {
+	fn feed<I: Iterator<Item = Line>>(mut self, lines: I, quit: &AtomicBool) {
+		let mut batch = Batch::default();
+		// until the first match turns up, lines are held back rather than pushed, so that the
+		// pager opens on it
+		let mut lead = self.term.is_some().then(Lead::default);
 
-				tokio::spawn(async move {
-					let colored = colorize_line(&joined);
+		for line in lines {
+			if quit.load(Ordering::SeqCst) {
+				return;
+			}
 
-					if let Ok(mut lines_lock) = line_clone.lock() {
-						lines_lock[idx] = Some(colored);
+			let shown = match self.filter {
+				Some(ref mut filter) => filter.push(line),
+				None => vec![Some(line)],
+			};
+
+			for line in shown {
+				let (Some(held), Some(term)) = (&mut lead, &self.term) else {
+					self.add(&mut batch, line);
+					continue;
+				};
+
+				if let Some((skipped, context)) = held.push(line, term) {
+					if skipped > 0 {
+						batch.text.push_str(&format!(
+							"\x1b[2m··· {skipped} lines before the first match aren't shown ···\x1b[0m\n"
+						));
+						batch.rows += 1;
+						batch.pushed += 1;
 					}
 
-					// we spawn another task here 'cause we don't want to block up the completion
-					// of the colorization task with this computation (and the locking that comes
-					// along with it and could slow it down a lot)
-					tokio::spawn(async move {
-						done_clone.fetch_add(1, Ordering::SeqCst);
-						let done_now = done_clone.load(Ordering::SeqCst);
-
-						// If we have less chunks to iterate through than we have sections to print
-						// in the little loading indicator, we'll be printing something different
-						// every time that we get here, so we should continue.
-						if chunk_count >= LOADING_SECTIONS
-							&& done_now % (chunk_count / LOADING_SECTIONS) != 0
-						{
-							return;
-						}
-
-						// calculate percentages to print out a nice little loading thing
-						let perc = ((done_now as f64 / chunk_count as f64)
-							* LOADING_SECTIONS as f64) as usize;
-
-						// get the character in the middle that won't be completely empty or full
-						let last_char = LAST_CHARS[perc % SECTIONS];
-						let already = perc / SECTIONS;
-
-						// print out the progress bar, resetting the cursor and clearing the line
-						print!(
-							"\x1b[2K\rLoading... [{}{}{}]",
-							"█".repeat(already),
-							if idx == chunk_count - 1 {
-								""
-							} else {
-								last_char
-							},
-							" ".repeat((TERM_WIDTH - already).saturating_sub(1))
-						);
-
-						// flush stdout so that it actually goes to the screen
-						use std::io::Write;
-						let _ = std::io::stdout().flush();
-					});
-				})
-			})
-			.collect::<Vec<_>>();
+					for line in context {
+						self.add(&mut batch, line);
+					}
+					lead = None;
+				}
+			}
 
-		futures::future::join_all(chunk_joins).await;
-
-		let file_contents = Arc::try_unwrap(lines_mx)
-			.expect("lines_mx was passed to a buffer that never completed")
-			.into_inner()
-			.expect("Could not get inner value from Mutex lines_mx")
-			.into_iter()
-			.flatten()
-			.collect::<Vec<String>>()
-			.join("\n");
-
-		// so that we can get a pretty newline after printing the colorize loading bar
-		println!();
-
-		let pager = minus::Pager::new();
-
-		pager
-			.set_text(file_contents)
-			.map_err(|_| FilterErrors::ViewPagingFailed)?;
-		pager
-			.set_line_numbers(minus::LineNumbers::Disabled)
-			.map_err(|_| FilterErrors::ViewPagingFailed)?;
-
-		// set a nice prompt with all the details that we want them to see
-		let prompt_str = format!(
-			"{}/{} ({}; {})",
-			entry.date_time(),
-			log,
-			entry.user_id.unwrap_or_else(|| "unknown".to_owned()),
-			entry.reason.unwrap_or_else(|| "unknown".to_owned())
-		);
-		pager
-			.set_prompt(prompt_str)
-			.map_err(|_| FilterErrors::ViewPagingFailed)?;
+			if batch.count >= BATCH_LINES {
+				if !self.push(std::mem::take(&mut batch.text), &mut batch.matches, false) {
+					return;
+				}
 
-		minus::page_all(pager).map_err(|_| FilterErrors::ViewPagingFailed)?;
+				batch.pushed += batch.count;
+				batch.count = 0;
+				self.progress.wait(batch.pushed);
+			}
+		}
+
+		// there was nothing to open on, so they get to see all of it
+		for line in lead.map(|lead| lead.held).unwrap_or_default() {
+			self.add(&mut batch, line);
+		}
+
+		self.push(batch.text, &mut batch.matches, true);
 	}
 
-	Ok(())
+	// colorizes the line and adds it to the batch, or a separator if it's `None`
+	fn add(&self, batch: &mut Batch, line: Option<Line>) {
+		let Some(line) = line else {
+			batch.text.push_str(SEPARATOR);
+			batch.rows += 1;
+			batch.pushed += 1;
+			return;
+		};
+
+		let start = batch.text.len();
+
+		if let Some(tag) = self.tags.get(line.source) {
+			batch.text.push_str(tag);
+		}
+
+		batch.text.push_str(&color_line(
+			&self.config.highlight,
+			&line.text,
+			self.term.as_ref(),
+		));
+
+		if let Some(ref term) = self.term {
+			if term.regex.is_match(&line.text) {
+				batch.matches.push(batch.rows);
+			}
+
+			let shown = batch.text[start..].trim_end_matches('\n');
+			batch.rows += textwrap::wrap(shown, batch.cols).len().max(1);
+		}
+
+		batch.count += 1;
+	}
+
+	// pushes the batch to the pager, along with where the matches in it are. Returns false if
+	// the pager's gone
+	fn push(&self, batch: String, matches: &mut Vec<usize>, done: bool) -> bool {
+		if !batch.is_empty() && self.pager.push_str(batch).is_err() {
+			return false;
+		}
+
+		if self.term.is_none() {
+			return true;
+		}
+
+		let mut rows = self
+			.match_rows
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		rows.append(matches);
+
+		let count = match rows.len() {
+			1 => "1 match".to_owned(),
+			n => format!("{n} matches"),
+		};
+		let more = if done {
+			""
+		} else {
+			" so far"
+		};
+
+		self.pager
+			.set_prompt(format!("{} [{count}{more}; n/p to jump]", self.prompt))
+			.is_ok()
+	}
+}
+
+// the lines the feeder has colorized but not yet pushed, and where the pager is at
+struct Batch {
+	text: String,
+	// how many lines are in `text`
+	count: usize,
+	// the pager wraps long lines, so to know which row a match will be on, we have to wrap
+	// them the same way it does. This uses the width of the terminal when the file was
+	// opened, so the jumps may be a bit off if it's resized
+	cols: usize,
+	rows: usize,
+	// the rows of the matches in `text`
+	matches: Vec<usize>,
+	// how many lines (including separators) the pager has been given
+	pushed: usize,
+}
+
+impl Default for Batch {
+	fn default() -> Batch {
+		Batch {
+			text: String::new(),
+			count: 0,
+			cols: crossterm::terminal::size().map_or(80, |(cols, _)| cols as usize),
+			rows: 0,
+			matches: Vec::new(),
+			pushed: 0,
+		}
+	}
+}
+
+// the lines before the first match of the term. minus always opens at the top and can't be told
+// to scroll anywhere else, so the only way to open on the first match is to leave out most of
+// what comes before it
+#[derive(Default)]
+struct Lead {
+	held: Vec<Option<Line>>,
+}
+
+impl Lead {
+	// holds on to the line, unless it's the first match. Then it gives back how many of the lines
+	// before it to leave out, and the rest of them (ending with the match) to show
+	fn push(&mut self, line: Option<Line>, term: &Rule) -> Option<(usize, Vec<Option<Line>>)> {
+		let found = line.as_ref().is_some_and(|l| term.regex.is_match(&l.text));
+		self.held.push(line);
+
+		if !found {
+			return None;
+		}
+
+		let skipped = self.held.len().saturating_sub(LEAD_CONTEXT + 1);
+		Some((skipped, self.held.split_off(skipped)))
+	}
 }
 
-fn colorize_line(line: &str) -> String {
-	// ya know, I wish there was a better/faster way of doing this. But I simply don't know what.
-	let res = NUM_REGEX.replace_all(line, NUM_REP_STR);
-	let res = NS_REGEX.replace_all(&res, NS_REP_STR);
-	let res = FN_REGEX.replace_all(&res, FN_REP_STR);
-	let res = NULL_REGEX.replace_all(&res, NULL_REP_STR);
-	let res = HEX_REGEX.replace_all(&res, HEX_REP_STR);
-	let res = URL_REGEX.replace_all(&res, URL_REP_STR);
-	let res = ROOM_REGEX.replace_all(&res, ROOM_REP_STR);
-	let res = USER_REGEX.replace_all(&res, USER_REP_STR);
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{thread, time::Duration};
 
-	res.to_string()
+	fn wait_in_background(progress: &Arc<Progress>, pushed: usize) -> thread::JoinHandle<()> {
+		let progress = progress.clone();
+		let waiting = thread::spawn(move || progress.wait(pushed));
+
+		thread::sleep(Duration::from_millis(50));
+		waiting
+	}
+
+	#[test]
+	fn feeder_waits_until_the_user_scrolls_near_the_end() {
+		// a screen of 10 rows, plus 5 more screens, is 60 lines
+		let progress = Arc::new(Progress::new(10));
+		progress.wait(59);
+
+		let waiting = wait_in_background(&progress, 100);
+		assert!(!waiting.is_finished());
+
+		// that only wants 80 lines
+		progress.scrolled_to(30, 10);
+		thread::sleep(Duration::from_millis(50));
+		assert!(!waiting.is_finished());
+
+		// and that wants 110
+		progress.scrolled_to(60, 10);
+		waiting.join().unwrap();
+	}
+
+	#[test]
+	fn closing_the_pager_releases_the_feeder() {
+		let progress = Arc::new(Progress::new(10));
+
+		let waiting = wait_in_background(&progress, 1000);
+		assert!(!waiting.is_finished());
+
+		progress.release();
+		waiting.join().unwrap();
+	}
+
+	#[test]
+	fn leaves_out_what_comes_before_the_first_match() {
+		let term = Rule::new("term", "needle", "reverse bold", i64::MAX).unwrap();
+		let line = |text: &str| {
+			Some(Line {
+				source: 0,
+				text: text.to_owned(),
+			})
+		};
+		let texts = |lines: Vec<Option<Line>>| {
+			lines
+				.into_iter()
+				.map(|l| l.map_or("--".to_owned(), |l| l.text))
+				.collect::<Vec<_>>()
+		};
+
+		let mut lead = Lead::default();
+		for n in 0..10 {
+			assert!(lead.push(line(&n.to_string()), &term).is_none());
+		}
+		assert!(lead.push(None, &term).is_none());
+
+		let (skipped, shown) = lead.push(line("a needle"), &term).unwrap();
+		assert_eq!(skipped, 8);
+		assert_eq!(texts(shown), ["8", "9", "--", "a needle"]);
+
+		// a match right at the start has nothing before it to leave out
+		let mut lead = Lead::default();
+		let (skipped, shown) = lead.push(line("needle"), &term).unwrap();
+		assert_eq!(skipped, 0);
+		assert_eq!(texts(shown), ["needle"]);
+	}
 }
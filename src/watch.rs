@@ -0,0 +1,373 @@
+use crate::{
+	config::Config,
+	entry::Entry,
+	errors::SyncErrors,
+	filter::Filter,
+	sync::{download_files, Download, SyncTracker},
+	*,
+};
+use futures::StreamExt;
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+// we never wait longer than this between polls, no matter how many times in a row they failed
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 30);
+
+// what we found out about a single new entry during a poll
+enum Check {
+	// the entry didn't match the filter, so we don't care about it
+	Rejected,
+	// all of the entry's files are already on the device, so a sync got to it before we did
+	Synced,
+	// the entry matched, and these of its files still need to be downloaded
+	Missing(Entry, Vec<String>),
+	// we couldn't tell if the entry matched, or what files it has
+	Failed,
+}
+
+// everything we remember between polls, so that each poll only has to look at what's new
+#[derive(Default)]
+struct WatchState {
+	// the most recent day we've seen on the server
+	last_day: Option<String>,
+	// the entries (`day/time`) that we're completely done with
+	seen: HashSet<String>,
+	// the entries that had no logs on the device when we first found them and haven't been fully
+	// downloaded yet, which are the only ones that count as new once they are
+	fresh: HashSet<String>,
+	// the results of `Filter::entry_ok` for every entry we've checked, so that entries which
+	// failed to download don't need their details re-checked on the next poll
+	verdicts: HashMap<String, bool>,
+}
+
+pub async fn watch(filter: Arc<Filter>, conf: Arc<Config>, interval: Duration) {
+	let mut state = WatchState::default();
+	let mut delay = interval;
+
+	println!(
+		"\n👀 Watching for new entries every {}s (press Ctrl-C to stop)",
+		interval.as_secs()
+	);
+
+	loop {
+		match poll(&filter, &conf, &mut state).await {
+			Ok(()) => delay = interval,
+			Err(err) => {
+				// back off a bit so we don't hammer a server that's having trouble
+				delay = (delay * 2).min(MAX_BACKOFF).max(interval);
+				warn!("{err}; trying again in {}s", delay.as_secs());
+			}
+		}
+
+		tokio::time::sleep(delay).await;
+	}
+}
+
+async fn poll(
+	filter: &Arc<Filter>,
+	conf: &Arc<Config>,
+	state: &mut WatchState,
+) -> Result<(), String> {
+	let list_url = format!("{}/api/listing/", conf.server);
+
+	let days_text = req_with_auth(&list_url, conf)
+		.await
+		.map_err(|err| format!("Couldn't get list of days from server: {err}"))?
+		.text()
+		.await
+		.map_err(|err| format!("Server's list of days contains unparseable text: {err}"))?;
+
+	let mut days = get_links(&days_text)
+		.into_iter()
+		.map(|d| d.replace('/', ""))
+		.filter(|d| filter.day_ok(d))
+		.collect::<Vec<_>>();
+
+	days.sort();
+
+	// we only need to look at the last day we saw (since new times may have shown up in it) and
+	// anything after that. If this is the first poll, the regular sync just finished, so only
+	// the most recent day could have anything new in it.
+	let days = match state.last_day {
+		Some(ref last) => days.into_iter().filter(|d| d >= last).collect::<Vec<_>>(),
+		None => days.pop().into_iter().collect(),
+	};
+
+	let mut new_times = Vec::new();
+
+	for day in &days {
+		let times_text = req_with_auth(&format!("{list_url}{day}"), conf)
+			.await
+			.map_err(|err| format!("Could not get list of times of day {day}: {err}"))?
+			.text()
+			.await
+			.map_err(|err| format!("Could not get text for list of times of day {day}: {err}"))?;
+
+		for entry in get_links(&times_text)
+			.into_iter()
+			.map(|t| Entry::new(day, t, conf.clone()))
+		{
+			if !state.seen.contains(&entry.date_time()) {
+				new_times.push(entry);
+			}
+		}
+	}
+
+	if let Some(last) = days.into_iter().last() {
+		state.last_day = Some(last);
+	}
+
+	let checks = futures::stream::iter(new_times.into_iter().map(|entry| {
+		let cached = state.verdicts.get(&entry.date_time()).copied();
+		check(entry, cached, filter.clone())
+	}))
+	.buffer_unordered(conf.threads)
+	.collect::<Vec<_>>()
+	.await;
+
+	let mut failed = 0;
+	let mut missing = Vec::new();
+
+	for (date_time, verdict, check) in checks {
+		if let Some(verdict) = verdict {
+			state.verdicts.insert(date_time.clone(), verdict);
+		}
+
+		match check {
+			Check::Rejected | Check::Synced => {
+				state.seen.insert(date_time);
+			}
+			Check::Missing(entry, files) => {
+				if !entry.has_logs() {
+					state.fresh.insert(date_time);
+				}
+				missing.push((entry, files));
+			}
+			Check::Failed => failed += 1,
+		}
+	}
+
+	let (downloaded, failed_downloads) = download(missing, conf).await;
+	failed += failed_downloads;
+
+	let new_entries = downloaded
+		.into_iter()
+		.filter_map(|entry| {
+			println!(
+				"[{}] \x1b[32;1m+\x1b[0m {}",
+				chrono::Local::now().format("%H:%M:%S"),
+				entry.selectable_description()
+			);
+
+			let date_time = entry.date_time();
+			state.seen.insert(date_time.clone());
+			state.fresh.remove(&date_time).then_some(date_time)
+		})
+		.collect::<Vec<_>>();
+
+	// each poll that got something new counts as its own sync as far as the hooks care
+	if !new_entries.is_empty() {
+		hooks::on_new_entries(conf, &new_entries).await;
+		hooks::on_sync_complete(conf, &new_entries, failed == 0).await;
+	}
+
+	if failed > 0 {
+		return Err(format!(
+			"{failed} new entries could not be fully downloaded"
+		));
+	}
+
+	Ok(())
+}
+
+// gets the entry's files from the server and checks if the entry fits the filter (unless we
+// already know). returns the entry's `day/time`, the verdict of the filter (if we got one), and
+// what still needs to be done with it
+async fn check(
+	mut entry: Entry,
+	cached: Option<bool>,
+	filter: Arc<Filter>,
+) -> (String, Option<bool>, Check) {
+	let date_time = entry.date_time();
+
+	if let Err(err) = entry.retrieve_file_list(true).await {
+		err!("Couldn't get the list of files in {date_time}: {err}");
+		return (date_time, cached, Check::Failed);
+	}
+
+	let dir = sync_dir().join(&date_time);
+	let missing = entry
+		.files
+		.iter()
+		.flatten()
+		.filter(|f| !dir.join(f).exists())
+		.cloned()
+		.collect::<Vec<_>>();
+
+	// a sync may have only gotten partway through it, or only cached its details, so we can only
+	// skip it if every file the server has for it is here
+	if missing.is_empty() && entry.has_logs() {
+		return (date_time, cached, Check::Synced);
+	}
+
+	let verdict = match cached {
+		Some(verdict) => verdict,
+		None => match filter.entry_ok(&mut entry, true).await {
+			Ok(verdict) => verdict,
+			Err(err) => {
+				err!("Failed to check entry {date_time}: {err}");
+				return (date_time, None, Check::Failed);
+			}
+		},
+	};
+
+	let check = if verdict {
+		Check::Missing(entry, missing)
+	} else {
+		Check::Rejected
+	};
+
+	(date_time, Some(verdict), check)
+}
+
+// downloads the missing files of all the entries, returning the entries that are now complete and
+// how many of them aren't
+async fn download(missing: Vec<(Entry, Vec<String>)>, conf: &Arc<Config>) -> (Vec<Entry>, usize) {
+	if missing.is_empty() {
+		return (Vec::new(), 0);
+	}
+
+	let state = Arc::new(Mutex::new(SyncTracker {
+		prefix: "Downloading files:".to_owned(),
+		started: 0,
+		done: 0,
+		total: 0,
+		completed: Vec::new(),
+	}));
+
+	let mut entries = Vec::new();
+	let mut downloads = Vec::new();
+	let mut failed = 0;
+
+	for (entry, files) in missing {
+		let date_time = entry.date_time();
+
+		if let Err(err) = fs::create_dir_all(sync_dir().join(&date_time)) {
+			err!("Couldn't create the directory for {date_time}: {err}");
+			failed += 1;
+			continue;
+		}
+
+		// which of these are new is kept track of across polls in `WatchState::fresh` instead
+		downloads.extend(files.into_iter().map(|f| Download {
+			subdir: format!("{date_time}/{f}"),
+			is_cache: false,
+			new_entry: false,
+			state: state.clone(),
+			config: conf.clone(),
+		}));
+		entries.push(entry);
+	}
+
+	let failed_files = match download_files(downloads, &state, conf).await {
+		Ok(()) => Vec::new(),
+		Err(SyncErrors::FilesDownloadFailed(files)) => files,
+		Err(err) => {
+			err!("Couldn't download the new entries: {err}");
+			return (Vec::new(), failed + entries.len());
+		}
+	};
+
+	let (mut complete, incomplete): (Vec<_>, Vec<_>) = entries.into_iter().partition(|e| {
+		let date_time = e.date_time();
+		!failed_files.iter().any(|f| {
+			f.subdir
+				.rsplit_once('/')
+				.is_some_and(|(d, _)| d == date_time)
+		})
+	});
+
+	for entry in &mut complete {
+		// the details file is on the device now, so this won't hit the network
+		if !entry.checked_details {
+			let _ = entry.set_download_values().await;
+		}
+	}
+
+	(complete, failed + incomplete.len())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{MockServer, TestEnv};
+
+	const DETAILS: &str = "crashed\nApplication: riot-android\nuser_id: @bob:example.org\n";
+
+	#[tokio::test]
+	async fn downloads_entries_that_a_sync_did_not_finish() {
+		let server = MockServer::start().await;
+		let hooks = tempfile::tempdir().unwrap();
+		let ran = hooks.path().join("ran");
+		let env = TestEnv::new(
+			&server,
+			&format!("on-new-entry = \"echo $RAGER_TIME >> '{}'\"", ran.display()),
+		);
+
+		for time in ["161300", "170000", "180000"] {
+			server.add_entry(
+				&format!("2021-07-08/{time}"),
+				&[
+					"crashed",
+					"Application: riot-android",
+					"user_id: @bob:example.org",
+				],
+				&[
+					("logs-0000.log", "first log"),
+					("logs-0001.log", "second log"),
+				],
+			);
+		}
+
+		// one that a sync finished, one that it only got partway through, and one that it only
+		// cached the details of
+		for file in [crate::DETAILS, "logs-0000.log", "logs-0001.log"] {
+			env.add_local_file(&format!("2021-07-08/161300/{file}"), DETAILS);
+		}
+		for file in [crate::DETAILS, "logs-0000.log"] {
+			env.add_local_file(&format!("2021-07-08/170000/{file}"), DETAILS);
+		}
+		env.add_local_file(&format!("2021-07-08/180000/{}", crate::DETAILS), DETAILS);
+
+		let mut state = WatchState::default();
+		poll(&Arc::default(), &env.config, &mut state)
+			.await
+			.expect("Poll failed");
+
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-08/170000/logs-0001.log")).unwrap(),
+			"second log"
+		);
+		assert_eq!(
+			fs::read_to_string(env.store_path("2021-07-08/180000/logs-0000.log")).unwrap(),
+			"first log"
+		);
+
+		// only the one that was just details before is new
+		assert_eq!(fs::read_to_string(&ran).unwrap(), "180000\n");
+		assert!(state.fresh.is_empty());
+
+		// the finished one is never checked against the filter, since it isn't new
+		assert_eq!(state.seen.len(), 3);
+		let mut checked = state.verdicts.keys().collect::<Vec<_>>();
+		checked.sort();
+		assert_eq!(checked, ["2021-07-08/170000", "2021-07-08/180000"]);
+
+		let requests = server.requests();
+		assert!(!requests.iter().any(|r| r.starts_with("2021-07-08/161300/")));
+	}
+}